@@ -1,52 +1,128 @@
 /*! Implements a minimal database with the following features:
 * Relation and view instances are generic over [`Tuple`] types.
-* Supports incremental view update by keeping track of recently added tuples.
-* Relation instances monotonically grow (supports insertion but not deletion).
+* Supports incremental view update by keeping track of recently added and removed tuples.
+* Relation instances support insertion and deletion of tuples, which can be grouped in transactions.
+* Batches of tuples are reference-counted, so snapshots of databases share their tuples.
+* The history of relations can be tracked to evaluate expressions at earlier epochs, or to
+  evaluate only the tuples that are added to their results since a cursor.
+* Views deliver the tuples that are added to them to their subscribers.
+* Relations of dynamically typed rows can be added with schemas that are decided at runtime.
+* Relation instances can be indexed by keys that are reused by joins and lookups.
+* Relation instances can be constrained by keys and by inclusion dependencies on other relations.
+* Expressions can be explained with the statistics of the instances they refer to, and
+  analyzed with the measurements of evaluating their sub-expressions.
+* With the `sync` feature, databases are `Send + Sync` and can be shared between threads.
+* With the `parallel` feature, joins and products are evaluated in parallel.
+* With the `serde` feature, relation instances can be exported to and imported from JSON and bincode.
+* With the `storage` feature, relation instances can be saved to and loaded from disk.
 
 [`Database`]: ../trait.Tuple.html
 */
+mod analyze;
+mod constraint;
 mod evaluate;
+mod explain;
 mod expression_ext;
 mod helpers;
+mod history;
 mod instance;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "storage")]
+mod storage;
+mod subscription;
+mod transaction;
 mod validate;
+#[cfg(feature = "storage")]
+mod wal;
 
 use crate::{
-    expression::{dependency, view::ViewRef, Expression, IntoExpression, Relation, View},
-    Error, Tuple,
+    expression::{
+        dependency, index::IndexRef, view::ViewRef, Closure, Expression, Index, IntoExpression,
+        Mono, Relation, UnaryFn, View,
+    },
+    Error, Row, RowSchema, Tuple,
 };
+pub use analyze::Analysis;
+pub use explain::{Plan, Statistics};
 use expression_ext::ExpressionExt;
 pub use instance::Tuples;
+#[cfg(feature = "serde")]
+pub use serialize::Schema;
 use std::{
-    cell::Cell,
-    collections::{HashMap, HashSet},
+    any::Any,
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
 };
+pub use transaction::Transaction;
 
-use instance::{DynInstance, Instance};
+use constraint::{DynInclusion, Inclusion, Pending, RelationChange};
+pub use history::Cursor;
+use history::DynHistory;
+use instance::{DynIndexInstance, DynInstance, IndexInstance, Instance};
+use subscription::{DynNotification, DynSubscriber, Subscriber};
 
 /// Contains the information about an instance in the database.
 struct RelationEntry {
     /// Is the `Instance` containing the tuples of this relation.
     instance: Box<dyn DynInstance>,
 
+    /// Contains the indexes on this relation, identified by their position.
+    indexes: Vec<Box<dyn DynIndexInstance>>,
+
+    /// Maps the names of the arrangements on this relation to the position of their
+    /// indexes.
+    arrangements: HashMap<String, usize>,
+
+    /// Is the position of the index whose keys identify the tuples of this relation if it
+    /// is added with a key.
+    key: Option<usize>,
+
+    /// Contains the inclusion dependencies of this relation on other relations.
+    inclusions: Vec<Box<dyn DynInclusion>>,
+
+    /// Contains the names of the relations with inclusion dependencies on this relation.
+    referencing: Vec<String>,
+
+    /// Is the history of the changes to this relation if its history is tracked.
+    history: Option<Box<dyn DynHistory>>,
+
+    /// Is the schema of the rows of this relation if it is added with a schema.
+    schema: Option<RowSchema>,
+
     /// Contains references to the views that this relation appears in their
     /// expression. These are the views that depend on the content of this relation.
     dependent_views: HashSet<ViewRef>,
 
     /// A flag that indicating if this relation is being stabilized.
-    stabilizing: Cell<bool>,
+    stabilizing: AtomicBool,
 }
 
 impl RelationEntry {
-    /// Creates a new `RelationEntry` with the given `instance`.
+    /// Creates a new `RelationEntry` with an empty instance of tuples of type `T`.
     fn new<T>() -> Self
     where
         T: Tuple + 'static,
     {
+        Self::with_instance(Box::new(Instance::<T>::new()))
+    }
+
+    /// Creates a new `RelationEntry` with the given `instance`.
+    fn with_instance(instance: Box<dyn DynInstance>) -> Self {
         Self {
-            instance: Box::new(Instance::<T>::new()),
+            instance,
+            indexes: Vec::new(),
+            arrangements: HashMap::new(),
+            key: None,
+            inclusions: Vec::new(),
+            referencing: Vec::new(),
+            history: None,
+            schema: None,
             dependent_views: HashSet::new(),
-            stabilizing: Cell::new(false),
+            stabilizing: AtomicBool::new(false),
         }
     }
 
@@ -54,14 +130,30 @@ impl RelationEntry {
     fn add_dependent_view(&mut self, view_ref: ViewRef) {
         self.dependent_views.insert(view_ref);
     }
+
+    /// Applies `changed()` on the instance of this relation and its indexes, returning
+    /// true if the instance has been affected by last updates.
+    fn changed(&self) -> bool {
+        for index in self.indexes.iter() {
+            index.instance().changed();
+        }
+        self.instance.changed()
+    }
 }
 
 impl Clone for RelationEntry {
     fn clone(&self) -> Self {
         Self {
             instance: self.instance.clone_box(),
+            indexes: self.indexes.iter().map(|i| i.clone_box()).collect(),
+            arrangements: self.arrangements.clone(),
+            key: self.key,
+            inclusions: self.inclusions.iter().map(|i| i.clone_box()).collect(),
+            referencing: self.referencing.clone(),
+            history: self.history.as_ref().map(|h| h.clone_box()),
+            schema: self.schema.clone(),
             dependent_views: self.dependent_views.clone(),
-            stabilizing: self.stabilizing.clone(),
+            stabilizing: AtomicBool::new(self.stabilizing.load(Ordering::SeqCst)),
         }
     }
 }
@@ -88,23 +180,23 @@ struct ViewEntry {
     /// content of this view.
     dependent_views: HashSet<ViewRef>,
 
+    /// Contains the subscribers that receive the tuples that are added to this view.
+    subscribers: Vec<Box<dyn DynSubscriber>>,
+
     /// A flag that indicating if this view is being stabilized.
-    stabilizing: Cell<bool>,
+    stabilizing: AtomicBool,
 }
 
 impl ViewEntry {
     /// Creates a new `ViewEntry` with the given `view_instance`.
-    fn new<T, E>(view_instance: ViewInstance<T, E>) -> Self
-    where
-        T: Tuple + 'static,
-        E: ExpressionExt<T> + 'static,
-    {
+    fn new(view_instance: Box<dyn DynViewInstance>) -> Self {
         Self {
-            instance: Box::new(view_instance),
+            instance: view_instance,
             dependee_relations: HashSet::new(),
             dependee_views: HashSet::new(),
             dependent_views: HashSet::new(),
-            stabilizing: Cell::new(false),
+            subscribers: Vec::new(),
+            stabilizing: AtomicBool::new(false),
         }
     }
 
@@ -121,7 +213,9 @@ impl Clone for ViewEntry {
             dependee_views: self.dependee_views.clone(),
             dependee_relations: self.dependee_relations.clone(),
             dependent_views: self.dependent_views.clone(),
-            stabilizing: self.stabilizing.clone(),
+            // the subscribers of a view are not notified of the changes to its clones:
+            subscribers: Vec::new(),
+            stabilizing: AtomicBool::new(self.stabilizing.load(Ordering::SeqCst)),
         }
     }
 }
@@ -160,11 +254,38 @@ impl Clone for ViewEntry {
 ///
 /// assert_eq!(vec![4, 8, 15, 16, 23, 42, 101, 888, 1001, 8008], numbers_data.into_tuples());
 /// assert_eq!(vec![15, 23, 101, 1001], odds_data.into_tuples());
+///
+/// // remove some items from `numbers`:
+/// db.remove(&numbers, vec![15, 16, 8008].into()).unwrap();
+///
+/// // views reflect the removal:
+/// let odds_data = db.evaluate(&odds).unwrap();
+/// assert_eq!(vec![23, 101, 1001], odds_data.into_tuples());
 /// ```
 pub struct Database {
     relations: HashMap<String, RelationEntry>,
     views: HashMap<ViewRef, ViewEntry>,
     view_counter: i32,
+
+    /// Is the number of times that the relations of the database are stabilized with
+    /// changes.
+    epoch: AtomicU64,
+
+    /// Serializes the operations that update the instances of a database that is
+    /// shared between threads.
+    #[cfg(feature = "sync")]
+    lock: std::sync::Mutex<()>,
+
+    /// Contains the notifications of subscribers that are waiting to be delivered when
+    /// the operation that stabilizes their views is finished.
+    notifications: Mutex<VecDeque<Box<dyn DynNotification>>>,
+
+    /// Is true while the notifications of subscribers are being delivered.
+    delivering: AtomicBool,
+
+    /// Is the write-ahead log of a database that is opened by `Database::open`.
+    #[cfg(feature = "storage")]
+    log: Option<wal::Log>,
 }
 
 impl Database {
@@ -174,6 +295,13 @@ impl Database {
             relations: HashMap::new(),
             views: HashMap::new(),
             view_counter: 0,
+            epoch: AtomicU64::new(0),
+            #[cfg(feature = "sync")]
+            lock: std::sync::Mutex::new(()),
+            notifications: Mutex::new(VecDeque::new()),
+            delivering: AtomicBool::new(false),
+            #[cfg(feature = "storage")]
+            log: None,
         }
     }
 
@@ -183,9 +311,92 @@ impl Database {
         T: Tuple,
         E: ExpressionExt<T>,
     {
+        let _guard = self.lock();
         expression.collect_recent(&evaluate::Evaluator::new(self))
     }
 
+    /// Evaluates `expression` in the database like [`Database::evaluate`] and returns the
+    /// result alongside an [`Analysis`] of the evaluation. The analysis is a tree with the
+    /// measurements of every sub-expression of `expression` under a root `"Evaluate"` node,
+    /// which includes the time of stabilizing the instances that `expression` depends on.
+    ///
+    /// **Example**:
+    /// ```rust
+    /// use codd::{Database, expression::Select};
+    ///
+    /// let mut db = Database::new();
+    /// let fruit = db.add_relation::<String>("fruit").unwrap();
+    /// db.insert(&fruit, vec!["Apple".to_string(), "Banana".to_string()].into()).unwrap();
+    ///
+    /// let select = Select::new(&fruit, |t| t.contains('A'));
+    /// let (result, analysis) = db.analyze(&select).unwrap();
+    /// assert_eq!(vec!["Apple".to_string()], result.into_tuples());
+    ///
+    /// let select = &analysis.children()[0];
+    /// assert_eq!("Select", select.operator());
+    /// assert_eq!(2, select.input());
+    /// assert_eq!(1, select.output());
+    /// ```
+    ///
+    /// [`Database::evaluate`]: ./struct.Database.html#method.evaluate
+    /// [`Analysis`]: ./struct.Analysis.html
+    pub fn analyze<T, E>(&self, expression: &E) -> Result<(Tuples<T>, Analysis), Error>
+    where
+        T: Tuple,
+        E: ExpressionExt<T>,
+    {
+        let _guard = self.lock();
+        let profiler = analyze::Profiler::new();
+        let evaluator = evaluate::Evaluator::with_profiler(self, &profiler);
+        let result = profiler.record("Evaluate", expression, || {
+            expression.collect_recent(&evaluator)
+        })?;
+        let analysis = profiler
+            .into_analysis()
+            .expect("expecting the analysis of the expression");
+        Ok((result, analysis))
+    }
+
+    /// Returns a [`Plan`] that describes how `expression` is evaluated in the database. The
+    /// plan is a tree of the operators of `expression`, where the relations, indexes and
+    /// views that are referred to by `expression` come with the [`Statistics`] of their
+    /// instances.
+    ///
+    /// **Note**: explaining an expression doesn't stabilize the instances of the database;
+    /// the statistics reflect the batches of the instances before `expression` is evaluated.
+    ///
+    /// **Example**:
+    /// ```rust
+    /// use codd::{Database, expression::Select};
+    ///
+    /// let mut db = Database::new();
+    /// let fruit = db.add_relation::<String>("fruit").unwrap();
+    /// db.insert(&fruit, vec!["Apple".to_string(), "Banana".to_string()].into()).unwrap();
+    ///
+    /// let select = Select::new(&fruit, |t| t.contains('A'));
+    /// let plan = db.explain(&select).unwrap();
+    ///
+    /// assert_eq!("Select", plan.operator());
+    /// assert_eq!(&[2], plan.children()[0].statistics().unwrap().to_add());
+    /// assert_eq!(
+    ///     "Select\n  Relation `fruit` (stable: 0 batches / 0 tuples, recent: 0 tuples, to add: 1 batches / 2 tuples)\n",
+    ///     plan.to_string()
+    /// );
+    /// ```
+    ///
+    /// [`Plan`]: ./struct.Plan.html
+    /// [`Statistics`]: ./struct.Statistics.html
+    pub fn explain<T, E>(&self, expression: &E) -> Result<Plan, Error>
+    where
+        T: Tuple,
+        E: Expression<T>,
+    {
+        let _guard = self.lock();
+        let mut visitor = explain::ExplainVisitor::new(self);
+        expression.visit(&mut visitor);
+        visitor.into_plan()
+    }
+
     /// Adds a new relation instance identified by `name` to the database and returns a
     /// [`Relation`] object that can be used to access the instance.
     ///
@@ -203,16 +414,849 @@ impl Database {
         }
     }
 
-    /// Inserts tuples in the `Instance` corresponding to `relation`.
+    /// Adds a new relation instance identified by `name` to the database, where `key` is a
+    /// closure that returns the key of the tuples in the relation, and returns a
+    /// [`Relation`] object that can be used to access the instance. Inserting tuples that
+    /// have the same key as a different tuple, in the same batch or in the relation, fails
+    /// with [`Error::ConstraintViolation`] and leaves the relation unchanged.
+    ///
+    /// **Note**: key constraints are not saved with the `storage` feature.
+    ///
+    /// **Example**:
+    /// ```rust
+    /// use codd::{Database, Error};
+    ///
+    /// let mut db = Database::new();
+    /// let band = db
+    ///     .add_relation_with_key::<String, (String, i32)>("band", |b| b.0.clone())
+    ///     .unwrap();
+    ///
+    /// db.insert(&band, vec![("Beatles".to_string(), 1960)].into()).unwrap();
+    /// assert!(matches!(
+    ///     db.insert(&band, vec![("Beatles".to_string(), 1962)].into()),
+    ///     Err(Error::ConstraintViolation { .. })
+    /// ));
+    /// assert_eq!(1, db.evaluate(&band).unwrap().len());
+    /// ```
+    ///
+    /// [`Relation`]: ./expression/struct.Relation.html
+    /// [`Error::ConstraintViolation`]: ./enum.Error.html#variant.ConstraintViolation
+    pub fn add_relation_with_key<K, T>(
+        &mut self,
+        name: &str,
+        key: impl UnaryFn<T, K> + 'static,
+    ) -> Result<Relation<T>, Error>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static,
+    {
+        let relation = self.add_relation(name)?;
+        self.add_index(&relation, key)?;
+        self.relations.get_mut(name).unwrap().key = Some(0);
+        Ok(relation)
+    }
+
+    /// Adds a new relation instance identified by `name` with [`Row`]s that are described
+    /// by `schema` to the database and returns a [`Relation`] object that can be used to
+    /// access the instance. Because the tuples of the relation are rows, its columns can be
+    /// decided at runtime. If `schema` has key columns, the values of the key columns
+    /// identify the rows of the relation (see [`Database::add_relation_with_key`]).
+    ///
+    /// **Example**:
+    /// ```rust
+    /// use codd::{Database, expression::Select, RowSchema, Value, ValueType};
+    ///
+    /// let mut db = Database::new();
+    /// let schema = RowSchema::new()
+    ///     .with_column("name", ValueType::Str)
+    ///     .with_column("price", ValueType::Float);
+    /// let items = db.add_relation_with_schema("items", schema).unwrap();
+    ///
+    /// db.insert(&items, vec![
+    ///     vec![Value::from("apple"), Value::from(0.5)],
+    ///     vec![Value::from("melon"), Value::from(3.25)],
+    ///     vec![Value::from("lemon"), Value::Null],
+    /// ].into()).unwrap();
+    ///
+    /// let price = db.relation_schema(&items).unwrap().position("price").unwrap();
+    /// let cheap = Select::new(&items, move |row| row[price] < Value::from(1.0));
+    /// assert_eq!(
+    ///     vec![
+    ///         vec![Value::from("apple"), Value::from(0.5)],
+    ///         vec![Value::from("lemon"), Value::Null],
+    ///     ],
+    ///     db.evaluate(&cheap).unwrap().into_tuples()
+    /// );
+    /// ```
+    ///
+    /// [`Row`]: ./type.Row.html
+    /// [`Relation`]: ./expression/struct.Relation.html
+    /// [`Database::add_relation_with_key`]: ./struct.Database.html#method.add_relation_with_key
+    pub fn add_relation_with_schema(
+        &mut self,
+        name: &str,
+        schema: RowSchema,
+    ) -> Result<Relation<Row>, Error> {
+        let relation = if schema.keys().is_empty() {
+            self.add_relation(name)?
+        } else {
+            let keys = schema.keys().to_vec();
+            self.add_relation_with_key(name, move |row: &Row| -> Row {
+                keys.iter().map(|k| row[*k].clone()).collect()
+            })?
+        };
+        self.relations.get_mut(name).unwrap().schema = Some(schema);
+        Ok(relation)
+    }
+
+    /// Returns the schema of `relation` if it is added by [`Database::add_relation_with_schema`].
+    ///
+    /// [`Database::add_relation_with_schema`]: ./struct.Database.html#method.add_relation_with_schema
+    pub fn relation_schema(&self, relation: &Relation<Row>) -> Option<&RowSchema> {
+        self.relations
+            .get(relation.name())
+            .and_then(|r| r.schema.as_ref())
+    }
+
+    /// Returns the names of the relations of the database, sorted by name, with the
+    /// schemas of the relations that are added by [`Database::add_relation_with_schema`].
+    ///
+    /// **Example**:
+    /// ```rust
+    /// use codd::{Database, RowSchema, ValueType};
+    ///
+    /// let mut db = Database::new();
+    /// let schema = RowSchema::new().with_key_column("id", ValueType::Int);
+    /// db.add_relation_with_schema("items", schema.clone()).unwrap();
+    /// db.add_relation::<i32>("numbers").unwrap();
+    ///
+    /// assert_eq!(
+    ///     vec![("items", Some(&schema)), ("numbers", None)],
+    ///     db.relations()
+    /// );
+    /// ```
+    ///
+    /// [`Database::add_relation_with_schema`]: ./struct.Database.html#method.add_relation_with_schema
+    pub fn relations(&self) -> Vec<(&str, Option<&RowSchema>)> {
+        let mut relations: Vec<_> = self
+            .relations
+            .iter()
+            .map(|(name, entry)| (name.as_str(), entry.schema.as_ref()))
+            .collect();
+        relations.sort_by_key(|(name, _)| *name);
+        relations
+    }
+
+    /// Inserts tuples in the `Instance` corresponding to `relation`. If `relation` is added
+    /// with a schema, its rows are validated against the schema and no row is inserted
+    /// when a row doesn't match the schema. Likewise, no tuple is inserted when the
+    /// insertion violates an inclusion dependency of `relation` that rejects violations.
     pub fn insert<T>(&self, relation: &Relation<T>, tuples: Tuples<T>) -> Result<(), Error>
     where
         T: Tuple + 'static,
     {
-        let instance = self.relation_instance(&relation)?;
-        instance.insert(tuples);
+        let _guard = self.lock();
+        self.check_insert(relation, &tuples)?;
+        #[cfg(feature = "storage")]
+        self.log(&[(wal::Operation::Insert, relation.name(), &tuples)])?;
+        self.apply_insert(relation, tuples)
+    }
+
+    /// Stabilizes the relations of the database and returns the current epoch of the
+    /// database. The epoch is a logical timestamp that advances whenever a relation is
+    /// stabilized with changes (e.g., when an expression over the relation is evaluated
+    /// after inserting tuples into it). The state of the relations at the returned epoch
+    /// can be queried later by [`Database::evaluate_as_of`].
+    ///
+    /// [`Database::evaluate_as_of`]: ./struct.Database.html#method.evaluate_as_of
+    pub fn epoch(&self) -> Result<u64, Error> {
+        let _guard = self.lock();
+        let names: Vec<&str> = self.relations.keys().map(String::as_str).collect();
+        self.stabilize_relations(&names)?;
+        Ok(self.epoch.load(Ordering::SeqCst))
+    }
+
+    /// Starts tracking the history of `relation` from the current epoch of the database, so
+    /// that expressions over `relation` can be evaluated at this epoch and the epochs after
+    /// by [`Database::evaluate_as_of`].
+    ///
+    /// **Note**: the history keeps every batch of tuples that is added to or removed from
+    /// `relation` until it is trimmed by [`Database::trim_history`]. Histories are not saved
+    /// with the `storage` feature.
+    ///
+    /// [`Database::evaluate_as_of`]: ./struct.Database.html#method.evaluate_as_of
+    /// [`Database::trim_history`]: ./struct.Database.html#method.trim_history
+    pub fn track_history<T>(&mut self, relation: &Relation<T>) -> Result<(), Error>
+    where
+        T: Tuple + 'static,
+    {
+        self.relation_instance(relation)?;
+        {
+            let _guard = self.lock();
+            self.stabilize_relation(relation.name())?;
+        }
+        let epoch = self.epoch.load(Ordering::SeqCst);
+        let entry = self.relations.get_mut(relation.name()).unwrap();
+        if entry.history.is_none() {
+            entry.history = Some(entry.instance.history(epoch));
+        }
+        Ok(())
+    }
+
+    /// Stops tracking the history of `relation` and discards its history. Does nothing if
+    /// the history of `relation` is not tracked.
+    pub fn untrack_history<T>(&mut self, relation: &Relation<T>) -> Result<(), Error>
+    where
+        T: Tuple + 'static,
+    {
+        self.relation_instance(relation)?;
+        self.relations.get_mut(relation.name()).unwrap().history = None;
+        Ok(())
+    }
+
+    /// Discards the changes to `relation` before `before_epoch` (or before the current
+    /// epoch if `before_epoch` is later) from its history, so that expressions over
+    /// `relation` can no longer be evaluated at the earlier epochs. Returns
+    /// [`Error::HistoryNotFound`] if the history of `relation` is not tracked.
+    ///
+    /// **Example**:
+    /// ```rust
+    /// use codd::{Database, Error};
+    ///
+    /// let mut db = Database::new();
+    /// let r = db.add_relation::<i32>("r").unwrap();
+    /// db.track_history(&r).unwrap();
+    ///
+    /// db.insert(&r, vec![1].into()).unwrap();
+    /// let first = db.epoch().unwrap();
+    /// db.insert(&r, vec![2].into()).unwrap();
+    /// let second = db.epoch().unwrap();
+    ///
+    /// db.trim_history(&r, second).unwrap();
+    /// assert_eq!(vec![1, 2], db.evaluate_as_of(&r, second).unwrap().into_tuples());
+    /// assert!(matches!(
+    ///     db.evaluate_as_of(&r, first),
+    ///     Err(Error::HistoryNotFound { .. })
+    /// ));
+    /// ```
+    ///
+    /// [`Error::HistoryNotFound`]: ./enum.Error.html#variant.HistoryNotFound
+    pub fn trim_history<T>(
+        &mut self,
+        relation: &Relation<T>,
+        before_epoch: u64,
+    ) -> Result<(), Error>
+    where
+        T: Tuple + 'static,
+    {
+        self.relation_instance(relation)?;
+        let epoch = self.epoch.load(Ordering::SeqCst);
+        let history = self
+            .relations
+            .get_mut(relation.name())
+            .unwrap()
+            .history
+            .as_mut()
+            .ok_or_else(|| Error::HistoryNotFound {
+                relation: relation.name().into(),
+                epoch: before_epoch,
+            })?;
+        history.trim(std::cmp::min(before_epoch, epoch));
+        Ok(())
+    }
+
+    /// Evaluates `expression` on the state of the relations of the database at `epoch`,
+    /// which is returned by [`Database::epoch`]. The relations of `expression` must be
+    /// tracked by [`Database::track_history`] since `epoch`; otherwise,
+    /// [`Error::HistoryNotFound`] is returned. Expressions over views cannot be evaluated
+    /// at an epoch.
+    ///
+    /// **Note**: the relations are reconstructed at `epoch` from the closest checkpoint in
+    /// their histories, which are stored every 64 changes to a relation; so, the cost of
+    /// the reconstruction is proportional to the size of the relations at the checkpoint
+    /// and the changes after it, but not to the length of the histories.
+    ///
+    /// **Example**:
+    /// ```rust
+    /// use codd::Database;
+    ///
+    /// let mut db = Database::new();
+    /// let r = db.add_relation::<i32>("r").unwrap();
+    /// db.track_history(&r).unwrap();
+    ///
+    /// db.insert(&r, vec![1, 2].into()).unwrap();
+    /// let epoch = db.epoch().unwrap();
+    /// db.remove(&r, vec![1].into()).unwrap();
+    /// db.insert(&r, vec![3].into()).unwrap();
+    ///
+    /// assert_eq!(vec![2, 3], db.evaluate(&r).unwrap().into_tuples());
+    /// assert_eq!(vec![1, 2], db.evaluate_as_of(&r, epoch).unwrap().into_tuples());
+    /// assert!(db.evaluate_as_of(&r, 0).unwrap().is_empty());
+    /// ```
+    ///
+    /// [`Database::epoch`]: ./struct.Database.html#method.epoch
+    /// [`Database::track_history`]: ./struct.Database.html#method.track_history
+    /// [`Error::HistoryNotFound`]: ./enum.Error.html#variant.HistoryNotFound
+    pub fn evaluate_as_of<T, E>(&self, expression: &E, epoch: u64) -> Result<Tuples<T>, Error>
+    where
+        T: Tuple,
+        E: ExpressionExt<T>,
+    {
+        let (relations, views) = dependency::expression_dependencies(expression);
+        if !views.is_empty() {
+            return Err(Error::UnsupportedExpression {
+                name: "View".into(),
+                operation: "Evaluate As Of".into(),
+            });
+        }
+
+        self.past(relations, Some(epoch))?.evaluate(expression)
+    }
+
+    /// Evaluates `expression` like [`Database::evaluate`] and returns only the tuples that
+    /// are added to the result since `cursor`, alongside a new [`Cursor`] for the current
+    /// state of the database. Only the net changes to the relations of `expression` since
+    /// the epoch of `cursor`, taken from their histories, are evaluated incrementally
+    /// (like the changes that stabilize a view) against the current relations; the result
+    /// is not evaluated at the epoch of `cursor`. The relations of `expression` must be
+    /// tracked by [`Database::track_history`] since the epoch of `cursor`, or since they
+    /// are added if `cursor` is `Cursor::default()`, which starts from the empty state.
+    ///
+    /// **Example**:
+    /// ```rust
+    /// use codd::{Cursor, Database, Expression};
+    ///
+    /// let mut db = Database::new();
+    /// let r = db.add_relation::<i32>("r").unwrap();
+    /// db.track_history(&r).unwrap();
+    /// let doubled = r.builder().project(|t| t * 2).build();
+    ///
+    /// db.insert(&r, vec![1, 2].into()).unwrap();
+    /// let (tuples, cursor) = db.evaluate_since(&doubled, &Cursor::default()).unwrap();
+    /// assert_eq!(vec![2, 4], tuples.into_tuples());
+    ///
+    /// db.insert(&r, vec![2, 3].into()).unwrap();
+    /// let (tuples, _) = db.evaluate_since(&doubled, &cursor).unwrap();
+    /// assert_eq!(vec![6], tuples.into_tuples());
+    /// ```
+    ///
+    /// [`Database::evaluate`]: ./struct.Database.html#method.evaluate
+    /// [`Cursor`]: ./struct.Cursor.html
+    /// [`Database::track_history`]: ./struct.Database.html#method.track_history
+    pub fn evaluate_since<T, E>(
+        &self,
+        expression: &E,
+        cursor: &Cursor,
+    ) -> Result<(Tuples<T>, Cursor), Error>
+    where
+        T: Tuple + 'static,
+        E: ExpressionExt<T> + 'static,
+    {
+        let (relations, views) = dependency::expression_dependencies(expression);
+        if !views.is_empty() {
+            return Err(Error::UnsupportedExpression {
+                name: "View".into(),
+                operation: "Evaluate Since".into(),
+            });
+        }
+
+        let (forward, backward, epoch) = {
+            let _guard = self.lock();
+            // all relations are stabilized so that the cursor reflects every change so far:
+            let names: Vec<&str> = self.relations.keys().map(String::as_str).collect();
+            self.stabilize_relations(&names)?;
+            let epoch = self.epoch.load(Ordering::SeqCst);
+            let (forward, backward) = self.delta(relations, cursor.epoch(), epoch)?;
+            (forward, backward, epoch)
+        };
+
+        // the recent tuples of `expression` with the changes are current but may have been
+        // in the result at the epoch of `cursor`; the tuples that are removed from
+        // `expression` with the reversed changes are current and were not in the result:
+        let recent = expression.collect_recent(&evaluate::IncrementalCollector::new(&forward))?;
+        let removed =
+            expression.collect_removed(&evaluate::IncrementalCollector::new(&backward))?;
+        let mut tuples = Vec::new();
+        helpers::intersect_helper(&recent, &removed, |t| tuples.push(t.clone()));
+        Ok((tuples.into(), Cursor::new(epoch)))
+    }
+
+    /// Returns two new databases with the relations identified by `relations` and their
+    /// indexes, which look as if they are stabilized with the net changes to the relations
+    /// after `since` (or since the start of their histories if `since` is `None`) until
+    /// `until`: in the first database, the added tuples are recent and the removed tuples
+    /// are removed; in the second one, the changes are reversed. The other tuples of the
+    /// relations are stable in both databases.
+    fn delta(
+        &self,
+        relations: HashSet<String>,
+        since: Option<u64>,
+        until: u64,
+    ) -> Result<(Database, Database), Error> {
+        let mut forward = Database::new();
+        let mut backward = Database::new();
+        for name in relations {
+            let entry = self
+                .relations
+                .get(&name)
+                .ok_or_else(|| Error::InstanceNotFound { name: name.clone() })?;
+            let (added, removed) = entry
+                .history
+                .as_ref()
+                .and_then(|h| h.changes(since, until))
+                .ok_or_else(|| Error::HistoryNotFound {
+                    relation: name.clone(),
+                    epoch: since.unwrap_or_default(),
+                })?;
+            let (added, removed) = (added.as_ref(), removed.as_ref());
+
+            let mut forward_entry =
+                RelationEntry::with_instance(entry.instance.delta(added, added, removed));
+            forward_entry.indexes = entry
+                .indexes
+                .iter()
+                .map(|i| i.delta(added, added, removed))
+                .collect();
+            let mut backward_entry =
+                RelationEntry::with_instance(entry.instance.delta(added, removed, added));
+            backward_entry.indexes = entry
+                .indexes
+                .iter()
+                .map(|i| i.delta(added, removed, added))
+                .collect();
+            forward.relations.insert(name.clone(), forward_entry);
+            backward.relations.insert(name, backward_entry);
+        }
+        Ok((forward, backward))
+    }
+
+    /// Returns a new database with the relations identified by `relations` at `epoch`, or
+    /// with the empty relations if `epoch` is `None`. The relations are reconstructed from
+    /// their histories, alongside their indexes.
+    fn past(&self, relations: HashSet<String>, epoch: Option<u64>) -> Result<Database, Error> {
+        let _guard = self.lock();
+        let mut past = Database::new();
+        for name in relations {
+            let entry = self
+                .relations
+                .get(&name)
+                .ok_or_else(|| Error::InstanceNotFound { name: name.clone() })?;
+            let instance = entry
+                .history
+                .as_ref()
+                .and_then(|h| h.as_of(epoch))
+                .ok_or_else(|| Error::HistoryNotFound {
+                    relation: name.clone(),
+                    epoch: epoch.unwrap_or_default(),
+                })?;
+            let mut past_entry = RelationEntry::with_instance(instance);
+            past_entry.indexes = entry
+                .indexes
+                .iter()
+                .map(|i| i.rebuild(past_entry.instance.as_any()))
+                .collect();
+            past.relations.insert(name, past_entry);
+        }
+        Ok(past)
+    }
+
+    /// Returns a snapshot of the database: a copy of the database that shares the batches
+    /// of tuples in its instances with the receiver. Taking a snapshot is proportional to
+    /// the number of batches; a batch is copied only when it is changed in either database.
+    ///
+    /// **Note**: the snapshot of a database that is opened by `Database::open` with the
+    /// `storage` feature is not backed by the files of the database.
+    ///
+    /// **Example**:
+    /// ```rust
+    /// use codd::Database;
+    ///
+    /// let mut db = Database::new();
+    /// let r = db.add_relation::<i32>("r").unwrap();
+    /// db.insert(&r, vec![1, 2, 3].into()).unwrap();
+    ///
+    /// let snapshot = db.snapshot();
+    /// db.remove(&r, vec![1].into()).unwrap();
+    /// db.insert(&r, vec![4].into()).unwrap();
+    ///
+    /// assert_eq!(vec![1, 2, 3], snapshot.evaluate(&r).unwrap().into_tuples());
+    /// assert_eq!(vec![2, 3, 4], db.evaluate(&r).unwrap().into_tuples());
+    /// ```
+    pub fn snapshot(&self) -> Self {
+        self.clone()
+    }
+
+    /// Returns a new [`Transaction`] that buffers insertions into and removals from the
+    /// relations of the database until it is committed.
+    ///
+    /// [`Transaction`]: ./struct.Transaction.html
+    pub fn transaction(&self) -> Transaction<'_> {
+        Transaction::new(self)
+    }
+
+    /// Removes tuples from the `Instance` corresponding to `relation`. Tuples that don't
+    /// exist in the instance are ignored. No tuple is removed when the removal violates an
+    /// inclusion dependency on `relation` that rejects violations.
+    ///
+    /// **Note**: removals flow incrementally through `Select`, `Intersect`, `Difference`
+    /// and `Join` (which joins only the tuples with the keys of the removed tuples). A
+    /// tuple of `Project`, `Product` or `Union` may have several derivations, so the
+    /// current tuples of their sub-expressions are collected again and, for `Project` and
+    /// `Product` (every current pair), mapped until each candidate for removal is found.
+    /// Removing tuples from views over these expressions costs up to a full evaluation of
+    /// their sub-expressions.
+    pub fn remove<T>(&self, relation: &Relation<T>, tuples: Tuples<T>) -> Result<(), Error>
+    where
+        T: Tuple + 'static,
+    {
+        let _guard = self.lock();
+        self.check_remove(relation, &tuples)?;
+        #[cfg(feature = "storage")]
+        self.log(&[(wal::Operation::Remove, relation.name(), &tuples)])?;
+        self.apply_remove(relation, tuples)
+    }
+
+    /// Adds an inclusion dependency from `relation` to `referenced` to the database,
+    /// requiring the key of every tuple in `relation`, returned by `key`, to be the key of
+    /// a tuple in `referenced`, returned by `referenced_key`. The dependency is checked
+    /// before tuples are inserted into `relation` or removed from `referenced`: an
+    /// insertion or a removal that leaves a tuple of `relation` without a referenced tuple
+    /// is not applied, and the tuples of `relation` that would violate the dependency are
+    /// returned in an [`Error::ConstraintViolation`].
+    ///
+    /// **Note**: the existing tuples of `relation` are not checked when the dependency is
+    /// added. Inclusion dependencies are not saved with the `storage` feature.
+    ///
+    /// **Example**:
+    /// ```rust
+    /// use codd::{Database, Error};
+    ///
+    /// let mut db = Database::new();
+    /// let musician = db.add_relation::<(String, String)>("musician").unwrap();
+    /// let band = db.add_relation::<(String, i32)>("band").unwrap();
+    /// db.add_inclusion(&musician, |m| m.1.clone(), &band, |b| b.0.clone()).unwrap();
+    ///
+    /// db.insert(&band, vec![("Beatles".to_string(), 1960)].into()).unwrap();
+    /// let result = db.insert(&musician, vec![
+    ///     ("John".to_string(), "Beatles".to_string()),
+    ///     ("Mick".to_string(), "Rolling Stones".to_string()),
+    /// ].into());
+    ///
+    /// assert!(matches!(result, Err(Error::ConstraintViolation { .. })));
+    /// assert!(db.evaluate(&musician).unwrap().is_empty());
+    ///
+    /// db.insert(&musician, vec![("John".to_string(), "Beatles".to_string())].into()).unwrap();
+    /// assert!(matches!(
+    ///     db.remove(&band, vec![("Beatles".to_string(), 1960)].into()),
+    ///     Err(Error::ConstraintViolation { .. })
+    /// ));
+    /// ```
+    ///
+    /// [`Error::ConstraintViolation`]: ./enum.Error.html#variant.ConstraintViolation
+    pub fn add_inclusion<K, T, S>(
+        &mut self,
+        relation: &Relation<T>,
+        key: impl UnaryFn<T, K> + 'static,
+        referenced: &Relation<S>,
+        referenced_key: impl UnaryFn<S, K> + 'static,
+    ) -> Result<(), Error>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static,
+        S: Tuple + 'static,
+    {
+        self.inclusion(
+            relation,
+            Closure::unary(key),
+            referenced,
+            referenced_key,
+            None,
+        )
+    }
+
+    /// Adds an inclusion dependency from `relation` to `referenced` to the database, similar
+    /// to [`Database::add_inclusion`], where the tuples that violate the dependency are kept
+    /// in `relation` and recorded in a new relation identified by `violations`. A recorded
+    /// tuple is retracted from `violations` once it no longer violates the dependency
+    /// (e.g., when a tuple that it references is inserted into `referenced`). Returns a
+    /// [`Relation`] object that can be used to query the violations.
+    ///
+    /// **Example**:
+    /// ```rust
+    /// use codd::Database;
+    ///
+    /// let mut db = Database::new();
+    /// let musician = db.add_relation::<(String, String)>("musician").unwrap();
+    /// let band = db.add_relation::<(String, i32)>("band").unwrap();
+    /// let dangling = db
+    ///     .add_inclusion_with_violations(
+    ///         &musician,
+    ///         |m| m.1.clone(),
+    ///         &band,
+    ///         |b| b.0.clone(),
+    ///         "dangling",
+    ///     )
+    ///     .unwrap();
+    ///
+    /// db.insert(&band, vec![("Beatles".to_string(), 1960)].into()).unwrap();
+    /// db.insert(&musician, vec![
+    ///     ("John".to_string(), "Beatles".to_string()),
+    ///     ("Mick".to_string(), "Rolling Stones".to_string()),
+    /// ].into()).unwrap();
+    ///
+    /// assert_eq!(
+    ///     vec![("Mick".to_string(), "Rolling Stones".to_string())],
+    ///     db.evaluate(&dangling).unwrap().into_tuples()
+    /// );
+    /// assert_eq!(2, db.evaluate(&musician).unwrap().len());
+    ///
+    /// db.insert(&band, vec![("Rolling Stones".to_string(), 1962)].into()).unwrap();
+    /// assert!(db.evaluate(&dangling).unwrap().is_empty());
+    /// ```
+    ///
+    /// [`Database::add_inclusion`]: ./struct.Database.html#method.add_inclusion
+    /// [`Relation`]: ./expression/struct.Relation.html
+    pub fn add_inclusion_with_violations<K, T, S>(
+        &mut self,
+        relation: &Relation<T>,
+        key: impl UnaryFn<T, K> + 'static,
+        referenced: &Relation<S>,
+        referenced_key: impl UnaryFn<S, K> + 'static,
+        violations: &str,
+    ) -> Result<Relation<T>, Error>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static,
+        S: Tuple + 'static,
+    {
+        self.relation_instance(relation)?;
+        self.relation_instance(referenced)?;
+        let result = self.add_relation(violations)?;
+        let key = Closure::unary(key);
+        let position = self.index(&result, key.clone())?.reference().position;
+        self.inclusion(
+            relation,
+            key,
+            referenced,
+            referenced_key,
+            Some((violations.into(), position)),
+        )?;
+        Ok(result)
+    }
+
+    /// Adds an inclusion dependency from `relation` to `referenced`, recording the
+    /// violations in the relation `violations` by its index at the given position if it
+    /// is given.
+    fn inclusion<K, T, S>(
+        &mut self,
+        relation: &Relation<T>,
+        key: Closure<dyn UnaryFn<T, K>>,
+        referenced: &Relation<S>,
+        referenced_key: impl UnaryFn<S, K> + 'static,
+        violations: Option<(String, usize)>,
+    ) -> Result<(), Error>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static,
+        S: Tuple + 'static,
+    {
+        self.relation_instance(relation)?;
+        let index = self
+            .add_index(referenced, referenced_key)?
+            .reference()
+            .position;
+        let keyed = self.index(relation, key.clone())?.reference().position;
+        let inclusion = Inclusion::<K, T, S>::new(key, keyed, referenced.name(), index, violations);
+        self.relations
+            .get_mut(relation.name())
+            .unwrap()
+            .inclusions
+            .push(Box::new(inclusion));
+        let entry = self.relations.get_mut(referenced.name()).unwrap();
+        if !entry.referencing.iter().any(|r| r == relation.name()) {
+            entry.referencing.push(relation.name().into());
+        }
         Ok(())
     }
 
+    /// Adds a new index on `relation` to the database, where `key` is a closure that returns
+    /// the key of the tuples in the index, and returns an [`Index`] object that can be used
+    /// to access the index. The index contains the existing tuples of `relation` and is
+    /// maintained as tuples are inserted into and removed from `relation`.
+    ///
+    /// **Note**: indexes are not saved with the `storage` feature; they are added again to
+    /// a loaded database.
+    ///
+    /// [`Index`]: ./expression/struct.Index.html
+    pub fn add_index<K, T>(
+        &mut self,
+        relation: &Relation<T>,
+        key: impl UnaryFn<T, K> + 'static,
+    ) -> Result<Index<K, T>, Error>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static,
+    {
+        self.index(relation, Closure::unary(key))
+    }
+
+    /// Adds a new index on `relation` with the shared closure `key` to the database.
+    fn index<K, T>(
+        &mut self,
+        relation: &Relation<T>,
+        key: Closure<dyn UnaryFn<T, K>>,
+    ) -> Result<Index<K, T>, Error>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static,
+    {
+        let index = IndexInstance::new(self.relation_instance(relation)?, key.clone());
+        let entry = self.relations.get_mut(relation.name()).unwrap();
+        entry.indexes.push(Box::new(index));
+        Ok(Index::new(relation.clone(), entry.indexes.len() - 1, key))
+    }
+
+    /// Returns the arrangement of `relation` that is identified by `name`: an index on
+    /// `relation` that is shared by all expressions (and views) that arrange `relation` by
+    /// the same name. If the arrangement doesn't exist, it is added with `key` as its key
+    /// closure; otherwise, `key` is ignored and the existing index is returned. Joins in
+    /// views that are built by [`Index::with_key`] on the same arrangement read the keyed
+    /// tuples of one incrementally maintained index.
+    ///
+    /// **Example**:
+    /// ```rust
+    /// use codd::{Database, Expression};
+    ///
+    /// let mut db = Database::new();
+    /// let musician = db.add_relation::<(String, String)>("musician").unwrap();
+    /// let band = db.add_relation::<(String, i32)>("band").unwrap();
+    /// let genre = db.add_relation::<(String, String)>("genre").unwrap();
+    ///
+    /// let by_band = db.arrange(&musician, "band", |m| m.1.clone()).unwrap();
+    /// let formed = db
+    ///     .store_view(
+    ///         by_band
+    ///             .with_key()
+    ///             .join(band.builder().with_key(|b| b.0.clone()))
+    ///             .on(|_, m, b| (m.0.clone(), b.1))
+    ///             .build(),
+    ///     )
+    ///     .unwrap();
+    ///
+    /// // the arrangement is shared by both views:
+    /// let by_band = db.arrange(&musician, "band", |m| m.1.clone()).unwrap();
+    /// let plays = db
+    ///     .store_view(
+    ///         by_band
+    ///             .with_key()
+    ///             .join(genre.builder().with_key(|g| g.0.clone()))
+    ///             .on(|_, m, g| (m.0.clone(), g.1.clone()))
+    ///             .build(),
+    ///     )
+    ///     .unwrap();
+    ///
+    /// let petrucci = ("John Petrucci".to_string(), "Dream Theater".to_string());
+    /// let progressive = ("Dream Theater".to_string(), "Progressive Metal".to_string());
+    /// db.insert(&musician, vec![petrucci].into()).unwrap();
+    /// db.insert(&band, vec![("Dream Theater".to_string(), 1985)].into()).unwrap();
+    /// db.insert(&genre, vec![progressive].into()).unwrap();
+    ///
+    /// assert_eq!(
+    ///     vec![("John Petrucci".to_string(), 1985)],
+    ///     db.evaluate(&formed).unwrap().into_tuples()
+    /// );
+    /// assert_eq!(
+    ///     vec![("John Petrucci".to_string(), "Progressive Metal".to_string())],
+    ///     db.evaluate(&plays).unwrap().into_tuples()
+    /// );
+    /// ```
+    ///
+    /// [`Index::with_key`]: ./expression/struct.Index.html#method.with_key
+    pub fn arrange<K, T>(
+        &mut self,
+        relation: &Relation<T>,
+        name: &str,
+        key: impl UnaryFn<T, K> + 'static,
+    ) -> Result<Index<K, T>, Error>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static,
+    {
+        self.relation_instance(relation)?;
+        let entry = &self.relations[relation.name()];
+        if let Some(&position) = entry.arrangements.get(name) {
+            let index = entry.indexes[position]
+                .as_any()
+                .downcast_ref::<IndexInstance<K, T>>()
+                .ok_or_else(|| Error::InstanceExists {
+                    name: format!("{}.{}", relation.name(), name),
+                })?;
+            return Ok(Index::new(relation.clone(), position, index.key().clone()));
+        }
+
+        let index = self.add_index(relation, key)?;
+        let entry = self.relations.get_mut(relation.name()).unwrap();
+        entry
+            .arrangements
+            .insert(name.into(), entry.indexes.len() - 1);
+        Ok(index)
+    }
+
+    /// Appends one record with `entries` to the write-ahead log of the database if the
+    /// database has a log.
+    #[cfg(feature = "storage")]
+    fn log(&self, entries: &[wal::Entry]) -> Result<(), Error> {
+        match &self.log {
+            Some(log) => log.append(entries),
+            None => Ok(()),
+        }
+    }
+
+    /// Acquires the lock that serializes the operations on the instances of the database
+    /// with the `sync` feature. The notifications of subscribers that are queued during the
+    /// operation are delivered when the returned guard is dropped, after the lock is
+    /// released. A lock that is poisoned by a panic in a closure of an expression (e.g., a
+    /// `Select` predicate) is recovered, like a `RefCell` borrow is released by a panic.
+    fn lock(&self) -> Guard<'_> {
+        Guard {
+            database: self,
+            #[cfg(feature = "sync")]
+            lock: Some(
+                self.lock
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner),
+            ),
+        }
+    }
+
+    /// Delivers the queued notifications of subscribers in the order in which they are
+    /// queued. The notifications that are queued by a callback (e.g., when the callback
+    /// evaluates an expression) are delivered after the notifications before them by the
+    /// ongoing delivery.
+    fn deliver(&self) {
+        while self
+            .delivering
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            // `delivering` is reset even if a callback panics:
+            let delivering = Delivering(&self.delivering);
+            loop {
+                let notification = self.notifications.lock().unwrap().pop_front();
+                match notification {
+                    Some(notification) => notification.deliver(),
+                    None => break,
+                }
+            }
+            drop(delivering);
+
+            // a notification may be queued by another thread before `delivering` is reset:
+            if self.notifications.lock().unwrap().is_empty() {
+                break;
+            }
+        }
+    }
+
     /// Returns the instance for `relation` if it exists.
     fn relation_instance<T>(&self, relation: &Relation<T>) -> Result<&Instance<T>, Error>
     where
@@ -228,10 +1272,179 @@ impl Database {
         Ok(result)
     }
 
+    /// Returns an error if inserting `tuples` into `relation` violates the schema or the
+    /// key of `relation`, or an inclusion dependency whose violations are rejected.
+    fn check_insert<T>(&self, relation: &Relation<T>, tuples: &Tuples<T>) -> Result<(), Error>
+    where
+        T: Tuple + 'static,
+    {
+        self.check_changes(relation, tuples, &Vec::new().into())?;
+        self.check_inclusions(
+            &[relation.name()],
+            &RelationChange {
+                relation: relation.name(),
+                inserted: Some(tuples),
+                removed: None,
+            },
+        )
+    }
+
+    /// Returns an error if inserting `tuples` into `relation`, while removing `removing`
+    /// from `relation`, violates the schema or the key of `relation`.
+    fn check_changes<T>(
+        &self,
+        relation: &Relation<T>,
+        tuples: &Tuples<T>,
+        removing: &Tuples<T>,
+    ) -> Result<(), Error>
+    where
+        T: Tuple + 'static,
+    {
+        self.relation_instance(relation)?;
+        let entry = &self.relations[relation.name()];
+        if let Some(schema) = &entry.schema {
+            if let Some(rows) = (tuples as &dyn Any).downcast_ref::<Tuples<Row>>() {
+                for row in rows.iter() {
+                    schema.validate(relation.name(), row)?;
+                }
+            }
+        }
+        if let Some(key) = entry.key {
+            let conflicts = entry.indexes[key].conflicts(tuples, removing);
+            if !conflicts.is_empty() {
+                return Err(Error::ConstraintViolation {
+                    relation: relation.name().into(),
+                    tuples: conflicts,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns an error if removing `tuples` from `relation` violates an inclusion
+    /// dependency whose violations are rejected.
+    fn check_remove<T>(&self, relation: &Relation<T>, tuples: &Tuples<T>) -> Result<(), Error>
+    where
+        T: Tuple + 'static,
+    {
+        self.relation_instance(relation)?;
+        self.check_inclusions(
+            &[relation.name()],
+            &RelationChange {
+                relation: relation.name(),
+                inserted: None,
+                removed: Some(tuples),
+            },
+        )
+    }
+
+    /// Returns the inclusion dependencies on or of the relations `names`, paired with the
+    /// names of their constrained relations.
+    fn inclusions<'a>(&'a self, names: &[&'a str]) -> Vec<(&'a str, &'a dyn DynInclusion)> {
+        let mut constrained = BTreeSet::new();
+        for name in names {
+            if let Some(entry) = self.relations.get(*name) {
+                constrained.insert(*name);
+                constrained.extend(entry.referencing.iter().map(String::as_str));
+            }
+        }
+        let mut result = Vec::new();
+        for name in constrained {
+            for inclusion in self.relations[name].inclusions.iter() {
+                if names.contains(&name) || names.contains(&inclusion.referenced()) {
+                    result.push((name, inclusion.as_ref()));
+                }
+            }
+        }
+        result
+    }
+
+    /// Returns an error if applying `pending` on the relations `names` violates an
+    /// inclusion dependency whose violations are rejected.
+    fn check_inclusions(&self, names: &[&str], pending: &dyn Pending) -> Result<(), Error> {
+        for (relation, inclusion) in self.inclusions(names) {
+            inclusion.check(self, relation, pending)?;
+        }
+        Ok(())
+    }
+
+    /// Updates the violations relations of the inclusion dependencies on or of the
+    /// relations `names` after `pending` is applied on them.
+    fn record_inclusions(&self, names: &[&str], pending: &dyn Pending) {
+        for (relation, inclusion) in self.inclusions(names) {
+            inclusion.record(self, relation, pending);
+        }
+    }
+
+    /// Inserts `tuples` into the instance of `relation` and its indexes.
+    fn apply_insert<T>(&self, relation: &Relation<T>, tuples: Tuples<T>) -> Result<(), Error>
+    where
+        T: Tuple + 'static,
+    {
+        let instance = self.relation_instance(relation)?;
+        for index in self.relations[relation.name()].indexes.iter() {
+            index.insert(&tuples);
+        }
+        instance.insert(tuples.clone());
+        self.record_inclusions(
+            &[relation.name()],
+            &RelationChange {
+                relation: relation.name(),
+                inserted: Some(&tuples),
+                removed: None,
+            },
+        );
+        Ok(())
+    }
+
+    /// Removes `tuples` from the instance of `relation` and its indexes.
+    fn apply_remove<T>(&self, relation: &Relation<T>, tuples: Tuples<T>) -> Result<(), Error>
+    where
+        T: Tuple + 'static,
+    {
+        let instance = self.relation_instance(relation)?;
+        for index in self.relations[relation.name()].indexes.iter() {
+            index.remove(&tuples);
+        }
+        instance.remove(tuples.clone());
+        self.record_inclusions(
+            &[relation.name()],
+            &RelationChange {
+                relation: relation.name(),
+                inserted: None,
+                removed: Some(&tuples),
+            },
+        );
+        Ok(())
+    }
+
+    /// Returns the instance of the index identified by `reference` if it exists.
+    fn index_instance<K, T>(&self, reference: &IndexRef) -> Result<&Instance<(K, T)>, Error>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static,
+    {
+        let result = self
+            .relations
+            .get(&reference.relation)
+            .and_then(|r| r.indexes.get(reference.position))
+            .and_then(|i| i.instance().as_any().downcast_ref::<Instance<(K, T)>>())
+            .ok_or_else(|| Error::IndexNotFound {
+                relation: reference.relation.clone(),
+                position: reference.position,
+            })?;
+        Ok(result)
+    }
+
     /// Stores a new view over `expression` and returns a [`View`] objeect that can be
     /// evaluated as a view.
     ///
+    /// **Note**: a view over an [`Aggregate`] keeps an accumulator for every group and
+    /// folds the recently added tuples into the accumulators of their groups; only the
+    /// groups with removed tuples are folded again.
+    ///
     /// [`View`]: ./expression/struct.View.html
+    /// [`Aggregate`]: ./expression/struct.Aggregate.html
     pub fn store_view<T, E, I>(&mut self, expression: I) -> Result<View<T, E>, Error>
     where
         T: Tuple + 'static,
@@ -239,12 +1452,126 @@ impl Database {
         I: IntoExpression<T, E>,
     {
         let expression = expression.into_expression();
-        // `validator` rejects views over `Difference` (not supported):
-        validate::validate_view_expression(&expression)?;
 
         let (relation_deps, view_deps) = dependency::expression_dependencies(&expression);
+        let entry = ViewEntry::new(expression.into_view_instance().0);
+        self.store_view_entry(entry, relation_deps, view_deps)
+    }
+
+    /// Subscribes `callback` to the changes of `view`: whenever the view is stabilized with
+    /// new tuples, the batch of the new tuples is passed to `callback`. Views are stabilized
+    /// when they (or the relations and views that they depend on) are evaluated, and when
+    /// a transaction that changes their relations is committed. The tuples that are
+    /// removed from the view are not delivered. The batches are delivered in order, after
+    /// the operation that stabilizes the view is finished (with the `sync` feature, after
+    /// the database is unlocked); so, `callback` may evaluate expressions in the database.
+    /// A panic in `callback` propagates to the operation, and the notifications that
+    /// remain in the queue are delivered by the next operation.
+    ///
+    /// **Example**:
+    /// ```rust
+    /// use codd::{Database, Expression};
+    /// use std::sync::{Arc, Mutex};
+    ///
+    /// let mut db = Database::new();
+    /// let r = db.add_relation::<i32>("r").unwrap();
+    /// let even = db.store_view(r.builder().select(|t| t % 2 == 0).build()).unwrap();
+    ///
+    /// let received = Arc::new(Mutex::new(Vec::new()));
+    /// let sink = received.clone();
+    /// db.subscribe(&even, move |batch: &codd::Tuples<i32>| {
+    ///     sink.lock().unwrap().extend(batch.iter().cloned())
+    /// })
+    /// .unwrap();
+    ///
+    /// db.insert(&r, vec![1, 2, 3, 4].into()).unwrap();
+    /// db.evaluate(&r).unwrap();
+    /// assert_eq!(vec![2, 4], *received.lock().unwrap());
+    /// ```
+    pub fn subscribe<T, E>(
+        &mut self,
+        view: &View<T, E>,
+        callback: impl UnaryFn<Tuples<T>, ()> + 'static,
+    ) -> Result<(), Error>
+    where
+        T: Tuple + 'static,
+        E: Expression<T> + 'static,
+    {
+        self.view_instance(view)?;
+        self.views
+            .get_mut(view.reference())
+            .unwrap()
+            .subscribers
+            .push(Box::new(Subscriber::new(callback)));
+        Ok(())
+    }
 
-        let mut entry = ViewEntry::new(ViewInstance::new(expression));
+    /// Stores a new recursive view and returns a [`View`] object that can be evaluated
+    /// as a view. The expression of the view is built by `recursive`, which is given a
+    /// placeholder for the view itself. The view is evaluated semi-naively to the least
+    /// fixpoint of its expression.
+    ///
+    /// **Note**: the placeholder may not appear on the right side of a [`Difference`] or
+    /// inside an [`Aggregate`].
+    ///
+    /// **Example**:
+    /// ```rust
+    /// use codd::{Database, Expression};
+    ///
+    /// let mut db = Database::new();
+    /// let edges = db.add_relation::<(i32, i32)>("edges").unwrap();
+    ///
+    /// // compute the transitive closure of `edges`:
+    /// let paths = db
+    ///     .store_recursive_view(|paths| {
+    ///         let steps = paths
+    ///             .builder()
+    ///             .with_key(|t: &(i32, i32)| t.1)
+    ///             .join(edges.builder().with_key(|t| t.0))
+    ///             .on(|_, l, r| (l.0, r.1))
+    ///             .build();
+    ///         edges.builder().union(steps).build()
+    ///     })
+    ///     .unwrap();
+    ///
+    /// db.insert(&edges, vec![(1, 2), (2, 3), (3, 4)].into()).unwrap();
+    /// assert_eq!(
+    ///     vec![(1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)],
+    ///     db.evaluate(&paths).unwrap().into_tuples()
+    /// );
+    /// ```
+    ///
+    /// [`View`]: ./expression/struct.View.html
+    /// [`Difference`]: ./expression/struct.Difference.html
+    /// [`Aggregate`]: ./expression/struct.Aggregate.html
+    pub fn store_recursive_view<T, E, I, F>(&mut self, recursive: F) -> Result<View<T, E>, Error>
+    where
+        T: Tuple + 'static,
+        E: ExpressionExt<T> + 'static,
+        I: IntoExpression<T, E>,
+        F: FnOnce(View<T, Mono<T>>) -> I,
+    {
+        let placeholder = View::new(ViewRef(self.view_counter));
+        let expression = recursive(placeholder.clone()).into_expression();
+        validate::validate_recursive_view_expression(&expression, placeholder.reference())?;
+
+        let (relation_deps, view_deps) = dependency::expression_dependencies(&expression);
+        let entry = ViewEntry::new(Box::new(ViewInstance::recursive(expression)));
+        self.store_view_entry(entry, relation_deps, view_deps)
+    }
+
+    /// Stores `entry` for a new view with the given dependencies, initializes the view
+    /// and returns a [`View`] object for it.
+    fn store_view_entry<T, E>(
+        &mut self,
+        mut entry: ViewEntry,
+        relation_deps: HashSet<String>,
+        view_deps: HashSet<ViewRef>,
+    ) -> Result<View<T, E>, Error>
+    where
+        T: Tuple + 'static,
+        E: ExpressionExt<T> + 'static,
+    {
         let reference = ViewRef(self.view_counter);
 
         // track relation dependencies of this view:
@@ -263,9 +1590,21 @@ impl Database {
             entry.dependee_views.insert(r.clone());
         }
 
-        entry.instance.initialize(self)?;
+        // a recursive view depends on itself:
+        if entry.dependee_views.contains(&reference) {
+            entry.add_dependent_view(reference.clone());
+        }
 
+        // the view is initialized after it is stored as it may appear in its own expression:
         self.views.insert(reference.clone(), entry);
+        let initialized = {
+            let _guard = self.lock();
+            self.views[&reference].instance.initialize(self)
+        };
+        if let Err(e) = initialized {
+            self.views.remove(&reference);
+            return Err(e);
+        }
         self.view_counter += 1;
 
         Ok(View::new(reference))
@@ -280,11 +1619,11 @@ impl Database {
         let result = self
             .views
             .get(view.reference())
-            .and_then(|v| v.instance.as_any().downcast_ref::<ViewInstance<T, E>>())
+            .and_then(|v| v.instance.instance().as_any().downcast_ref::<Instance<T>>())
             .ok_or(Error::InstanceNotFound {
                 name: format!("{:?}", view.reference()),
             })?;
-        Ok(result.instance())
+        Ok(result)
     }
 
     /// Stabilizes the view identified by `view_ref` by stabilizing its dependees and
@@ -293,11 +1632,11 @@ impl Database {
     fn stabilize_view(&self, view_ref: &ViewRef) -> Result<(), Error> {
         if let Some(entry) = self.views.get(view_ref) {
             // do nothing if the view is already stabilizing:
-            if entry.stabilizing.get() {
+            if entry.stabilizing.load(Ordering::SeqCst) {
                 return Ok(());
             }
 
-            entry.stabilizing.set(true);
+            entry.stabilizing.store(true, Ordering::SeqCst);
 
             for r in entry.dependee_relations.iter() {
                 self.stabilize_relation(r)?;
@@ -307,13 +1646,18 @@ impl Database {
             }
 
             while entry.instance.instance().changed() {
+                for subscriber in entry.subscribers.iter() {
+                    if let Some(notification) = subscriber.notification(entry.instance.instance()) {
+                        self.notifications.lock().unwrap().push_back(notification);
+                    }
+                }
                 for r in entry.dependent_views.iter() {
                     self.views.get(r).unwrap().instance.stabilize(&self)?;
                     self.stabilize_view(r)?;
                 }
             }
 
-            entry.stabilizing.set(false);
+            entry.stabilizing.store(false, Ordering::SeqCst);
         }
 
         Ok(())
@@ -322,28 +1666,92 @@ impl Database {
     /// Stabilizes the relation identified by `name`. It also stabilizes
     /// all views depending on this `name`.
     fn stabilize_relation(&self, name: &str) -> Result<(), Error> {
-        if let Some(entry) = self.relations.get(name) {
-            // do nothing if relation is already stabilizing:
-            if entry.stabilizing.get() {
-                return Ok(());
+        self.stabilize_relations(&[name])
+    }
+
+    /// Stabilizes the relations identified by `names` together, so that the views
+    /// depending on more than one of these relations are stabilized once with the
+    /// changes to all of them. The changes are recorded at the same epoch.
+    fn stabilize_relations(&self, names: &[&str]) -> Result<(), Error> {
+        let mut entries = Vec::new();
+        for name in names {
+            if let Some(entry) = self.relations.get(*name) {
+                // do nothing if relation is already stabilizing:
+                if entry.stabilizing.load(Ordering::SeqCst) {
+                    continue;
+                }
+
+                entries.push(entry);
             }
+        }
 
-            entry.stabilizing.set(true);
+        for entry in entries.iter() {
+            entry.stabilizing.store(true, Ordering::SeqCst);
+        }
 
-            while entry.instance.changed() {
+        loop {
+            let changed: Vec<_> = entries.iter().filter(|e| e.changed()).collect();
+            if changed.is_empty() {
+                break;
+            }
+
+            let epoch = self.epoch.fetch_add(1, Ordering::SeqCst) + 1;
+            let mut views = Vec::new();
+            for entry in changed {
+                if let Some(history) = &entry.history {
+                    history.record(entry.instance.as_ref(), epoch);
+                }
                 for r in entry.dependent_views.iter() {
-                    self.views.get(r).unwrap().instance.stabilize(&self)?;
-                    self.stabilize_view(r)?;
+                    if !views.contains(&r) {
+                        views.push(r);
+                    }
                 }
             }
+            for r in views {
+                self.views.get(r).unwrap().instance.stabilize(&self)?;
+                self.stabilize_view(r)?;
+            }
+        }
 
-            entry.stabilizing.set(false);
+        for entry in entries.iter() {
+            entry.stabilizing.store(false, Ordering::SeqCst);
         }
 
         Ok(())
     }
 }
 
+/// Is returned by `Database::lock` to release the lock of the database and deliver the
+/// queued notifications of subscribers when it is dropped.
+struct Guard<'d> {
+    database: &'d Database,
+
+    #[cfg(feature = "sync")]
+    lock: Option<std::sync::MutexGuard<'d, ()>>,
+}
+
+impl Drop for Guard<'_> {
+    fn drop(&mut self) {
+        #[cfg(feature = "sync")]
+        drop(self.lock.take());
+        // the notifications are delivered by the next operation if the current operation
+        // panics:
+        if !std::thread::panicking() {
+            self.database.deliver();
+        }
+    }
+}
+
+/// Is created by `Database::deliver` to reset the flag of the ongoing delivery of the
+/// notifications of subscribers when it is dropped, even if a callback panics.
+struct Delivering<'d>(&'d AtomicBool);
+
+impl Drop for Delivering<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
 impl Default for Database {
     fn default() -> Self {
         Self::new()
@@ -352,6 +1760,8 @@ impl Default for Database {
 
 impl Clone for Database {
     fn clone(&self) -> Self {
+        // the instances are not copied while another thread changes them:
+        let _guard = self.lock();
         let mut relations = HashMap::new();
         let mut views = HashMap::new();
 
@@ -366,6 +1776,13 @@ impl Clone for Database {
             relations,
             views,
             view_counter: self.view_counter,
+            epoch: AtomicU64::new(self.epoch.load(Ordering::SeqCst)),
+            #[cfg(feature = "sync")]
+            lock: std::sync::Mutex::new(()),
+            notifications: Mutex::new(VecDeque::new()),
+            delivering: AtomicBool::new(false),
+            #[cfg(feature = "storage")]
+            log: None,
         }
     }
 }
@@ -373,7 +1790,10 @@ impl Clone for Database {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::expression::{Join, Project, Select};
+    use crate::{
+        expression::{Difference, Join, Project, Select, Union},
+        Value, ValueType,
+    };
 
     #[test]
     fn test_insert() {
@@ -407,6 +1827,113 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_remove() {
+        {
+            let mut database = Database::new();
+            let r = database.add_relation::<i32>("r").unwrap();
+            assert!(database.remove(&r, vec![1, 2, 3].into()).is_ok());
+            assert_eq!(
+                Tuples::<i32>::from(vec![1, 2, 3]),
+                database.relation_instance(&r).unwrap().to_remove()[0]
+            );
+        }
+        {
+            let mut database = Database::new();
+            let r = database.add_relation::<i32>("r").unwrap();
+            database.insert(&r, vec![1, 2, 3].into()).unwrap();
+            assert!(database.remove(&r, vec![1, 4].into()).is_ok());
+            assert_eq!(
+                Tuples::<i32>::from(vec![2, 3]),
+                database.relation_instance(&r).unwrap().to_add()[0]
+            );
+            assert_eq!(
+                Tuples::<i32>::from(vec![1, 4]),
+                database.relation_instance(&r).unwrap().to_remove()[0]
+            );
+        }
+        {
+            let mut database = Database::new();
+            let r = database.add_relation::<i32>("r").unwrap();
+            database.insert(&r, vec![1, 2, 3].into()).unwrap();
+            assert_eq!(vec![1, 2, 3], database.evaluate(&r).unwrap().into_tuples());
+
+            database.remove(&r, vec![2, 4].into()).unwrap();
+            assert_eq!(vec![1, 3], database.evaluate(&r).unwrap().into_tuples());
+
+            database.insert(&r, vec![2].into()).unwrap();
+            database.remove(&r, vec![3].into()).unwrap();
+            assert_eq!(vec![1, 2], database.evaluate(&r).unwrap().into_tuples());
+        }
+        {
+            let database = Database::new();
+            let r = Database::new().add_relation("r").unwrap(); // dummy database
+            assert!(database.remove(&r, vec![1, 2, 3].into()).is_err());
+        }
+    }
+
+    #[cfg(feature = "sync")]
+    #[test]
+    fn test_sync_database() {
+        use std::{sync::Arc, thread};
+
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Database>();
+
+        let mut database = Database::new();
+        let r = database.add_relation::<i32>("r").unwrap();
+        let odds = database
+            .store_view(r.builder().select(|t| t % 2 == 1).build())
+            .unwrap();
+        let database = Arc::new(database);
+
+        let handles = (0..4)
+            .map(|i| {
+                let database = database.clone();
+                let r = r.clone();
+                let odds = odds.clone();
+                thread::spawn(move || {
+                    database
+                        .insert(&r, (i * 10..(i + 1) * 10).collect::<Vec<_>>().into())
+                        .unwrap();
+                    database.evaluate(&odds).unwrap()
+                })
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            assert!(handle.join().is_ok());
+        }
+
+        assert_eq!(
+            (0..40).filter(|t| t % 2 == 1).collect::<Vec<_>>(),
+            database.evaluate(&odds).unwrap().into_tuples()
+        );
+    }
+
+    #[test]
+    fn test_panic_in_closure() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let mut database = Database::new();
+        let r = database.add_relation::<i32>("r").unwrap();
+        database.insert(&r, vec![1, 2, 3].into()).unwrap();
+
+        let select = r
+            .builder()
+            .select(|&t| if t == 2 { panic!("select") } else { true })
+            .build();
+        assert!(catch_unwind(AssertUnwindSafe(|| database.evaluate(&select))).is_err());
+
+        // the database is usable after the panic:
+        database.insert(&r, vec![4].into()).unwrap();
+        assert_eq!(
+            vec![1, 2, 3, 4],
+            database.evaluate(&r).unwrap().into_tuples()
+        );
+        database.remove(&r, vec![2].into()).unwrap();
+        assert_eq!(vec![1, 3, 4], database.evaluate(&r).unwrap().into_tuples());
+    }
+
     #[test]
     fn test_database_new() {
         let database = Database::new();
@@ -448,6 +1975,58 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_snapshot() {
+        let mut database = Database::new();
+        let r = database.add_relation::<(i32, i32)>("r").unwrap();
+        let sum = database
+            .store_view(
+                r.builder()
+                    .group_by(|t| t.0)
+                    .aggregate(0, |s, t| s + t.1)
+                    .build(),
+            )
+            .unwrap();
+        database.track_history(&r).unwrap();
+        database.insert(&r, vec![(1, 10), (2, 20)].into()).unwrap();
+        assert_eq!(
+            vec![(1, 10), (2, 20)],
+            database.evaluate(&sum).unwrap().into_tuples()
+        );
+
+        let snapshot = database.snapshot();
+        database.insert(&r, vec![(1, 1), (3, 30)].into()).unwrap();
+        database.remove(&r, vec![(2, 20)].into()).unwrap();
+        assert_eq!(
+            vec![(1, 11), (3, 30)],
+            database.evaluate(&sum).unwrap().into_tuples()
+        );
+        let epoch = database.epoch().unwrap();
+
+        assert_eq!(
+            vec![(1, 10), (2, 20)],
+            snapshot.evaluate(&sum).unwrap().into_tuples()
+        );
+        assert_eq!(
+            vec![(1, 10), (2, 20)],
+            snapshot.evaluate_as_of(&r, epoch).unwrap().into_tuples()
+        );
+
+        snapshot.insert(&r, vec![(2, 2)].into()).unwrap();
+        assert_eq!(
+            vec![(1, 10), (2, 22)],
+            snapshot.evaluate(&sum).unwrap().into_tuples()
+        );
+        assert_eq!(
+            vec![(1, 11), (3, 30)],
+            database.evaluate(&sum).unwrap().into_tuples()
+        );
+        assert_eq!(
+            vec![(1, 1), (1, 10), (3, 30)],
+            database.evaluate_as_of(&r, epoch).unwrap().into_tuples()
+        );
+    }
+
     #[test]
     fn test_add_relation() {
         let mut database = Database::new();
@@ -457,6 +2036,257 @@ mod tests {
         assert!(database.relations.get("b").is_none());
     }
 
+    #[test]
+    fn test_add_relation_with_schema() {
+        let mut database = Database::new();
+        let schema = RowSchema::new()
+            .with_key_column("id", ValueType::Int)
+            .with_column("name", ValueType::Str);
+        let r = database
+            .add_relation_with_schema("r", schema.clone())
+            .unwrap();
+        assert!(database
+            .add_relation_with_schema("r", schema.clone())
+            .is_err());
+        assert_eq!(Some(&schema), database.relation_schema(&r));
+
+        database
+            .insert(&r, vec![vec![Value::from(1), Value::Null]].into())
+            .unwrap();
+        let result = database.insert(
+            &r,
+            vec![
+                vec![Value::from(2), Value::from("b")],
+                vec![Value::from(3), Value::from(true)],
+            ]
+            .into(),
+        );
+        assert!(matches!(
+            result,
+            Err(Error::TypeMismatch { column, .. }) if column == "name"
+        ));
+        assert!(matches!(
+            database.insert(&r, vec![vec![Value::Null, Value::from("c")]].into()),
+            Err(Error::NullKey { .. })
+        ));
+        assert_eq!(
+            vec![vec![Value::from(1), Value::Null]],
+            database.evaluate(&r).unwrap().into_tuples()
+        );
+    }
+
+    #[test]
+    fn test_add_relation_with_key() {
+        let mut database = Database::new();
+        let r = database
+            .add_relation_with_key::<i32, (i32, i32)>("r", |t| t.0)
+            .unwrap();
+        assert!(database
+            .add_relation_with_key::<i32, (i32, i32)>("r", |t| t.0)
+            .is_err());
+
+        database.insert(&r, vec![(1, 10), (2, 20)].into()).unwrap();
+        let result = database.insert(&r, vec![(3, 30), (3, 31)].into());
+        assert!(matches!(
+            result,
+            Err(Error::ConstraintViolation { relation, tuples })
+                if relation == "r" && tuples == vec!["(3, 30)", "(3, 31)"]
+        ));
+        assert_eq!(
+            Tuples::from(vec![(1, 10), (2, 20)]),
+            database.evaluate(&r).unwrap()
+        );
+
+        // inserting an existing tuple is not a violation:
+        database.insert(&r, vec![(1, 10)].into()).unwrap();
+        let result = database.insert(&r, vec![(2, 21), (4, 40)].into());
+        assert!(matches!(
+            result,
+            Err(Error::ConstraintViolation { tuples, .. })
+                if tuples == vec!["(2, 20)", "(2, 21)"]
+        ));
+
+        // a removed tuple doesn't conflict with its replacement:
+        database.remove(&r, vec![(2, 20)].into()).unwrap();
+        database.insert(&r, vec![(2, 21)].into()).unwrap();
+        assert_eq!(
+            Tuples::from(vec![(1, 10), (2, 21)]),
+            database.evaluate(&r).unwrap()
+        );
+
+        let schema = RowSchema::new()
+            .with_key_column("id", ValueType::Int)
+            .with_column("name", ValueType::Str);
+        let s = database.add_relation_with_schema("s", schema).unwrap();
+        database
+            .insert(&s, vec![vec![Value::from(1), Value::from("a")]].into())
+            .unwrap();
+        assert!(matches!(
+            database.insert(&s, vec![vec![Value::from(1), Value::from("b")]].into()),
+            Err(Error::ConstraintViolation { .. })
+        ));
+    }
+
+    #[test]
+    fn test_add_inclusion() {
+        let mut database = Database::new();
+        let r = database.add_relation::<(i32, i32)>("r").unwrap();
+        let s = database.add_relation::<i32>("s").unwrap();
+        let dummy = Relation::<i32>::new("t");
+        assert!(database.add_inclusion(&r, |t| t.1, &dummy, |t| *t).is_err());
+        database.add_inclusion(&r, |t| t.1, &s, |t| *t).unwrap();
+
+        database.insert(&s, vec![1, 2].into()).unwrap();
+        let result = database.insert(&r, vec![(10, 1), (20, 3)].into());
+        assert!(matches!(
+            result,
+            Err(Error::ConstraintViolation { relation, tuples })
+                if relation == "r" && tuples == vec!["(20, 3)"]
+        ));
+        // the violating insertion is rejected before it is applied:
+        assert_eq!(0, database.relation_instance(&r).unwrap().to_add().len());
+        assert!(database.evaluate(&r).unwrap().is_empty());
+
+        database.insert(&r, vec![(10, 1), (20, 2)].into()).unwrap();
+        assert_eq!(
+            Tuples::from(vec![(10, 1), (20, 2)]),
+            database.evaluate(&r).unwrap()
+        );
+
+        // removing a referenced tuple is rejected:
+        let result = database.remove(&s, vec![1, 2].into());
+        assert!(matches!(
+            result,
+            Err(Error::ConstraintViolation { relation, tuples })
+                if relation == "r" && tuples == vec!["(10, 1)", "(20, 2)"]
+        ));
+        assert_eq!(0, database.relation_instance(&s).unwrap().to_remove().len());
+        assert_eq!(Tuples::from(vec![1, 2]), database.evaluate(&s).unwrap());
+
+        // pending removals of the referenced tuples are considered:
+        database.remove(&r, vec![(20, 2)].into()).unwrap();
+        database.remove(&s, vec![2].into()).unwrap();
+        assert!(database.insert(&r, vec![(30, 2)].into()).is_err());
+        assert_eq!(Tuples::from(vec![(10, 1)]), database.evaluate(&r).unwrap());
+        assert_eq!(Tuples::from(vec![1]), database.evaluate(&s).unwrap());
+    }
+
+    #[test]
+    fn test_add_inclusion_with_violations() {
+        let mut database = Database::new();
+        let r = database.add_relation::<(i32, i32)>("r").unwrap();
+        let s = database.add_relation::<i32>("s").unwrap();
+        let v = database
+            .add_inclusion_with_violations(&r, |t| t.1, &s, |t| *t, "v")
+            .unwrap();
+        assert!(database
+            .add_inclusion_with_violations(&r, |t| t.1, &s, |t| *t, "v")
+            .is_err());
+
+        database.insert(&s, vec![1].into()).unwrap();
+        database.insert(&r, vec![(10, 1), (20, 3)].into()).unwrap();
+
+        // the violations are recorded before they are evaluated:
+        assert_eq!(Tuples::from(vec![(20, 3)]), database.evaluate(&v).unwrap());
+        assert_eq!(
+            Tuples::from(vec![(10, 1), (20, 3)]),
+            database.evaluate(&r).unwrap()
+        );
+
+        let cloned = database.clone();
+        database.insert(&r, vec![(30, 4), (40, 3)].into()).unwrap();
+        assert_eq!(
+            Tuples::from(vec![(20, 3), (30, 4), (40, 3)]),
+            database.evaluate(&v).unwrap()
+        );
+        assert_eq!(Tuples::from(vec![(20, 3)]), cloned.evaluate(&v).unwrap());
+
+        // the violations are retracted when they are resolved:
+        database.insert(&s, vec![3].into()).unwrap();
+        assert_eq!(Tuples::from(vec![(30, 4)]), database.evaluate(&v).unwrap());
+        database.remove(&r, vec![(30, 4)].into()).unwrap();
+        assert!(database.evaluate(&v).unwrap().is_empty());
+
+        // removing a referenced tuple records the tuples that reference it:
+        database.remove(&s, vec![1].into()).unwrap();
+        assert_eq!(Tuples::from(vec![(10, 1)]), database.evaluate(&v).unwrap());
+
+        // a violation that is resolved before it is evaluated is never observed:
+        database.insert(&r, vec![(50, 5)].into()).unwrap();
+        database.insert(&s, vec![5].into()).unwrap();
+        assert_eq!(Tuples::from(vec![(10, 1)]), database.evaluate(&v).unwrap());
+    }
+
+    #[test]
+    fn test_add_index() {
+        let mut database = Database::new();
+        let r = database.add_relation::<(i32, i32)>("r").unwrap();
+        let dummy = Relation::<(i32, i32)>::new("s");
+        assert!(database.add_index(&r, |t| t.0).is_ok());
+        assert!(database.add_index(&r, |t| t.1).is_ok());
+        assert!(database.add_index(&dummy, |t| t.0).is_err());
+        assert_eq!(2, database.relations["r"].indexes.len());
+    }
+
+    #[test]
+    fn test_arrange() {
+        let mut database = Database::new();
+        let r = database.add_relation::<(i32, i32)>("r").unwrap();
+        let s = database.add_relation::<(i32, i32)>("s").unwrap();
+        let t = database.add_relation::<(i32, i32)>("t").unwrap();
+
+        let first = database.arrange(&r, "first", |t| t.0).unwrap();
+        let r_s = database
+            .store_view(
+                first
+                    .with_key()
+                    .join(s.builder().with_key(|t| t.0))
+                    .on(|_, &l, &r| (l.1, r.1))
+                    .build(),
+            )
+            .unwrap();
+        let first = database.arrange(&r, "first", |t| t.0).unwrap();
+        let r_t = database
+            .store_view(
+                first
+                    .with_key()
+                    .join(t.builder().with_key(|t| t.0))
+                    .on(|_, &l, &r| (l.1, r.1))
+                    .build(),
+            )
+            .unwrap();
+
+        // the views share the arrangement:
+        assert_eq!(1, database.relations["r"].indexes.len());
+        assert!(database.arrange(&r, "first", |t| t.1 as u8).is_err());
+        assert!(database.arrange(&r, "second", |t| t.1).is_ok());
+        assert_eq!(2, database.relations["r"].indexes.len());
+
+        database.insert(&r, vec![(1, 10), (2, 20)].into()).unwrap();
+        database.insert(&s, vec![(1, 100)].into()).unwrap();
+        database.insert(&t, vec![(2, 200)].into()).unwrap();
+        assert_eq!(
+            vec![(10, 100)],
+            database.evaluate(&r_s).unwrap().into_tuples()
+        );
+        assert_eq!(
+            vec![(20, 200)],
+            database.evaluate(&r_t).unwrap().into_tuples()
+        );
+
+        database.remove(&r, vec![(1, 10)].into()).unwrap();
+        database.insert(&r, vec![(2, 30)].into()).unwrap();
+        assert!(database.evaluate(&r_s).unwrap().is_empty());
+        assert_eq!(
+            vec![(20, 200), (30, 200)],
+            database.evaluate(&r_t).unwrap().into_tuples()
+        );
+
+        let cloned = database.clone();
+        assert_eq!(0, cloned.relations["r"].arrangements["first"]);
+        assert_eq!(1, cloned.relations["r"].arrangements["second"]);
+    }
+
     #[test]
     fn test_get_relation() {
         let mut database = Database::new();
@@ -531,6 +2361,51 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_store_recursive_view() {
+        {
+            let mut database = Database::new();
+            let a = database.add_relation::<i32>("a").unwrap();
+            database
+                .store_recursive_view(|v| Union::new(a, v.builder().project(|t| t + 1).build()))
+                .unwrap();
+
+            let entry = database.views.get(&ViewRef(0)).unwrap();
+            assert!(entry.dependee_relations.contains("a"));
+            assert!(entry.dependee_views.contains(&ViewRef(0)));
+            assert!(entry.dependent_views.contains(&ViewRef(0)));
+            assert!(database.relations["a"]
+                .dependent_views
+                .contains(&ViewRef(0)));
+        }
+        {
+            let mut database = Database::new();
+            let result = database.store_recursive_view(|v: View<i32, Mono<i32>>| {
+                Union::new(Relation::<i32>::new("a"), v)
+            });
+            assert!(result.is_err());
+            assert!(database.views.is_empty());
+            assert_eq!(0, database.view_counter);
+        }
+        {
+            let mut database = Database::new();
+            let a = database.add_relation::<i32>("a").unwrap();
+            let result = database.store_recursive_view(|v| Difference::new(a, v));
+            assert!(result.is_err());
+            assert!(database.views.is_empty());
+        }
+        {
+            let mut database = Database::new();
+            let a = database.add_relation::<(i32, i32)>("a").unwrap();
+            let result = database.store_recursive_view(|v: View<(i32, i32), Mono<(i32, i32)>>| {
+                let count = v.builder().group_by(|t| t.0).aggregate(0, |c, _| c + 1);
+                Union::new(a, count.build())
+            });
+            assert!(result.is_err());
+            assert!(database.views.is_empty());
+        }
+    }
+
     #[test]
     fn test_get_view() {
         let mut database = Database::new();
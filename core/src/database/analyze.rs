@@ -0,0 +1,285 @@
+use crate::{Error, Tuple, Tuples};
+use std::{cell::RefCell, collections::HashMap, fmt, time::Duration, time::Instant};
+
+/// Contains the measurements of evaluating a sub-expression, returned by
+/// [`Database::analyze`] alongside the result of the evaluation.
+///
+/// **Note**: the time of a sub-expression includes the time of its own sub-expressions.
+/// A sub-expression that is evaluated more than once (e.g., for its recent and stable
+/// tuples) accumulates the measurements of all its evaluations.
+///
+/// [`Database::analyze`]: ./struct.Database.html#method.analyze
+#[derive(Clone, Debug)]
+pub struct Analysis {
+    operator: &'static str,
+    time: Duration,
+    input: usize,
+    output: usize,
+    batches: usize,
+    children: Vec<Analysis>,
+}
+
+impl Analysis {
+    /// Returns the name of the operator, e.g., `"Join"`.
+    #[inline(always)]
+    pub fn operator(&self) -> &str {
+        self.operator
+    }
+
+    /// Returns the wall time that is spent on evaluating the sub-expression.
+    #[inline(always)]
+    pub fn time(&self) -> Duration {
+        self.time
+    }
+
+    /// Returns the number of tuples that the sub-expression received from its own
+    /// sub-expressions.
+    #[inline(always)]
+    pub fn input(&self) -> usize {
+        self.input
+    }
+
+    /// Returns the number of tuples that the sub-expression returned.
+    #[inline(always)]
+    pub fn output(&self) -> usize {
+        self.output
+    }
+
+    /// Returns the number of batches of tuples that the sub-expression returned, which
+    /// are merged into the result of the evaluation.
+    #[inline(always)]
+    pub fn batches(&self) -> usize {
+        self.batches
+    }
+
+    /// Returns the measurements of the sub-expressions of the receiver.
+    #[inline(always)]
+    pub fn children(&self) -> &[Analysis] {
+        &self.children
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        writeln!(
+            f,
+            "{:indent$}{} (time: {:?}, input: {} tuples, output: {} tuples / {} batches)",
+            "",
+            self.operator,
+            self.time,
+            self.input,
+            self.output,
+            self.batches,
+            indent = depth * 2
+        )?;
+        for child in self.children.iter() {
+            child.write(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+/// Is implemented by the results of collectors to count their tuples and batches.
+pub(super) trait Collected {
+    /// Returns the number of tuples in the receiver.
+    fn tuples(&self) -> usize;
+
+    /// Returns the number of batches in the receiver.
+    fn batches(&self) -> usize;
+}
+
+impl<T: Tuple> Collected for Tuples<T> {
+    fn tuples(&self) -> usize {
+        self.len()
+    }
+
+    fn batches(&self) -> usize {
+        1
+    }
+}
+
+impl<T: Tuple> Collected for Vec<Tuples<T>> {
+    fn tuples(&self) -> usize {
+        self.iter().map(|b| b.len()).sum()
+    }
+
+    fn batches(&self) -> usize {
+        self.len()
+    }
+}
+
+/// Identifies a sub-expression by its address and type, since the expression is borrowed
+/// during the evaluation.
+type Key = (usize, &'static str, &'static str);
+
+/// Is a sub-expression that is measured by a `Profiler`.
+struct Node {
+    analysis: Analysis,
+    parent: Option<usize>,
+}
+
+#[derive(Default)]
+struct State {
+    nodes: Vec<Node>,
+    keys: HashMap<Key, usize>,
+    /// Contains the nodes of the sub-expressions that are being evaluated.
+    stack: Vec<usize>,
+}
+
+/// Records the measurements of the sub-expressions that are evaluated by the collectors
+/// of a database.
+#[derive(Default)]
+pub(super) struct Profiler {
+    state: RefCell<State>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Evaluates `expression` by `collect`, measuring the evaluation for the sub-expression
+    /// `expression` of the operator `operator`.
+    pub fn record<E, R>(
+        &self,
+        operator: &'static str,
+        expression: &E,
+        collect: impl FnOnce() -> Result<R, Error>,
+    ) -> Result<R, Error>
+    where
+        R: Collected,
+    {
+        let key = (
+            expression as *const E as usize,
+            std::any::type_name::<E>(),
+            operator,
+        );
+        let id = {
+            let mut state = self.state.borrow_mut();
+            let parent = state.stack.last().copied();
+            let id = match state.keys.get(&key) {
+                Some(id) => *id,
+                None => {
+                    let id = state.nodes.len();
+                    state.nodes.push(Node {
+                        analysis: Analysis {
+                            operator,
+                            time: Duration::default(),
+                            input: 0,
+                            output: 0,
+                            batches: 0,
+                            children: Vec::new(),
+                        },
+                        parent,
+                    });
+                    state.keys.insert(key, id);
+                    id
+                }
+            };
+            state.stack.push(id);
+            id
+        };
+
+        let start = Instant::now();
+        let result = collect();
+        let time = start.elapsed();
+
+        let mut state = self.state.borrow_mut();
+        state.stack.pop();
+        if let Ok(result) = &result {
+            let analysis = &mut state.nodes[id].analysis;
+            analysis.time += time;
+            analysis.output += result.tuples();
+            analysis.batches += result.batches();
+            if let Some(parent) = state.stack.last().copied() {
+                state.nodes[parent].analysis.input += result.tuples();
+            }
+        }
+        result
+    }
+
+    /// Consumes the receiver and returns the measurements of the first recorded
+    /// sub-expression.
+    pub fn into_analysis(self) -> Option<Analysis> {
+        let mut nodes = self.state.into_inner().nodes;
+        // children are recorded after their parents:
+        while let Some(node) = nodes.pop() {
+            match node.parent {
+                Some(parent) => nodes[parent].analysis.children.insert(0, node.analysis),
+                None if nodes.is_empty() => return Some(node.analysis),
+                None => {}
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{expression::Project, Database, Expression, Tuples};
+
+    #[test]
+    fn test_analyze() {
+        let mut database = Database::new();
+        let r = database.add_relation::<(i32, i32)>("r").unwrap();
+        let s = database.add_relation::<(i32, i32)>("s").unwrap();
+        database
+            .insert(&r, vec![(1, 10), (2, 20), (3, 30)].into())
+            .unwrap();
+        database
+            .insert(&s, vec![(1, 100), (3, 300)].into())
+            .unwrap();
+
+        let join = r
+            .builder()
+            .with_key(|t| t.0)
+            .join(s.builder().with_key(|t| t.0))
+            .on(|_, l, r| l.1 + r.1)
+            .build();
+        let project = Project::new(&join, |t| t * 2);
+
+        let (result, analysis) = database.analyze(&project).unwrap();
+        assert_eq!(Tuples::from(vec![220, 660]), result);
+        assert_eq!(database.evaluate(&project).unwrap(), result);
+
+        assert_eq!("Evaluate", analysis.operator());
+        assert_eq!(2, analysis.input());
+        assert_eq!(2, analysis.output());
+
+        let project = &analysis.children()[0];
+        assert_eq!("Project", project.operator());
+        assert_eq!(2, project.input());
+        assert_eq!(2, project.output());
+        assert!(project.time() <= analysis.time());
+
+        let join = &project.children()[0];
+        assert_eq!("Join", join.operator());
+        assert_eq!(2, join.output());
+        assert_eq!(
+            vec!["Relation", "Relation"],
+            join.children()
+                .iter()
+                .map(|c| c.operator())
+                .collect::<Vec<_>>()
+        );
+        // the stable tuples of the relations are collected for the recent and the stable
+        // tuples of the join:
+        assert_eq!(6, join.children()[0].output());
+        assert_eq!(4, join.children()[1].output());
+        assert_eq!(10, join.input());
+
+        assert_eq!(5, analysis.to_string().lines().count());
+    }
+
+    #[test]
+    fn test_analyze_error() {
+        let database = Database::new();
+        let mut other = Database::new();
+        let r = other.add_relation::<i32>("r").unwrap();
+        assert!(database.analyze(&r).is_err());
+    }
+}
@@ -0,0 +1,292 @@
+use super::{
+    instance::{IndexInstance, Instance},
+    Database, Tuples,
+};
+use crate::{
+    expression::{Closure, UnaryFn},
+    Error, MaybeSync, Tuple,
+};
+use std::{
+    any::Any,
+    collections::{BTreeMap, BTreeSet},
+    marker::PhantomData,
+};
+
+/// Provides the tuples that an operation inserts into and removes from the relations of
+/// a database, as `Tuples` hidden by `Any`. The tuples that are inserted into a relation
+/// and the tuples that are removed from it are disjoint.
+pub(super) trait Pending {
+    /// Returns the tuples that are inserted into the relation `name` if they exist.
+    fn inserted(&self, name: &str) -> Option<&dyn Any>;
+
+    /// Returns the tuples that are removed from the relation `name` if they exist.
+    fn removed(&self, name: &str) -> Option<&dyn Any>;
+}
+
+/// Is the `Pending` tuples of an operation that inserts tuples into and removes tuples
+/// from one relation.
+pub(super) struct RelationChange<'a> {
+    /// Is the name of the changed relation.
+    pub relation: &'a str,
+
+    /// Is the tuples that are inserted into the relation, if any.
+    pub inserted: Option<&'a dyn Any>,
+
+    /// Is the tuples that are removed from the relation, if any.
+    pub removed: Option<&'a dyn Any>,
+}
+
+impl Pending for RelationChange<'_> {
+    fn inserted(&self, name: &str) -> Option<&dyn Any> {
+        self.inserted.filter(|_| name == self.relation)
+    }
+
+    fn removed(&self, name: &str) -> Option<&dyn Any> {
+        self.removed.filter(|_| name == self.relation)
+    }
+}
+
+/// Is used to store the inclusion dependencies of a relation by hiding their (generic)
+/// types.
+pub(super) trait DynInclusion: MaybeSync {
+    /// Returns the name of the referenced relation of the dependency.
+    fn referenced(&self) -> &str;
+
+    /// Returns an error with the tuples of the constrained relation `relation` of `db` that
+    /// are left without a referenced tuple after `pending` is applied on `db`, if the
+    /// violations of the dependency are rejected. Only the tuples with the keys of the
+    /// pending tuples are checked.
+    fn check(&self, db: &Database, relation: &str, pending: &dyn Pending) -> Result<(), Error>;
+
+    /// Records the tuples of the constrained relation `relation` of `db` that violate the
+    /// dependency after `pending` is applied on `db` in the violations relation, and
+    /// retracts the recorded tuples that no longer violate the dependency, if the
+    /// violations are recorded. Only the tuples with the keys of the pending tuples are
+    /// examined.
+    fn record(&self, db: &Database, relation: &str, pending: &dyn Pending);
+
+    /// Clones the dependency in a `Box`.
+    fn clone_box(&self) -> Box<dyn DynInclusion>;
+}
+
+/// Is an inclusion dependency, requiring the key of every tuple of type `T` in a relation
+/// to be the key of a tuple of type `S` in a referenced relation.
+pub(super) struct Inclusion<K, T, S>
+where
+    K: Tuple,
+    T: Tuple,
+    S: Tuple,
+{
+    /// Is the closure that returns the keys of the tuples of the constrained relation.
+    key: Closure<dyn UnaryFn<T, K>>,
+
+    /// Is the position of the index on the constrained relation by `key`.
+    keyed: usize,
+
+    /// Is the name of the referenced relation.
+    referenced: String,
+
+    /// Is the position of the index on the referenced relation by the referenced keys.
+    index: usize,
+
+    /// Is the name of the relation that records the violating tuples, paired with the
+    /// position of its index by `key`, if the violations are not rejected.
+    violations: Option<(String, usize)>,
+
+    _marker: PhantomData<S>,
+}
+
+impl<K, T, S> Inclusion<K, T, S>
+where
+    K: Tuple + 'static,
+    T: Tuple + 'static,
+    S: Tuple + 'static,
+{
+    /// Creates a new dependency with the index at position `keyed` of the constrained
+    /// relation on the index at position `index` of the relation `referenced`.
+    pub fn new(
+        key: Closure<dyn UnaryFn<T, K>>,
+        keyed: usize,
+        referenced: &str,
+        index: usize,
+        violations: Option<(String, usize)>,
+    ) -> Self {
+        Self {
+            key,
+            keyed,
+            referenced: referenced.into(),
+            index,
+            violations,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the index at `position` of the relation `name` in `db`.
+    fn index<'d, U: Tuple + 'static>(
+        db: &'d Database,
+        name: &str,
+        position: usize,
+    ) -> &'d IndexInstance<K, U> {
+        db.relations[name].indexes[position]
+            .as_any()
+            .downcast_ref::<IndexInstance<K, U>>()
+            .expect("index of the dependency")
+    }
+
+    /// Returns the pending tuples of type `U` that are returned by `tuples` if they exist.
+    fn tuples<U: Tuple + 'static>(tuples: Option<&dyn Any>) -> &[U] {
+        tuples
+            .and_then(|t| t.downcast_ref::<Tuples<U>>())
+            .map_or(&[], |t| t.items())
+    }
+
+    /// Returns the keys of the pending tuples of the constrained relation `relation` and the
+    /// referenced relation.
+    fn pending_keys(&self, db: &Database, relation: &str, pending: &dyn Pending) -> BTreeSet<K> {
+        let mut keys = BTreeSet::new();
+        {
+            let mut key = self.key.get();
+            let inserted = Self::tuples::<T>(pending.inserted(relation));
+            let removed = Self::tuples::<T>(pending.removed(relation));
+            keys.extend(inserted.iter().chain(removed.iter()).map(&mut key));
+        }
+        let index = Self::index::<S>(db, &self.referenced, self.index);
+        let mut key = index.key().get();
+        let inserted = Self::tuples::<S>(pending.inserted(&self.referenced));
+        let removed = Self::tuples::<S>(pending.removed(&self.referenced));
+        keys.extend(inserted.iter().chain(removed.iter()).map(&mut key));
+        keys
+    }
+}
+
+impl<K, T, S> DynInclusion for Inclusion<K, T, S>
+where
+    K: Tuple + 'static,
+    T: Tuple + 'static,
+    S: Tuple + 'static,
+{
+    fn referenced(&self) -> &str {
+        &self.referenced
+    }
+
+    fn check(&self, db: &Database, relation: &str, pending: &dyn Pending) -> Result<(), Error> {
+        if self.violations.is_some() {
+            return Ok(());
+        }
+
+        let index = Self::index::<S>(db, &self.referenced, self.index);
+        let keyed = Self::index::<T>(db, relation, self.keyed);
+        let inserted = Self::tuples::<T>(pending.inserted(relation));
+        let removed = Self::tuples::<T>(pending.removed(relation));
+        let referenced_inserted: BTreeSet<K> = {
+            let mut key = index.key().get();
+            Self::tuples::<S>(pending.inserted(&self.referenced))
+                .iter()
+                .map(&mut key)
+                .collect()
+        };
+        let referenced_removed = Self::tuples::<S>(pending.removed(&self.referenced));
+        let mut inserted_by_key: BTreeMap<K, Vec<&T>> = BTreeMap::new();
+        {
+            let mut key = self.key.get();
+            for t in inserted.iter() {
+                inserted_by_key.entry(key(t)).or_default().push(t);
+            }
+        }
+
+        let mut violating = BTreeSet::new();
+        for key in self.pending_keys(db, relation, pending) {
+            let referenced = referenced_inserted.contains(&key)
+                || index
+                    .lookup(&key)
+                    .iter()
+                    .any(|t| referenced_removed.binary_search(t).is_err());
+            if referenced {
+                continue;
+            }
+
+            if let Some(tuples) = inserted_by_key.get(&key) {
+                violating.extend(tuples.iter().map(|&t| t.clone()));
+            }
+            violating.extend(
+                keyed
+                    .lookup(&key)
+                    .into_iter()
+                    .filter(|t| removed.binary_search(t).is_err()),
+            );
+        }
+
+        if violating.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::ConstraintViolation {
+                relation: relation.into(),
+                tuples: violating.iter().map(|t| format!("{:?}", t)).collect(),
+            })
+        }
+    }
+
+    fn record(&self, db: &Database, relation: &str, pending: &dyn Pending) {
+        let (violations, position) = match &self.violations {
+            Some(violations) => violations,
+            None => return,
+        };
+
+        let index = Self::index::<S>(db, &self.referenced, self.index);
+        let keyed = Self::index::<T>(db, relation, self.keyed);
+        let recorded = Self::index::<T>(db, violations, *position);
+        let mut violating = Vec::new();
+        let mut resolved = Vec::new();
+        for key in self.pending_keys(db, relation, pending) {
+            let dangling = if index.contains(&key) {
+                Vec::new()
+            } else {
+                keyed.lookup(&key)
+            };
+            let existing = recorded.lookup(&key);
+            violating.extend(
+                dangling
+                    .iter()
+                    .filter(|t| existing.binary_search(t).is_err())
+                    .cloned(),
+            );
+            resolved.extend(
+                existing
+                    .into_iter()
+                    .filter(|t| dangling.binary_search(t).is_err()),
+            );
+        }
+
+        let entry = &db.relations[violations];
+        let instance = entry
+            .instance
+            .as_any()
+            .downcast_ref::<Instance<T>>()
+            .expect("instance of the violations relation");
+        if !resolved.is_empty() {
+            let resolved = Tuples::from(resolved);
+            for index in entry.indexes.iter() {
+                index.remove(&resolved);
+            }
+            instance.remove(resolved);
+        }
+        if !violating.is_empty() {
+            let violating = Tuples::from(violating);
+            for index in entry.indexes.iter() {
+                index.insert(&violating);
+            }
+            instance.insert(violating);
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn DynInclusion> {
+        Box::new(Self {
+            key: self.key.clone(),
+            keyed: self.keyed,
+            referenced: self.referenced.clone(),
+            index: self.index,
+            violations: self.violations.clone(),
+            _marker: PhantomData,
+        })
+    }
+}
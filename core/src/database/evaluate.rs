@@ -1,59 +1,465 @@
 /// Implements an incremental algorithm for evaluating an expression in a database.
 use super::{
-    expression_ext::{ExpressionExt, RecentCollector, StableCollector},
-    helpers::{diff_helper, intersect_helper, join_helper, product_helper, project_helper},
+    analyze::{Collected, Profiler},
+    expression_ext::{ExpressionExt, RecentCollector, RemovedCollector, StableCollector},
+    helpers::{diff_helper, gallop, intersect_helper, project_helper},
+    instance::Instance,
     Database, Tuples,
 };
-use crate::{expression::*, Error, Tuple};
+use crate::{
+    expression::{index::IndexRef, *},
+    Error, Tuple,
+};
 
-/// Implements `crate::expression::RecentCollector` and `crate::expression::StableCollector`
-/// to incrementally collect recent and stable tuples of `Instance`s of a database for
-/// expressions.
+/// Implements `crate::expression::RecentCollector`, `crate::expression::StableCollector`
+/// and `crate::expression::RemovedCollector` to incrementally collect recent, stable and
+/// removed tuples of `Instance`s of a database for expressions.
 #[derive(Clone)]
 pub(super) struct IncrementalCollector<'d> {
     /// Is the database in which the visited expression is evaluated.
     database: &'d Database,
+
+    /// Is the profiler that measures the evaluation of sub-expressions, if any.
+    profiler: Option<&'d Profiler>,
 }
 
 impl<'d> IncrementalCollector<'d> {
     /// Creates a new collector for incremental evaluation.
     pub fn new(database: &'d Database) -> Self {
-        Self { database }
+        Self {
+            database,
+            profiler: None,
+        }
+    }
+
+    /// Evaluates `expression` by `collect` and records the evaluation by the profiler of
+    /// the receiver, if any.
+    fn record<E, R>(
+        &self,
+        operator: &'static str,
+        expression: &E,
+        collect: impl FnOnce() -> Result<R, Error>,
+    ) -> Result<R, Error>
+    where
+        R: Collected,
+    {
+        match self.profiler {
+            Some(profiler) => profiler.record(operator, expression, collect),
+            None => collect(),
+        }
+    }
+
+    /// Returns the batches of tuples to which `expression` currently evaluates, that is,
+    /// its stable batches followed by its recent tuples.
+    pub fn collect_current<T, E>(&self, expression: &E) -> Result<Vec<Tuples<T>>, Error>
+    where
+        T: Tuple,
+        E: ExpressionExt<T>,
+    {
+        let mut result = expression.collect_stable(self)?;
+        result.push(expression.collect_recent(self)?);
+        Ok(result)
+    }
+
+    /// Returns the instance of the index identified by `reference` if `reference` is given,
+    /// or an error if the index doesn't exist in the database.
+    fn index_instance<K, T>(
+        &self,
+        reference: Option<&IndexRef>,
+    ) -> Result<Option<&'d Instance<(K, T)>>, Error>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static,
+    {
+        reference
+            .map(|r| self.database.index_instance(r))
+            .transpose()
+    }
+
+    /// Returns the recent tuples of `expression` paired with their keys by `key`. The keyed
+    /// tuples are taken from the index identified by `index` if given.
+    fn keyed_recent<K, T, E>(
+        &self,
+        expression: &E,
+        index: Option<&IndexRef>,
+        key: impl FnMut(&T) -> K,
+    ) -> Result<Tuples<(K, T)>, Error>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static,
+        E: ExpressionExt<T>,
+    {
+        match self.index_instance(index)? {
+            Some(instance) => Ok(instance.recent().clone()),
+            None => Ok(keyed(expression.collect_recent(self)?, key)),
+        }
+    }
+
+    /// Returns the stable batches of `expression` paired with their keys by `key`. The
+    /// keyed batches are taken from the index identified by `index` if given.
+    fn keyed_stable<K, T, E>(
+        &self,
+        expression: &E,
+        index: Option<&IndexRef>,
+        mut key: impl FnMut(&T) -> K,
+    ) -> Result<Vec<Tuples<(K, T)>>, Error>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static,
+        E: ExpressionExt<T>,
+    {
+        match self.index_instance(index)? {
+            Some(instance) => Ok(instance.stable().clone()),
+            None => Ok(expression
+                .collect_stable(self)?
+                .into_iter()
+                .map(|batch| keyed(batch, &mut key))
+                .collect()),
+        }
+    }
+
+    /// Returns the removed tuples of `expression` paired with their keys by `key`. The
+    /// keyed tuples are taken from the index identified by `index` if given.
+    fn keyed_removed<K, T, E>(
+        &self,
+        expression: &E,
+        index: Option<&IndexRef>,
+        key: impl FnMut(&T) -> K,
+    ) -> Result<Tuples<(K, T)>, Error>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static,
+        E: ExpressionExt<T>,
+    {
+        match self.index_instance(index)? {
+            Some(instance) => Ok(instance.removed().clone()),
+            None => Ok(keyed(expression.collect_removed(self)?, key)),
+        }
+    }
+
+    /// Returns the current (stable and recent) tuples of `expression` whose keys by `key`
+    /// are in `keys`, paired with their keys. The keyed tuples are looked up in the index
+    /// identified by `index` if given; otherwise, the tuples are filtered by their keys.
+    fn keyed_current<K, T, E>(
+        &self,
+        expression: &E,
+        index: Option<&IndexRef>,
+        mut key: impl FnMut(&T) -> K,
+        keys: &[K],
+    ) -> Result<Vec<Tuples<(K, T)>>, Error>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static,
+        E: ExpressionExt<T>,
+    {
+        match self.index_instance(index)? {
+            Some(instance) => {
+                let mut result = instance
+                    .stable()
+                    .iter()
+                    .map(|batch| restrict_batch(batch, keys))
+                    .collect::<Vec<_>>();
+                result.push(restrict_batch(&instance.recent(), keys));
+                Ok(result)
+            }
+            None => Ok(self
+                .collect_current(expression)?
+                .iter()
+                .map(|batch| {
+                    batch
+                        .iter()
+                        .filter_map(|t| {
+                            let k = key(t);
+                            if keys.binary_search(&k).is_ok() {
+                                Some((k, t.clone()))
+                            } else {
+                                None
+                            }
+                        })
+                        .into()
+                })
+                .collect()),
+        }
+    }
+}
+
+/// Returns `tuples` paired with their keys by `key`, sorted by their keys.
+fn keyed<K, T>(tuples: Tuples<T>, mut key: impl FnMut(&T) -> K) -> Tuples<(K, T)>
+where
+    K: Tuple,
+    T: Tuple,
+{
+    tuples
+        .into_tuples()
+        .into_iter()
+        .map(|t| (key(&t), t))
+        .into()
+}
+
+/// Returns the tuples of the keyed `batch` of an index whose key is `key`.
+fn lookup_batch<K, T>(batch: &[(K, T)], key: &K) -> Tuples<T>
+where
+    K: Tuple,
+    T: Tuple,
+{
+    gallop(batch, |(k, _)| k < key)
+        .iter()
+        .take_while(|(k, _)| k == key)
+        .map(|(_, t)| t.clone())
+        .into()
+}
+
+/// Returns the tuples of the keyed `batch` of an index whose keys are in the sorted slice
+/// `keys`.
+fn restrict_batch<K, T>(batch: &[(K, T)], keys: &[K]) -> Tuples<(K, T)>
+where
+    K: Tuple,
+    T: Tuple,
+{
+    let mut result = Vec::new();
+    let mut slice = batch;
+    for key in keys {
+        slice = gallop(slice, |(k, _)| k < key);
+        let count = slice.iter().take_while(|(k, _)| k == key).count();
+        result.extend_from_slice(&slice[..count]);
+        slice = &slice[count..];
+    }
+    result.into()
+}
+
+/// Keeps track of the candidates of removal from an expression that are still derived
+/// from the current tuples of the expression.
+struct Support<T: Tuple> {
+    candidates: Tuples<T>,
+    supported: Vec<bool>,
+    remaining: usize,
+}
+
+impl<T: Tuple> Support<T> {
+    fn new(candidates: Tuples<T>) -> Self {
+        let remaining = candidates.len();
+        Self {
+            candidates,
+            supported: vec![false; remaining],
+            remaining,
+        }
+    }
+
+    /// Marks `tuple` as supported if it is a candidate and returns true if every candidate
+    /// is supported, so the remaining current tuples need not be derived.
+    fn derive(&mut self, tuple: &T) -> bool {
+        if let Ok(i) = self.candidates.binary_search(tuple) {
+            if !self.supported[i] {
+                self.supported[i] = true;
+                self.remaining -= 1;
+            }
+        }
+        self.remaining == 0
+    }
+
+    /// Returns the candidates that are not supported.
+    fn into_unsupported(self) -> Tuples<T> {
+        let supported = self.supported;
+        self.candidates
+            .into_tuples()
+            .into_iter()
+            .zip(supported)
+            .filter_map(|(t, s)| if s { None } else { Some(t) })
+            .collect::<Vec<_>>()
+            .into()
+    }
+}
+
+/// Returns the tuples of `candidates` that appear in none of the given `batches`.
+fn missing_from<T: Tuple>(candidates: &[T], batches: &[Tuples<T>]) -> Tuples<T> {
+    let mut result = Vec::new();
+    let slices = batches.iter().map(|t| &t[..]).collect::<Vec<_>>();
+    diff_helper(candidates, &slices, |t| result.push(t.clone()));
+    result.into()
+}
+
+/// Applies the mapping closure of `product` on every pair of `left` and `right` tuples
+/// and pushes the results into `result`.
+#[cfg(not(feature = "parallel"))]
+fn product_batches<L, R, Left, Right, T>(
+    product: &Product<L, R, Left, Right, T>,
+    left: &[L],
+    right: &[R],
+    result: &mut Vec<T>,
+) where
+    L: Tuple,
+    R: Tuple,
+    T: Tuple,
+    Left: Expression<L>,
+    Right: Expression<R>,
+{
+    use super::helpers::product_helper;
+
+    let mut mapper = product.mapper_mut();
+    product_helper(left, right, |v1, v2| result.push(mapper(v1, v2)));
+}
+
+/// Applies the mapping closure of `product` on every pair of `left` and `right` tuples
+/// in parallel and pushes the results into `result`.
+#[cfg(feature = "parallel")]
+fn product_batches<L, R, Left, Right, T>(
+    product: &Product<L, R, Left, Right, T>,
+    left: &[L],
+    right: &[R],
+    result: &mut Vec<T>,
+) where
+    L: Tuple,
+    R: Tuple,
+    T: Tuple,
+    Left: Expression<L>,
+    Right: Expression<R>,
+{
+    use super::helpers::par_product_helper;
+
+    let mapper = product.mapper_ref();
+    result.extend(par_product_helper(left, right, |v1, v2| mapper(v1, v2)));
+}
+
+/// Applies the joining closure of `join` on the pairs of `left` and `right` tuples that
+/// agree on their keys and pushes the results into `result`.
+#[cfg(not(feature = "parallel"))]
+fn join_batches<K, L, R, Left, Right, T>(
+    join: &Join<K, L, R, Left, Right, T>,
+    left: &[(K, L)],
+    right: &[(K, R)],
+    result: &mut Vec<T>,
+) where
+    K: Tuple,
+    L: Tuple,
+    R: Tuple,
+    T: Tuple,
+    Left: Expression<L>,
+    Right: Expression<R>,
+{
+    use super::helpers::join_helper;
+
+    let mut joiner = join.mapper_mut();
+    join_helper(left, right, |k, v1, v2| result.push(joiner(k, v1, v2)));
+}
+
+/// Applies the joining closure of `join` on the pairs of `left` and `right` tuples that
+/// agree on their keys in parallel and pushes the results into `result`.
+#[cfg(feature = "parallel")]
+fn join_batches<K, L, R, Left, Right, T>(
+    join: &Join<K, L, R, Left, Right, T>,
+    left: &[(K, L)],
+    right: &[(K, R)],
+    result: &mut Vec<T>,
+) where
+    K: Tuple,
+    L: Tuple,
+    R: Tuple,
+    T: Tuple,
+    Left: Expression<L>,
+    Right: Expression<R>,
+{
+    use super::helpers::par_join_helper;
+
+    let joiner = join.mapper_ref();
+    result.extend(par_join_helper(left, right, |k, v1, v2| joiner(k, v1, v2)));
+}
+
+/// Returns the grouping keys of `tuples` in `aggregate`.
+fn aggregate_keys<'t, K, S, A, E>(
+    aggregate: &Aggregate<K, S, A, E>,
+    tuples: impl Iterator<Item = &'t S>,
+) -> Tuples<K>
+where
+    K: Tuple,
+    S: Tuple + 't,
+    A: Tuple,
+    E: Expression<S>,
+{
+    tuples.map(aggregate.key_mut()).into()
+}
+
+/// Groups the tuples of `batches` by the key of `aggregate` and folds the tuples of the
+/// groups whose key satisfies `filter`.
+pub(super) fn aggregate_batches<K, S, A, E>(
+    aggregate: &Aggregate<K, S, A, E>,
+    batches: &[&[S]],
+    mut filter: impl FnMut(&K) -> bool,
+) -> Tuples<(K, A)>
+where
+    K: Tuple,
+    S: Tuple,
+    A: Tuple,
+    E: Expression<S>,
+{
+    let mut key = aggregate.key_mut();
+    let keyed: Tuples<(K, &S)> = batches
+        .iter()
+        .flat_map(|batch| batch.iter())
+        .map(|t| (key(t), t))
+        .filter(|(k, _)| filter(k))
+        .into();
+
+    let mut fold = aggregate.fold_mut();
+    let mut result: Vec<(K, A)> = Vec::new();
+    for (k, t) in keyed.iter() {
+        match result.last_mut() {
+            Some((last, value)) if last == k => *value = fold(value, t),
+            _ => result.push((k.clone(), fold(aggregate.init(), t))),
+        }
     }
+    result.into()
 }
 
 impl<'d> RecentCollector for IncrementalCollector<'d> {
-    fn collect_full<T>(&self, _: &Full<T>) -> Result<Tuples<T>, Error>
+    fn collect_full<T>(&self, full: &Full<T>) -> Result<Tuples<T>, Error>
     where
         T: Tuple,
     {
-        // `Full` is not range restricted, so cannot be evaluated.
-        Err(Error::UnsupportedExpression {
-            name: "Full".to_string(),
-            operation: "Evaluate".to_string(),
+        self.record("Full", full, || {
+            // `Full` is not range restricted, so cannot be evaluated.
+            Err(Error::UnsupportedExpression {
+                name: "Full".to_string(),
+                operation: "Evaluate".to_string(),
+            })
         })
     }
 
-    fn collect_empty<T>(&self, _: &Empty<T>) -> Result<Tuples<T>, Error>
+    fn collect_empty<T>(&self, empty: &Empty<T>) -> Result<Tuples<T>, Error>
     where
         T: Tuple,
     {
-        Ok(Vec::new().into())
+        self.record("Empty", empty, || Ok(Vec::new().into()))
     }
 
-    fn collect_singleton<T>(&self, _: &Singleton<T>) -> Result<Tuples<T>, Error>
+    fn collect_singleton<T>(&self, singleton: &Singleton<T>) -> Result<Tuples<T>, Error>
     where
         T: Tuple,
     {
-        Ok(Vec::new().into())
+        self.record("Singleton", singleton, || Ok(Vec::new().into()))
     }
 
     fn collect_relation<T>(&self, relation: &Relation<T>) -> Result<Tuples<T>, Error>
     where
         T: Tuple + 'static,
     {
-        let table = self.database.relation_instance(relation)?;
-        Ok(table.recent().clone())
+        self.record("Relation", relation, || {
+            let table = self.database.relation_instance(relation)?;
+            Ok(table.recent().clone())
+        })
+    }
+
+    fn collect_index<K, T>(&self, index: &Index<K, T>) -> Result<Tuples<T>, Error>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static,
+    {
+        self.record("Index", index, || match index.lookup_key() {
+            Some(key) => {
+                let instance = self.database.index_instance(index.reference())?;
+                Ok(lookup_batch(&instance.recent(), key))
+            }
+            None => index.relation().collect_recent(self),
+        })
     }
 
     fn collect_select<T, E>(&self, select: &Select<T, E>) -> Result<Tuples<T>, Error>
@@ -61,15 +467,17 @@ impl<'d> RecentCollector for IncrementalCollector<'d> {
         T: Tuple,
         E: ExpressionExt<T>,
     {
-        let mut result = Vec::new();
-        let recent = select.expression().collect_recent(self)?;
-        let mut predicate = select.predicate_mut();
-        for tuple in &recent[..] {
-            if predicate(tuple) {
-                result.push(tuple.clone());
+        self.record("Select", select, || {
+            let mut result = Vec::new();
+            let recent = select.expression().collect_recent(self)?;
+            let mut predicate = select.predicate_mut();
+            for tuple in &recent[..] {
+                if predicate(tuple) {
+                    result.push(tuple.clone());
+                }
             }
-        }
-        Ok(result.into())
+            Ok(result.into())
+        })
     }
 
     fn collect_union<T, L, R>(&self, union: &Union<T, L, R>) -> Result<Tuples<T>, Error>
@@ -78,19 +486,21 @@ impl<'d> RecentCollector for IncrementalCollector<'d> {
         L: ExpressionExt<T>,
         R: ExpressionExt<T>,
     {
-        let mut result = Vec::new();
+        self.record("Union", union, || {
+            let mut result = Vec::new();
 
-        let left_recent = union.left().collect_recent(self)?;
-        let right_recent = union.right().collect_recent(self)?;
+            let left_recent = union.left().collect_recent(self)?;
+            let right_recent = union.right().collect_recent(self)?;
 
-        for tuple in &left_recent[..] {
-            result.push(tuple.clone());
-        }
-        for tuple in &right_recent[..] {
-            result.push(tuple.clone());
-        }
+            for tuple in &left_recent[..] {
+                result.push(tuple.clone());
+            }
+            for tuple in &right_recent[..] {
+                result.push(tuple.clone());
+            }
 
-        Ok(result.into())
+            Ok(result.into())
+        })
     }
 
     fn collect_intersect<T, L, R>(&self, intersect: &Intersect<T, L, R>) -> Result<Tuples<T>, Error>
@@ -99,24 +509,26 @@ impl<'d> RecentCollector for IncrementalCollector<'d> {
         L: ExpressionExt<T>,
         R: ExpressionExt<T>,
     {
-        let mut result = Vec::new();
-        let incremental = IncrementalCollector::new(self.database);
+        self.record("Intersect", intersect, || {
+            let mut result = Vec::new();
+            let incremental = self.clone();
 
-        let left_recent = intersect.left().collect_recent(self)?;
-        let right_recent = intersect.right().collect_recent(self)?;
+            let left_recent = intersect.left().collect_recent(self)?;
+            let right_recent = intersect.right().collect_recent(self)?;
 
-        let left_stable = intersect.left().collect_stable(&incremental)?;
-        let right_stable = intersect.right().collect_stable(&incremental)?;
+            let left_stable = intersect.left().collect_stable(&incremental)?;
+            let right_stable = intersect.right().collect_stable(&incremental)?;
 
-        for batch in left_stable.iter() {
-            intersect_helper(&batch, &right_recent, |t| result.push(t.clone()))
-        }
-        for batch in right_stable.iter() {
-            intersect_helper(&left_recent, &batch, |t| result.push(t.clone()))
-        }
+            for batch in left_stable.iter() {
+                intersect_helper(&batch, &right_recent, |t| result.push(t.clone()))
+            }
+            for batch in right_stable.iter() {
+                intersect_helper(&left_recent, &batch, |t| result.push(t.clone()))
+            }
 
-        intersect_helper(&left_recent, &right_recent, |t| result.push(t.clone()));
-        Ok(result.into())
+            intersect_helper(&left_recent, &right_recent, |t| result.push(t.clone()));
+            Ok(result.into())
+        })
     }
 
     fn collect_difference<T, L, R>(
@@ -128,22 +540,25 @@ impl<'d> RecentCollector for IncrementalCollector<'d> {
         L: ExpressionExt<T>,
         R: ExpressionExt<T>,
     {
-        let mut result = Vec::new();
-        let incremental = IncrementalCollector::new(self.database);
-
-        let left_recent = difference.left().collect_recent(self)?;
-        let left_stable = difference.left().collect_stable(&incremental)?;
-        let right_stable = difference.right().collect_stable(&incremental)?;
-        let right_stable_slices = right_stable.iter().map(|t| &t[..]).collect::<Vec<_>>();
-
-        for batch in left_stable.iter() {
-            diff_helper(&batch, &right_stable_slices, |t| result.push(t.clone()));
-        }
-
-        diff_helper(&left_recent, &right_stable_slices, |t| {
-            result.push(t.clone())
-        });
-        Ok(result.into())
+        self.record("Difference", difference, || {
+            let mut result = Vec::new();
+            let incremental = self.clone();
+
+            let left_recent = difference.left().collect_recent(self)?;
+            let left_stable = difference.left().collect_stable(&incremental)?;
+            let right_removed = difference.right().collect_removed(&incremental)?;
+            let right = incremental.collect_current(difference.right())?;
+            let right_slices = right.iter().map(|t| &t[..]).collect::<Vec<_>>();
+
+            // recently added tuples of `left` that are not in `right`:
+            diff_helper(&left_recent, &right_slices, |t| result.push(t.clone()));
+
+            // tuples of `left` that are no longer in `right`:
+            for batch in left_stable.iter() {
+                intersect_helper(batch, &right_removed, |t| result.push(t.clone()));
+            }
+            Ok(result.into())
+        })
     }
 
     fn collect_project<S, T, E>(&self, project: &Project<S, T, E>) -> Result<Tuples<T>, Error>
@@ -152,12 +567,14 @@ impl<'d> RecentCollector for IncrementalCollector<'d> {
         S: Tuple,
         E: ExpressionExt<S>,
     {
-        let mut result = Vec::new();
-        let recent = project.expression().collect_recent(self)?;
-        let mut mapper = project.mapper_mut();
+        self.record("Project", project, || {
+            let mut result = Vec::new();
+            let recent = project.expression().collect_recent(self)?;
+            let mut mapper = project.mapper_mut();
 
-        project_helper(&recent, |t| result.push(mapper(t)));
-        Ok(result.into())
+            project_helper(&recent, |t| result.push(mapper(t)));
+            Ok(result.into())
+        })
     }
 
     fn collect_product<L, R, Left, Right, T>(
@@ -171,29 +588,27 @@ impl<'d> RecentCollector for IncrementalCollector<'d> {
         Left: ExpressionExt<L>,
         Right: ExpressionExt<R>,
     {
-        let mut result = Vec::new();
-        let incremental = IncrementalCollector::new(self.database);
+        self.record("Product", product, || {
+            let mut result = Vec::new();
+            let incremental = self.clone();
 
-        let left_recent = product.left().collect_recent(self)?;
-        let right_recent = product.right().collect_recent(self)?;
+            let left_recent = product.left().collect_recent(self)?;
+            let right_recent = product.right().collect_recent(self)?;
 
-        let left_stable = product.left().collect_stable(&incremental)?;
-        let right_stable = product.right().collect_stable(&incremental)?;
+            let left_stable = product.left().collect_stable(&incremental)?;
+            let right_stable = product.right().collect_stable(&incremental)?;
 
-        let mut mapper = product.mapper_mut();
-
-        for batch in left_stable.iter() {
-            product_helper(&batch, &right_recent, |v1, v2| result.push(mapper(v1, v2)));
-        }
-        for batch in right_stable.iter() {
-            product_helper(&left_recent, &batch, |v1, v2| result.push(mapper(v1, v2)));
-        }
+            for batch in left_stable.iter() {
+                product_batches(product, &batch, &right_recent, &mut result);
+            }
+            for batch in right_stable.iter() {
+                product_batches(product, &left_recent, &batch, &mut result);
+            }
 
-        product_helper(&left_recent, &right_recent, |v1, v2| {
-            result.push(mapper(v1, v2))
-        });
+            product_batches(product, &left_recent, &right_recent, &mut result);
 
-        Ok(result.into())
+            Ok(result.into())
+        })
     }
 
     fn collect_join<K, L, R, Left, Right, T>(
@@ -201,53 +616,64 @@ impl<'d> RecentCollector for IncrementalCollector<'d> {
         join: &Join<K, L, R, Left, Right, T>,
     ) -> Result<Tuples<T>, Error>
     where
-        K: Tuple,
-        L: Tuple,
-        R: Tuple,
+        K: Tuple + 'static,
+        L: Tuple + 'static,
+        R: Tuple + 'static,
         T: Tuple,
         Left: ExpressionExt<L>,
         Right: ExpressionExt<R>,
     {
-        let mut result = Vec::new();
-        let incremental = IncrementalCollector::new(self.database);
-
-        let mut left_key = join.left_key_mut();
-        let mut right_key = join.right_key_mut();
-
-        let left_recent = join.left().collect_recent(self)?;
-        let left_recent: Tuples<(K, &L)> = left_recent.iter().map(|t| (left_key(&t), t)).into();
-        let right_recent = join.right().collect_recent(self)?;
-        let right_recent: Tuples<(K, &R)> = right_recent.iter().map(|t| (right_key(&t), t)).into();
-
-        let left_stable = join.left().collect_stable(&incremental)?;
-        let left_stable: Vec<Tuples<(K, &L)>> = left_stable
-            .iter()
-            .map(|batch| batch.iter().map(|t| (left_key(&t), t)).into())
-            .collect();
-
-        let right_stable = join.right().collect_stable(&incremental)?;
-        let right_stable: Vec<Tuples<(K, &R)>> = right_stable
-            .iter()
-            .map(|batch| batch.iter().map(|t| (right_key(&t), t)).into())
-            .collect();
+        self.record("Join", join, || {
+            let mut result = Vec::new();
+            let incremental = self.clone();
+
+            let left_recent =
+                self.keyed_recent(join.left(), join.left_index(), join.left_key_mut())?;
+            let right_recent =
+                self.keyed_recent(join.right(), join.right_index(), join.right_key_mut())?;
+            let left_stable =
+                incremental.keyed_stable(join.left(), join.left_index(), join.left_key_mut())?;
+            let right_stable =
+                incremental.keyed_stable(join.right(), join.right_index(), join.right_key_mut())?;
+
+            for batch in left_stable.iter() {
+                join_batches(join, &batch, &right_recent, &mut result);
+            }
+            for batch in right_stable.iter() {
+                join_batches(join, &left_recent, &batch, &mut result);
+            }
+            join_batches(join, &left_recent, &right_recent, &mut result);
 
-        let mut joiner = join.mapper_mut();
+            Ok(result.into())
+        })
+    }
 
-        for batch in left_stable.iter() {
-            join_helper(&batch, &right_recent, |k, v1, v2| {
-                result.push(joiner(k, v1, v2))
-            });
-        }
-        for batch in right_stable.iter() {
-            join_helper(&left_recent, &batch, |k, v1, v2| {
-                result.push(joiner(k, v1, v2))
-            });
-        }
-        join_helper(&left_recent, &right_recent, |k, v1, v2| {
-            result.push(joiner(k, v1, v2))
-        });
+    fn collect_aggregate<K, S, A, E>(
+        &self,
+        aggregate: &Aggregate<K, S, A, E>,
+    ) -> Result<Tuples<(K, A)>, Error>
+    where
+        K: Tuple,
+        S: Tuple,
+        A: Tuple,
+        E: ExpressionExt<S>,
+    {
+        self.record("Aggregate", aggregate, || {
+            let recent = aggregate.expression().collect_recent(self)?;
+            let removed = aggregate.expression().collect_removed(self)?;
+            let groups = aggregate_keys(aggregate, recent.iter().chain(removed.iter()));
+            if groups.is_empty() {
+                return Ok(Vec::new().into());
+            }
 
-        Ok(result.into())
+            // the groups affected by recent or removed tuples are folded again:
+            let stable = aggregate.expression().collect_stable(self)?;
+            let mut current = stable.iter().map(|t| &t[..]).collect::<Vec<_>>();
+            current.push(&recent);
+            Ok(aggregate_batches(aggregate, &current, |k| {
+                groups.binary_search(k).is_ok()
+            }))
+        })
     }
 
     fn collect_view<T, E>(&self, view: &View<T, E>) -> Result<Tuples<T>, Error>
@@ -255,47 +681,74 @@ impl<'d> RecentCollector for IncrementalCollector<'d> {
         T: Tuple + 'static,
         E: ExpressionExt<T> + 'static,
     {
-        let table = self.database.view_instance(view)?;
-        Ok(table.recent().clone())
+        self.record("View", view, || {
+            let table = self.database.view_instance(view)?;
+            Ok(table.recent().clone())
+        })
     }
 }
 
 impl<'d> StableCollector for IncrementalCollector<'d> {
-    fn collect_full<T>(&self, _: &Full<T>) -> Result<Vec<Tuples<T>>, Error>
+    fn collect_full<T>(&self, full: &Full<T>) -> Result<Vec<Tuples<T>>, Error>
     where
         T: Tuple,
     {
-        // `Full` cannot be evaluated.
-        Err(Error::UnsupportedExpression {
-            name: "Full".to_string(),
-            operation: "Evaluate".to_string(),
+        self.record("Full", full, || {
+            // `Full` cannot be evaluated.
+            Err(Error::UnsupportedExpression {
+                name: "Full".to_string(),
+                operation: "Evaluate".to_string(),
+            })
         })
     }
 
-    fn collect_empty<T>(&self, _: &Empty<T>) -> Result<Vec<Tuples<T>>, Error>
+    fn collect_empty<T>(&self, empty: &Empty<T>) -> Result<Vec<Tuples<T>>, Error>
     where
         T: Tuple,
     {
-        Ok(Vec::new())
+        self.record("Empty", empty, || Ok(Vec::new()))
     }
 
     fn collect_singleton<T>(&self, singleton: &Singleton<T>) -> Result<Vec<Tuples<T>>, Error>
     where
         T: Tuple,
     {
-        Ok(vec![vec![singleton.tuple().clone()].into()])
+        self.record("Singleton", singleton, || {
+            Ok(vec![vec![singleton.tuple().clone()].into()])
+        })
     }
 
     fn collect_relation<T>(&self, relation: &Relation<T>) -> Result<Vec<Tuples<T>>, Error>
     where
         T: Tuple + 'static,
     {
-        let mut result = Vec::<Tuples<T>>::new();
-        let table = self.database.relation_instance(&relation)?;
-        for batch in table.stable().iter() {
-            result.push(batch.clone());
-        }
-        Ok(result)
+        self.record("Relation", relation, || {
+            let mut result = Vec::<Tuples<T>>::new();
+            let table = self.database.relation_instance(&relation)?;
+            for batch in table.stable().iter() {
+                result.push(batch.clone());
+            }
+            Ok(result)
+        })
+    }
+
+    fn collect_index<K, T>(&self, index: &Index<K, T>) -> Result<Vec<Tuples<T>>, Error>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static,
+    {
+        self.record("Index", index, || match index.lookup_key() {
+            Some(key) => {
+                let instance = self.database.index_instance(index.reference())?;
+                let result = instance
+                    .stable()
+                    .iter()
+                    .map(|batch| lookup_batch(batch, key))
+                    .collect();
+                Ok(result)
+            }
+            None => index.relation().collect_stable(self),
+        })
     }
 
     fn collect_select<T, E>(&self, select: &Select<T, E>) -> Result<Vec<Tuples<T>>, Error>
@@ -303,19 +756,21 @@ impl<'d> StableCollector for IncrementalCollector<'d> {
         T: Tuple,
         E: ExpressionExt<T>,
     {
-        let mut result = Vec::<Tuples<T>>::new();
-        let stable = select.expression().collect_stable(self)?;
-        let mut predicate = select.predicate_mut();
-        for batch in stable.iter() {
-            let mut tuples = Vec::new();
-            for tuple in &batch[..] {
-                if predicate(tuple) {
-                    tuples.push(tuple.clone());
+        self.record("Select", select, || {
+            let mut result = Vec::<Tuples<T>>::new();
+            let stable = select.expression().collect_stable(self)?;
+            let mut predicate = select.predicate_mut();
+            for batch in stable.iter() {
+                let mut tuples = Vec::new();
+                for tuple in &batch[..] {
+                    if predicate(tuple) {
+                        tuples.push(tuple.clone());
+                    }
                 }
+                result.push(tuples.into());
             }
-            result.push(tuples.into());
-        }
-        Ok(result)
+            Ok(result)
+        })
     }
 
     fn collect_union<T, L, R>(&self, union: &Union<T, L, R>) -> Result<Vec<Tuples<T>>, Error>
@@ -324,22 +779,24 @@ impl<'d> StableCollector for IncrementalCollector<'d> {
         L: ExpressionExt<T>,
         R: ExpressionExt<T>,
     {
-        let mut result = Vec::<Tuples<T>>::new();
-        let left_stable = union.left().collect_stable(self)?;
-        let right_stable = union.right().collect_stable(self)?;
-
-        for batch in left_stable.iter() {
-            let mut tuples = Vec::new();
-            project_helper(&batch, |t| tuples.push(t.clone()));
-            result.push(tuples.into());
-        }
-        for batch in right_stable.iter() {
-            let mut tuples = Vec::new();
-            project_helper(&batch, |t| tuples.push(t.clone()));
-            result.push(tuples.into());
-        }
+        self.record("Union", union, || {
+            let mut result = Vec::<Tuples<T>>::new();
+            let left_stable = union.left().collect_stable(self)?;
+            let right_stable = union.right().collect_stable(self)?;
+
+            for batch in left_stable.iter() {
+                let mut tuples = Vec::new();
+                project_helper(&batch, |t| tuples.push(t.clone()));
+                result.push(tuples.into());
+            }
+            for batch in right_stable.iter() {
+                let mut tuples = Vec::new();
+                project_helper(&batch, |t| tuples.push(t.clone()));
+                result.push(tuples.into());
+            }
 
-        Ok(result)
+            Ok(result)
+        })
     }
 
     fn collect_intersect<T, L, R>(
@@ -351,18 +808,20 @@ impl<'d> StableCollector for IncrementalCollector<'d> {
         L: ExpressionExt<T>,
         R: ExpressionExt<T>,
     {
-        let mut result = Vec::<Tuples<T>>::new();
-        let left = intersect.left().collect_stable(self)?;
-        let right = intersect.right().collect_stable(self)?;
-
-        for left_batch in left.iter() {
-            let mut tuples = Vec::new();
-            for right_batch in right.iter() {
-                intersect_helper(&left_batch, &right_batch, |t| tuples.push(t.clone()));
+        self.record("Intersect", intersect, || {
+            let mut result = Vec::<Tuples<T>>::new();
+            let left = intersect.left().collect_stable(self)?;
+            let right = intersect.right().collect_stable(self)?;
+
+            for left_batch in left.iter() {
+                let mut tuples = Vec::new();
+                for right_batch in right.iter() {
+                    intersect_helper(&left_batch, &right_batch, |t| tuples.push(t.clone()));
+                }
+                result.push(tuples.into());
             }
-            result.push(tuples.into());
-        }
-        Ok(result)
+            Ok(result)
+        })
     }
 
     fn collect_difference<T, L, R>(
@@ -374,17 +833,19 @@ impl<'d> StableCollector for IncrementalCollector<'d> {
         L: ExpressionExt<T>,
         R: ExpressionExt<T>,
     {
-        let mut result = Vec::<Tuples<T>>::new();
-        let left = difference.left().collect_stable(self)?;
-        let right = difference.right().collect_stable(self)?;
-        let right_slices = right.iter().map(|t| &t[..]).collect::<Vec<_>>();
-
-        for batch in left.iter() {
-            let mut tuples = Vec::new();
-            diff_helper(&batch, &right_slices, |t| tuples.push(t.clone()));
-            result.push(tuples.into());
-        }
-        Ok(result)
+        self.record("Difference", difference, || {
+            let mut result = Vec::<Tuples<T>>::new();
+            let left = difference.left().collect_stable(self)?;
+            let right = self.collect_current(difference.right())?;
+            let right_slices = right.iter().map(|t| &t[..]).collect::<Vec<_>>();
+
+            for batch in left.iter() {
+                let mut tuples = Vec::new();
+                diff_helper(&batch, &right_slices, |t| tuples.push(t.clone()));
+                result.push(tuples.into());
+            }
+            Ok(result)
+        })
     }
 
     fn collect_project<S, T, E>(&self, project: &Project<S, T, E>) -> Result<Vec<Tuples<T>>, Error>
@@ -393,15 +854,17 @@ impl<'d> StableCollector for IncrementalCollector<'d> {
         S: Tuple,
         E: ExpressionExt<S>,
     {
-        let mut result = Vec::<Tuples<T>>::new();
-        let stable = project.expression().collect_stable(self)?;
-        let mut mapper = project.mapper_mut();
-        for batch in stable.iter() {
-            let mut tuples = Vec::new();
-            project_helper(&batch, |t| tuples.push(mapper(t)));
-            result.push(tuples.into());
-        }
-        Ok(result)
+        self.record("Project", project, || {
+            let mut result = Vec::<Tuples<T>>::new();
+            let stable = project.expression().collect_stable(self)?;
+            let mut mapper = project.mapper_mut();
+            for batch in stable.iter() {
+                let mut tuples = Vec::new();
+                project_helper(&batch, |t| tuples.push(mapper(t)));
+                result.push(tuples.into());
+            }
+            Ok(result)
+        })
     }
 
     fn collect_product<L, R, Left, Right, T>(
@@ -415,21 +878,20 @@ impl<'d> StableCollector for IncrementalCollector<'d> {
         Left: ExpressionExt<L>,
         Right: ExpressionExt<R>,
     {
-        let mut result = Vec::<Tuples<T>>::new();
-        let left = product.left().collect_stable(self)?;
-        let right = product.right().collect_stable(self)?;
-
-        let mut mapper = product.mapper_mut();
-        for left_batch in left.iter() {
-            let mut tuples = Vec::new();
-            for right_batch in right.iter() {
-                product_helper(&left_batch, &right_batch, |v1, v2| {
-                    tuples.push(mapper(v1, v2))
-                });
+        self.record("Product", product, || {
+            let mut result = Vec::<Tuples<T>>::new();
+            let left = product.left().collect_stable(self)?;
+            let right = product.right().collect_stable(self)?;
+
+            for left_batch in left.iter() {
+                let mut tuples = Vec::new();
+                for right_batch in right.iter() {
+                    product_batches(product, &left_batch, &right_batch, &mut tuples);
+                }
+                result.push(tuples.into());
             }
-            result.push(tuples.into());
-        }
-        Ok(result)
+            Ok(result)
+        })
     }
 
     fn collect_join<K, L, R, Left, Right, T>(
@@ -437,40 +899,52 @@ impl<'d> StableCollector for IncrementalCollector<'d> {
         join: &Join<K, L, R, Left, Right, T>,
     ) -> Result<Vec<Tuples<T>>, Error>
     where
-        K: Tuple,
-        L: Tuple,
-        R: Tuple,
+        K: Tuple + 'static,
+        L: Tuple + 'static,
+        R: Tuple + 'static,
         T: Tuple,
         Left: ExpressionExt<L>,
         Right: ExpressionExt<R>,
     {
-        let mut result = Vec::<Tuples<T>>::new();
-        let mut left_key = join.left_key_mut();
-        let mut right_key = join.right_key_mut();
-
-        let left = join.left().collect_stable(self)?;
-        let left: Vec<Tuples<(K, &L)>> = left
-            .iter()
-            .map(|batch| batch.iter().map(|t| (left_key(&t), t)).into())
-            .collect();
-
-        let right = join.right().collect_stable(self)?;
-        let right: Vec<Tuples<(K, &R)>> = right
-            .iter()
-            .map(|batch| batch.iter().map(|t| (right_key(&t), t)).into())
-            .collect();
-
-        let mut joiner = join.mapper_mut();
-        for left_batch in left.iter() {
-            let mut tuples = Vec::new();
-            for right_batch in right.iter() {
-                join_helper(&left_batch, &right_batch, |k, v1, v2| {
-                    tuples.push(joiner(k, v1, v2))
-                });
+        self.record("Join", join, || {
+            let mut result = Vec::<Tuples<T>>::new();
+            let left = self.keyed_stable(join.left(), join.left_index(), join.left_key_mut())?;
+            let right =
+                self.keyed_stable(join.right(), join.right_index(), join.right_key_mut())?;
+
+            for left_batch in left.iter() {
+                let mut tuples = Vec::new();
+                for right_batch in right.iter() {
+                    join_batches(join, &left_batch, &right_batch, &mut tuples);
+                }
+                result.push(tuples.into());
             }
-            result.push(tuples.into());
-        }
-        Ok(result)
+            Ok(result)
+        })
+    }
+
+    fn collect_aggregate<K, S, A, E>(
+        &self,
+        aggregate: &Aggregate<K, S, A, E>,
+    ) -> Result<Vec<Tuples<(K, A)>>, Error>
+    where
+        K: Tuple,
+        S: Tuple,
+        A: Tuple,
+        E: ExpressionExt<S>,
+    {
+        self.record("Aggregate", aggregate, || {
+            let recent = aggregate.expression().collect_recent(self)?;
+            let removed = aggregate.expression().collect_removed(self)?;
+            let groups = aggregate_keys(aggregate, recent.iter().chain(removed.iter()));
+
+            // only the groups that are not affected by recent or removed tuples are stable:
+            let stable = aggregate.expression().collect_stable(self)?;
+            let stable = stable.iter().map(|t| &t[..]).collect::<Vec<_>>();
+            Ok(vec![aggregate_batches(aggregate, &stable, |k| {
+                groups.binary_search(k).is_err()
+            })])
+        })
     }
 
     fn collect_view<T, E>(&self, view: &View<T, E>) -> Result<Vec<Tuples<T>>, Error>
@@ -478,70 +952,411 @@ impl<'d> StableCollector for IncrementalCollector<'d> {
         T: Tuple + 'static,
         E: ExpressionExt<T> + 'static,
     {
-        let mut result = Vec::<Tuples<T>>::new();
-        let table = self.database.view_instance(&view)?;
-        for batch in table.stable().iter() {
-            result.push(batch.clone());
-        }
-        Ok(result)
-    }
-}
-
-/// Is an incremental evaluator for evaluating expressions in a database.
-#[derive(Clone)]
-pub(super) struct Evaluator<'d> {
-    /// Is the database in which the visited expression is evaluated.
-    database: &'d Database,
-}
-
-impl<'d> Evaluator<'d> {
-    /// Creates a new `Evaluator`.
-    pub fn new(database: &'d Database) -> Self {
-        Self { database }
+        self.record("View", view, || {
+            let mut result = Vec::<Tuples<T>>::new();
+            let table = self.database.view_instance(&view)?;
+            for batch in table.stable().iter() {
+                result.push(batch.clone());
+            }
+            Ok(result)
+        })
     }
 }
 
-impl<'d> RecentCollector for Evaluator<'d> {
-    fn collect_full<T>(&self, _: &Full<T>) -> Result<Tuples<T>, Error>
+impl<'d> RemovedCollector for IncrementalCollector<'d> {
+    fn collect_full<T>(&self, full: &Full<T>) -> Result<Tuples<T>, Error>
     where
         T: Tuple,
     {
-        Err(Error::UnsupportedExpression {
-            name: "Full".to_string(),
-            operation: "Evaluate".to_string(),
+        self.record("Full", full, || {
+            // `Full` cannot be evaluated.
+            Err(Error::UnsupportedExpression {
+                name: "Full".to_string(),
+                operation: "Evaluate".to_string(),
+            })
         })
     }
 
-    fn collect_empty<T>(&self, _: &Empty<T>) -> Result<Tuples<T>, Error>
+    fn collect_empty<T>(&self, empty: &Empty<T>) -> Result<Tuples<T>, Error>
     where
         T: Tuple,
     {
-        Ok(Vec::new().into())
+        self.record("Empty", empty, || Ok(Vec::new().into()))
     }
 
     fn collect_singleton<T>(&self, singleton: &Singleton<T>) -> Result<Tuples<T>, Error>
     where
         T: Tuple,
     {
-        Ok(vec![singleton.tuple().clone()].into())
+        self.record("Singleton", singleton, || Ok(Vec::new().into()))
     }
 
     fn collect_relation<T>(&self, relation: &Relation<T>) -> Result<Tuples<T>, Error>
     where
         T: Tuple + 'static,
     {
-        // stabilize the instance corresponding to this relation before evaluating the relation:
-        self.database.stabilize_relation(relation.name())?;
-        let table = self.database.relation_instance(&relation)?;
-
-        assert!(table.recent().is_empty());
-        assert!(table.to_add().is_empty());
-
-        let incremental = IncrementalCollector::new(self.database);
+        self.record("Relation", relation, || {
+            let table = self.database.relation_instance(relation)?;
+            Ok(table.removed().clone())
+        })
+    }
 
-        let mut result = relation.collect_recent(&incremental)?;
-        for batch in relation.collect_stable(&incremental)? {
-            result = result.merge(batch);
+    fn collect_index<K, T>(&self, index: &Index<K, T>) -> Result<Tuples<T>, Error>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static,
+    {
+        self.record("Index", index, || match index.lookup_key() {
+            Some(key) => {
+                let instance = self.database.index_instance(index.reference())?;
+                Ok(lookup_batch(&instance.removed(), key))
+            }
+            None => index.relation().collect_removed(self),
+        })
+    }
+
+    fn collect_select<T, E>(&self, select: &Select<T, E>) -> Result<Tuples<T>, Error>
+    where
+        T: Tuple,
+        E: ExpressionExt<T>,
+    {
+        self.record("Select", select, || {
+            let mut result = Vec::new();
+            let removed = select.expression().collect_removed(self)?;
+            let mut predicate = select.predicate_mut();
+            for tuple in removed.iter() {
+                if predicate(tuple) {
+                    result.push(tuple.clone());
+                }
+            }
+            Ok(result.into())
+        })
+    }
+
+    fn collect_union<T, L, R>(&self, union: &Union<T, L, R>) -> Result<Tuples<T>, Error>
+    where
+        T: Tuple,
+        L: ExpressionExt<T>,
+        R: ExpressionExt<T>,
+    {
+        self.record("Union", union, || {
+            let left_removed = union.left().collect_removed(self)?;
+            let right_removed = union.right().collect_removed(self)?;
+            let removed = left_removed.merge(right_removed);
+            if removed.is_empty() {
+                return Ok(removed);
+            }
+
+            // a tuple removed from one side may still be in the other side:
+            let mut current = self.collect_current(union.left())?;
+            current.extend(self.collect_current(union.right())?);
+            Ok(missing_from(&removed, &current))
+        })
+    }
+
+    fn collect_intersect<T, L, R>(&self, intersect: &Intersect<T, L, R>) -> Result<Tuples<T>, Error>
+    where
+        T: Tuple,
+        L: ExpressionExt<T>,
+        R: ExpressionExt<T>,
+    {
+        self.record("Intersect", intersect, || {
+            let left_removed = intersect.left().collect_removed(self)?;
+            let right_removed = intersect.right().collect_removed(self)?;
+            Ok(left_removed.merge(right_removed))
+        })
+    }
+
+    fn collect_difference<T, L, R>(
+        &self,
+        difference: &Difference<T, L, R>,
+    ) -> Result<Tuples<T>, Error>
+    where
+        T: Tuple,
+        L: ExpressionExt<T>,
+        R: ExpressionExt<T>,
+    {
+        self.record("Difference", difference, || {
+            // tuples that are removed from `left` or are recently added to `right`:
+            let left_removed = difference.left().collect_removed(self)?;
+            let right_recent = difference.right().collect_recent(self)?;
+            Ok(left_removed.merge(right_recent))
+        })
+    }
+
+    fn collect_project<S, T, E>(&self, project: &Project<S, T, E>) -> Result<Tuples<T>, Error>
+    where
+        T: Tuple,
+        S: Tuple,
+        E: ExpressionExt<S>,
+    {
+        self.record("Project", project, || {
+            let removed = project.expression().collect_removed(self)?;
+            if removed.is_empty() {
+                return Ok(Vec::new().into());
+            }
+            let current = self.collect_current(project.expression())?;
+
+            let mut mapper = project.mapper_mut();
+            let mut candidates = Vec::new();
+            project_helper(&removed, |t| candidates.push(mapper(t)));
+
+            // candidates that are still projected from the current tuples are not removed;
+            // the current tuples are projected until every candidate is supported:
+            let mut support = Support::new(candidates.into());
+            'current: for batch in current.iter() {
+                for t in batch.iter() {
+                    if support.derive(&mapper(t)) {
+                        break 'current;
+                    }
+                }
+            }
+            Ok(support.into_unsupported())
+        })
+    }
+
+    fn collect_product<L, R, Left, Right, T>(
+        &self,
+        product: &Product<L, R, Left, Right, T>,
+    ) -> Result<Tuples<T>, Error>
+    where
+        L: Tuple,
+        R: Tuple,
+        T: Tuple,
+        Left: ExpressionExt<L>,
+        Right: ExpressionExt<R>,
+    {
+        self.record("Product", product, || {
+            let left_removed = product.left().collect_removed(self)?;
+            let right_removed = product.right().collect_removed(self)?;
+            if left_removed.is_empty() && right_removed.is_empty() {
+                return Ok(Vec::new().into());
+            }
+            let left_current = self.collect_current(product.left())?;
+            let right_current = self.collect_current(product.right())?;
+
+            let mut candidates = Vec::new();
+            for batch in right_current.iter().chain(std::iter::once(&right_removed)) {
+                product_batches(product, &left_removed, batch, &mut candidates);
+            }
+            for batch in left_current.iter() {
+                product_batches(product, batch, &right_removed, &mut candidates);
+            }
+
+            // candidates that are still derived from the current tuples are not removed;
+            // since `Product` has no key, the current pairs are mapped until every
+            // candidate is supported:
+            let mut mapper = product.mapper_mut();
+            let mut support = Support::new(candidates.into());
+            'current: for l in left_current.iter().flat_map(|batch| batch.iter()) {
+                for r in right_current.iter().flat_map(|batch| batch.iter()) {
+                    if support.derive(&mapper(l, r)) {
+                        break 'current;
+                    }
+                }
+            }
+            Ok(support.into_unsupported())
+        })
+    }
+
+    fn collect_join<K, L, R, Left, Right, T>(
+        &self,
+        join: &Join<K, L, R, Left, Right, T>,
+    ) -> Result<Tuples<T>, Error>
+    where
+        K: Tuple + 'static,
+        L: Tuple + 'static,
+        R: Tuple + 'static,
+        T: Tuple,
+        Left: ExpressionExt<L>,
+        Right: ExpressionExt<R>,
+    {
+        self.record("Join", join, || {
+            let left_removed =
+                self.keyed_removed(join.left(), join.left_index(), join.left_key_mut())?;
+            let right_removed =
+                self.keyed_removed(join.right(), join.right_index(), join.right_key_mut())?;
+            if left_removed.is_empty() && right_removed.is_empty() {
+                return Ok(Vec::new().into());
+            }
+
+            // only the tuples with the keys of the removed tuples are joined again:
+            let keys: Tuples<K> = left_removed
+                .iter()
+                .map(|(k, _)| k.clone())
+                .chain(right_removed.iter().map(|(k, _)| k.clone()))
+                .into();
+            let left_current =
+                self.keyed_current(join.left(), join.left_index(), join.left_key_mut(), &keys)?;
+            let right_current = self.keyed_current(
+                join.right(),
+                join.right_index(),
+                join.right_key_mut(),
+                &keys,
+            )?;
+
+            let mut candidates = Vec::new();
+            for batch in right_current.iter().chain(std::iter::once(&right_removed)) {
+                join_batches(join, &left_removed, batch, &mut candidates);
+            }
+            for batch in left_current.iter() {
+                join_batches(join, batch, &right_removed, &mut candidates);
+            }
+
+            // candidates that are still derived from the current tuples are not removed:
+            let mut current = Vec::new();
+            for left_batch in left_current.iter() {
+                for right_batch in right_current.iter() {
+                    join_batches(join, left_batch, right_batch, &mut current);
+                }
+            }
+            Ok(missing_from(&Tuples::from(candidates), &[current.into()]))
+        })
+    }
+
+    fn collect_aggregate<K, S, A, E>(
+        &self,
+        aggregate: &Aggregate<K, S, A, E>,
+    ) -> Result<Tuples<(K, A)>, Error>
+    where
+        K: Tuple,
+        S: Tuple,
+        A: Tuple,
+        E: ExpressionExt<S>,
+    {
+        self.record("Aggregate", aggregate, || {
+            let recent = aggregate.expression().collect_recent(self)?;
+            let removed = aggregate.expression().collect_removed(self)?;
+            let groups = aggregate_keys(aggregate, recent.iter().chain(removed.iter()));
+            if groups.is_empty() {
+                return Ok(Vec::new().into());
+            }
+
+            // the previous values of the affected groups are removed unless they are unchanged:
+            let stable = aggregate.expression().collect_stable(self)?;
+            let mut previous = stable.iter().map(|t| &t[..]).collect::<Vec<_>>();
+            let mut current = previous.clone();
+            previous.push(&removed);
+            current.push(&recent);
+
+            let previous =
+                aggregate_batches(aggregate, &previous, |k| groups.binary_search(k).is_ok());
+            let current =
+                aggregate_batches(aggregate, &current, |k| groups.binary_search(k).is_ok());
+            Ok(missing_from(&previous, &[current]))
+        })
+    }
+
+    fn collect_view<T, E>(&self, view: &View<T, E>) -> Result<Tuples<T>, Error>
+    where
+        T: Tuple + 'static,
+        E: ExpressionExt<T> + 'static,
+    {
+        self.record("View", view, || {
+            let table = self.database.view_instance(view)?;
+            Ok(table.removed().clone())
+        })
+    }
+}
+
+/// Is an incremental evaluator for evaluating expressions in a database.
+#[derive(Clone)]
+pub(super) struct Evaluator<'d> {
+    /// Is the database in which the visited expression is evaluated.
+    database: &'d Database,
+
+    /// Is the profiler that measures the evaluation of sub-expressions, if any.
+    profiler: Option<&'d Profiler>,
+}
+
+impl<'d> Evaluator<'d> {
+    /// Creates a new `Evaluator`.
+    pub fn new(database: &'d Database) -> Self {
+        Self {
+            database,
+            profiler: None,
+        }
+    }
+
+    /// Creates a new `Evaluator` that measures the evaluation of sub-expressions by
+    /// `profiler`.
+    pub fn with_profiler(database: &'d Database, profiler: &'d Profiler) -> Self {
+        Self {
+            database,
+            profiler: Some(profiler),
+        }
+    }
+
+    /// Returns a collector for incremental evaluation that shares the profiler of the
+    /// receiver.
+    fn incremental(&self) -> IncrementalCollector<'d> {
+        IncrementalCollector {
+            database: self.database,
+            profiler: self.profiler,
+        }
+    }
+}
+
+impl<'d> RecentCollector for Evaluator<'d> {
+    fn collect_full<T>(&self, _: &Full<T>) -> Result<Tuples<T>, Error>
+    where
+        T: Tuple,
+    {
+        Err(Error::UnsupportedExpression {
+            name: "Full".to_string(),
+            operation: "Evaluate".to_string(),
+        })
+    }
+
+    fn collect_empty<T>(&self, _: &Empty<T>) -> Result<Tuples<T>, Error>
+    where
+        T: Tuple,
+    {
+        Ok(Vec::new().into())
+    }
+
+    fn collect_singleton<T>(&self, singleton: &Singleton<T>) -> Result<Tuples<T>, Error>
+    where
+        T: Tuple,
+    {
+        Ok(vec![singleton.tuple().clone()].into())
+    }
+
+    fn collect_relation<T>(&self, relation: &Relation<T>) -> Result<Tuples<T>, Error>
+    where
+        T: Tuple + 'static,
+    {
+        // stabilize the instance corresponding to this relation before evaluating the relation:
+        self.database.stabilize_relation(relation.name())?;
+        let table = self.database.relation_instance(&relation)?;
+
+        assert!(table.recent().is_empty());
+        assert!(table.to_add().is_empty());
+        assert!(table.to_remove().is_empty());
+
+        let incremental = self.incremental();
+
+        let mut result = relation.collect_recent(&incremental)?;
+        for batch in relation.collect_stable(&incremental)? {
+            result = result.merge(batch);
+        }
+
+        Ok(result)
+    }
+
+    fn collect_index<K, T>(&self, index: &Index<K, T>) -> Result<Tuples<T>, Error>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static,
+    {
+        // stabilize the relation of the index before evaluating the index:
+        self.database.stabilize_relation(index.relation().name())?;
+
+        let incremental = self.incremental();
+
+        let mut result = index.collect_recent(&incremental)?;
+        for batch in index.collect_stable(&incremental)? {
+            result = result.merge(batch);
         }
 
         Ok(result)
@@ -560,7 +1375,7 @@ impl<'d> RecentCollector for Evaluator<'d> {
             self.database.stabilize_view(&r)?;
         }
 
-        let incremental = IncrementalCollector::new(self.database);
+        let incremental = self.incremental();
 
         let mut result = select.collect_recent(&incremental)?;
         for batch in select.collect_stable(&incremental)? {
@@ -582,7 +1397,7 @@ impl<'d> RecentCollector for Evaluator<'d> {
             self.database.stabilize_view(&r)?;
         }
 
-        let incremental = IncrementalCollector::new(self.database);
+        let incremental = self.incremental();
 
         let mut result = union.collect_recent(&incremental)?;
         for batch in union.collect_stable(&incremental)? {
@@ -604,7 +1419,7 @@ impl<'d> RecentCollector for Evaluator<'d> {
             self.database.stabilize_view(&r)?;
         }
 
-        let incremental = IncrementalCollector::new(self.database);
+        let incremental = self.incremental();
 
         let mut result = intersect.collect_recent(&incremental)?;
         for batch in intersect.collect_stable(&incremental)? {
@@ -630,7 +1445,7 @@ impl<'d> RecentCollector for Evaluator<'d> {
             self.database.stabilize_view(&r)?;
         }
 
-        let incremental = IncrementalCollector::new(self.database);
+        let incremental = self.incremental();
 
         let mut result = difference.collect_recent(&incremental)?;
         for batch in difference.collect_stable(&incremental)? {
@@ -653,7 +1468,7 @@ impl<'d> RecentCollector for Evaluator<'d> {
             self.database.stabilize_view(&r)?;
         }
 
-        let incremental = IncrementalCollector::new(self.database);
+        let incremental = self.incremental();
 
         let mut result = project.collect_recent(&incremental)?;
         for batch in project.collect_stable(&incremental)? {
@@ -680,7 +1495,7 @@ impl<'d> RecentCollector for Evaluator<'d> {
             self.database.stabilize_view(&r)?;
         }
 
-        let incremental = IncrementalCollector::new(self.database);
+        let incremental = self.incremental();
 
         let mut result = product.collect_recent(&incremental)?;
         for batch in product.collect_stable(&incremental)? {
@@ -695,9 +1510,9 @@ impl<'d> RecentCollector for Evaluator<'d> {
         join: &Join<K, L, R, Left, Right, T>,
     ) -> Result<Tuples<T>, Error>
     where
-        K: Tuple,
-        L: Tuple,
-        R: Tuple,
+        K: Tuple + 'static,
+        L: Tuple + 'static,
+        R: Tuple + 'static,
         T: Tuple,
         Left: ExpressionExt<L>,
         Right: ExpressionExt<R>,
@@ -709,7 +1524,7 @@ impl<'d> RecentCollector for Evaluator<'d> {
             self.database.stabilize_view(&r)?;
         }
 
-        let incremental = IncrementalCollector::new(self.database);
+        let incremental = self.incremental();
 
         let mut result = join.collect_recent(&incremental)?;
         for batch in join.collect_stable(&incremental)? {
@@ -719,6 +1534,33 @@ impl<'d> RecentCollector for Evaluator<'d> {
         Ok(result)
     }
 
+    fn collect_aggregate<K, S, A, E>(
+        &self,
+        aggregate: &Aggregate<K, S, A, E>,
+    ) -> Result<Tuples<(K, A)>, Error>
+    where
+        K: Tuple,
+        S: Tuple,
+        A: Tuple,
+        E: ExpressionExt<S>,
+    {
+        for r in aggregate.relation_dependencies() {
+            self.database.stabilize_relation(r)?;
+        }
+        for r in aggregate.view_dependencies() {
+            self.database.stabilize_view(r)?;
+        }
+
+        let incremental = self.incremental();
+
+        let mut result = aggregate.collect_recent(&incremental)?;
+        for batch in aggregate.collect_stable(&incremental)? {
+            result = result.merge(batch);
+        }
+
+        Ok(result)
+    }
+
     fn collect_view<T, E>(&self, view: &View<T, E>) -> Result<Tuples<T>, Error>
     where
         T: Tuple + 'static,
@@ -728,8 +1570,9 @@ impl<'d> RecentCollector for Evaluator<'d> {
         let table = self.database.view_instance(view)?;
         assert!(table.recent().is_empty());
         assert!(table.to_add().is_empty());
+        assert!(table.to_remove().is_empty());
 
-        let incremental = IncrementalCollector::new(self.database);
+        let incremental = self.incremental();
 
         let mut result = view.collect_recent(&incremental)?;
         for batch in view.collect_stable(&incremental)? {
@@ -1102,39 +1945,152 @@ mod tests {
         }
     }
     #[test]
-    fn test_evaluate_union() {
+    fn test_evaluate_index() {
         {
             let mut database = Database::new();
-            let r = database.add_relation::<i32>("r").unwrap();
-            let s = database.add_relation::<i32>("s").unwrap();
-            let u = r.builder().union(s).build();
+            let r = database.add_relation::<(i32, i32)>("r").unwrap();
+            database
+                .insert(&r, vec![(1, 4), (2, 2), (1, 3)].into())
+                .unwrap();
+            database.evaluate(&r).unwrap();
+            database.insert(&r, vec![(3, 1)].into()).unwrap();
 
-            let result = database.evaluate(&u).unwrap();
-            assert_eq!(Tuples::<i32>::from(vec![]), result);
-        }
-        {
-            let mut database = Database::new();
-            let r = database.add_relation::<i32>("r").unwrap();
-            let s = database.add_relation::<i32>("s").unwrap();
-            database.insert(&r, vec![1, 2, 3].into()).unwrap();
-            let u = r.builder().union(s).build();
+            // the index contains the existing tuples of the relation:
+            let index = database.add_index(&r, |t| t.0).unwrap();
+            assert_eq!(
+                Tuples::from(vec![(1, 3), (1, 4), (2, 2), (3, 1)]),
+                database.evaluate(&index).unwrap()
+            );
+            assert_eq!(
+                Tuples::from(vec![(1, 3), (1, 4)]),
+                database.evaluate(&index.lookup(1)).unwrap()
+            );
+            assert_eq!(
+                Tuples::<(i32, i32)>::from(vec![]),
+                database.evaluate(&index.lookup(4)).unwrap()
+            );
 
-            let result = database.evaluate(&u).unwrap();
-            assert_eq!(Tuples::<i32>::from(vec![1, 2, 3]), result);
+            {
+                // the batches of the index mirror the batches of the relation:
+                let instance = database.relation_instance(&r).unwrap();
+                let index = database
+                    .index_instance::<i32, (i32, i32)>(index.reference())
+                    .unwrap();
+                assert_eq!(instance.stable().len(), index.stable().len());
+            }
+
+            database.remove(&r, vec![(1, 3)].into()).unwrap();
+            database.insert(&r, vec![(1, 5)].into()).unwrap();
+            assert_eq!(
+                Tuples::from(vec![(1, 4), (1, 5)]),
+                database.evaluate(&index.lookup(1)).unwrap()
+            );
         }
         {
             let mut database = Database::new();
-            let r = database.add_relation::<i32>("r").unwrap();
-            let s = database.add_relation::<i32>("s").unwrap();
-            database.insert(&s, vec![4, 5].into()).unwrap();
-            let u = r.builder().union(s).build();
-
-            let result = database.evaluate(&u).unwrap();
-            assert_eq!(Tuples::<i32>::from(vec![4, 5]), result);
-        }
+            let r = database.add_relation::<(i32, i32)>("r").unwrap();
+            let s = database.add_relation::<(i32, i32)>("s").unwrap();
+            let r_index = database.add_index(&r, |t| t.0).unwrap();
+            let s_index = database.add_index(&s, |t| t.1).unwrap();
+            let join = r_index
+                .with_key()
+                .join(s_index.with_key())
+                .on(|_, &l, &r| (l.1, r.0))
+                .build();
+            let view = database.store_view(join.clone()).unwrap();
+            let lookup = database
+                .store_view(r_index.lookup(1).builder().project(|t| t.1).build())
+                .unwrap();
 
-        {
-            let database = Database::new();
+            database
+                .insert(&r, vec![(1, 4), (2, 2), (1, 3)].into())
+                .unwrap();
+            database
+                .insert(&s, vec![(5, 1), (6, 2), (7, 3)].into())
+                .unwrap();
+            assert_eq!(
+                Tuples::from(vec![(2, 6), (3, 5), (4, 5)]),
+                database.evaluate(&join).unwrap()
+            );
+            assert_eq!(
+                Tuples::from(vec![(2, 6), (3, 5), (4, 5)]),
+                database.evaluate(&view).unwrap()
+            );
+            assert_eq!(
+                Tuples::from(vec![3, 4]),
+                database.evaluate(&lookup).unwrap()
+            );
+
+            database.insert(&r, vec![(3, 0)].into()).unwrap();
+            database.remove(&r, vec![(1, 4)].into()).unwrap();
+            database.remove(&s, vec![(6, 2)].into()).unwrap();
+            assert_eq!(
+                Tuples::from(vec![(0, 7), (3, 5)]),
+                database.evaluate(&view).unwrap()
+            );
+            assert_eq!(Tuples::from(vec![3]), database.evaluate(&lookup).unwrap());
+            assert_eq!(
+                Tuples::from(vec![(0, 7), (3, 5)]),
+                database.clone().evaluate(&join).unwrap()
+            );
+        }
+        {
+            let mut database = Database::new();
+            let mut other = Database::new();
+            let r = database.add_relation::<(i32, i32)>("r").unwrap();
+            let s = database.add_relation::<(i32, i32)>("s").unwrap();
+            other.add_relation::<(i32, i32)>("r").unwrap();
+            other.add_relation::<(i32, i32)>("s").unwrap();
+            let r_index = database.add_index(&r, |t| t.0).unwrap();
+            let join = r_index
+                .with_key()
+                .join(s.builder().with_key(|t| t.0))
+                .on(|_, &l, &r| (l.1, r.1))
+                .build();
+
+            // the index of the join doesn't exist in `other`:
+            assert!(database.evaluate(&join).is_ok());
+            assert!(matches!(
+                other.evaluate(&join),
+                Err(Error::IndexNotFound { position: 0, .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_evaluate_union() {
+        {
+            let mut database = Database::new();
+            let r = database.add_relation::<i32>("r").unwrap();
+            let s = database.add_relation::<i32>("s").unwrap();
+            let u = r.builder().union(s).build();
+
+            let result = database.evaluate(&u).unwrap();
+            assert_eq!(Tuples::<i32>::from(vec![]), result);
+        }
+        {
+            let mut database = Database::new();
+            let r = database.add_relation::<i32>("r").unwrap();
+            let s = database.add_relation::<i32>("s").unwrap();
+            database.insert(&r, vec![1, 2, 3].into()).unwrap();
+            let u = r.builder().union(s).build();
+
+            let result = database.evaluate(&u).unwrap();
+            assert_eq!(Tuples::<i32>::from(vec![1, 2, 3]), result);
+        }
+        {
+            let mut database = Database::new();
+            let r = database.add_relation::<i32>("r").unwrap();
+            let s = database.add_relation::<i32>("s").unwrap();
+            database.insert(&s, vec![4, 5].into()).unwrap();
+            let u = r.builder().union(s).build();
+
+            let result = database.evaluate(&u).unwrap();
+            assert_eq!(Tuples::<i32>::from(vec![4, 5]), result);
+        }
+
+        {
+            let database = Database::new();
             let r = Singleton::new(42);
             let s = Singleton::new(43);
             let u = r.builder().union(s).build();
@@ -1360,6 +2316,191 @@ mod tests {
             assert!(database.evaluate(&u).is_err());
         }
     }
+    #[test]
+    fn test_evaluate_aggregate() {
+        {
+            let mut database = Database::new();
+            let r = database.add_relation::<(i32, i32)>("r").unwrap();
+            let count = r
+                .builder()
+                .group_by(|t| t.0)
+                .aggregate(0, |c, _| c + 1)
+                .build();
+
+            let result = database.evaluate(&count).unwrap();
+            assert_eq!(Tuples::<(i32, i32)>::from(vec![]), result);
+        }
+        {
+            let mut database = Database::new();
+            let r = database.add_relation::<(i32, i32)>("r").unwrap();
+            let sum = r
+                .builder()
+                .group_by(|t| t.0)
+                .aggregate(0, |s, t| s + t.1)
+                .build();
+            database
+                .insert(&r, vec![(1, 4), (2, 2), (1, 3)].into())
+                .unwrap();
+
+            let result = database.evaluate(&sum).unwrap();
+            assert_eq!(Tuples::from(vec![(1, 7), (2, 2)]), result);
+        }
+        {
+            let database = Database::new();
+            let s = Singleton::new(42);
+            let max = s
+                .builder()
+                .group_by(|_| ())
+                .aggregate(0, |&m, &t| std::cmp::max(m, t))
+                .build();
+
+            let result = database.evaluate(&max).unwrap();
+            assert_eq!(Tuples::from(vec![((), 42)]), result);
+        }
+        {
+            let mut database = Database::new();
+            let r = database.add_relation::<(i32, i32)>("r").unwrap();
+            let s = database.add_relation::<i32>("s").unwrap();
+            let count = r
+                .builder()
+                .group_by(|t| t.0)
+                .aggregate(0, |c, _| c + 1)
+                .build();
+            let join = count
+                .builder()
+                .with_key(|t| t.0)
+                .join(s.builder().with_key(|&t| t))
+                .on(|_, l, _| *l)
+                .build();
+            let view = database.store_view(join).unwrap();
+
+            database
+                .insert(&r, vec![(1, 1), (1, 2), (2, 1)].into())
+                .unwrap();
+            database.insert(&s, vec![1, 2].into()).unwrap();
+            assert_eq!(
+                vec![(1, 2), (2, 1)],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+
+            database.insert(&r, vec![(1, 3), (3, 1)].into()).unwrap();
+            assert_eq!(
+                vec![(1, 3), (2, 1)],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+
+            database.remove(&r, vec![(1, 1), (2, 1)].into()).unwrap();
+            assert_eq!(
+                vec![(1, 2)],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+        }
+        {
+            let database = Database::new();
+            let mut dummy = Database::new();
+            let r = dummy.add_relation::<(i32, i32)>("r").unwrap();
+            let count = r
+                .builder()
+                .group_by(|t| t.0)
+                .aggregate(0, |c, _| c + 1)
+                .build();
+            assert!(database.evaluate(&count).is_err());
+        }
+    }
+
+    #[test]
+    fn test_evaluate_aggregate_view() {
+        {
+            let mut database = Database::new();
+            let r = database.add_relation::<(i32, i32)>("r").unwrap();
+            database.insert(&r, vec![(1, 1), (1, 3)].into()).unwrap();
+            let parities = r.builder().project(|t| (t.0, t.1 % 2)).build();
+            let view = database
+                .store_view(
+                    parities
+                        .builder()
+                        .group_by(|t| t.0)
+                        .aggregate(0, |c, _| c + 1)
+                        .build(),
+                )
+                .unwrap();
+            assert_eq!(
+                vec![(1, 1)],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+
+            // tuples that are already folded are not folded again:
+            database.insert(&r, vec![(1, 5), (2, 2)].into()).unwrap();
+            assert_eq!(
+                vec![(1, 1), (2, 1)],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+
+            database.insert(&r, vec![(1, 2)].into()).unwrap();
+            assert_eq!(
+                vec![(1, 2), (2, 1)],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+
+            database.remove(&r, vec![(1, 1), (1, 3)].into()).unwrap();
+            assert_eq!(
+                vec![(1, 2), (2, 1)],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+
+            database.remove(&r, vec![(1, 5), (2, 2)].into()).unwrap();
+            assert_eq!(
+                vec![(1, 1)],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+        }
+        {
+            let mut database = Database::new();
+            let r = database.add_relation::<(i32, i32)>("r").unwrap();
+            let v = database.store_view(r.clone()).unwrap();
+            let pairs = r
+                .builder()
+                .with_key(|t| t.0)
+                .join(v.builder().with_key(|t| t.0))
+                .on(|&k, l, r| (k, l.1, r.1))
+                .build();
+            let view = database
+                .store_view(
+                    pairs
+                        .builder()
+                        .group_by(|t| t.0)
+                        .aggregate(0, |c, _| c + 1)
+                        .build(),
+                )
+                .unwrap();
+
+            database
+                .insert(&r, vec![(1, 1), (1, 2), (2, 1)].into())
+                .unwrap();
+            assert_eq!(
+                vec![(1, 4), (2, 1)],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+
+            database.insert(&r, vec![(1, 3)].into()).unwrap();
+            assert_eq!(
+                vec![(1, 9), (2, 1)],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+
+            let cloned = database.clone();
+            database.remove(&r, vec![(1, 1)].into()).unwrap();
+            assert_eq!(
+                vec![(1, 4), (2, 1)],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+            assert_eq!(
+                vec![(1, 9), (2, 1)],
+                cloned.evaluate(&view).unwrap().into_tuples()
+            );
+        }
+    }
+
     #[test]
     fn test_evaluate_view() {
         {
@@ -1529,22 +2670,86 @@ mod tests {
             let r = database.add_relation::<(i32, i32)>("r").unwrap();
             let s = database.add_relation::<(i32, i32)>("s").unwrap();
             let rs = r.builder().difference(s.clone()).build();
+            let view = database.store_view(rs).unwrap();
 
-            assert!(database.store_view(rs).is_err());
+            database
+                .insert(&r, vec![(1, 4), (2, 2), (1, 3)].into())
+                .unwrap();
+            database.insert(&s, vec![(1, 4), (3, 2)].into()).unwrap();
+
+            let result = database.evaluate(&view).unwrap();
+            assert_eq!(Tuples::<(i32, i32)>::from(vec![(1, 3), (2, 2)]), result);
+
+            // tuples of `s` are removed from the view:
+            database.insert(&s, vec![(2, 2)].into()).unwrap();
+            let result = database.evaluate(&view).unwrap();
+            assert_eq!(Tuples::<(i32, i32)>::from(vec![(1, 3)]), result);
+
+            // tuples of `r` that are already in `s` are not added to the view:
+            database.insert(&r, vec![(3, 2), (4, 4)].into()).unwrap();
+            let result = database.evaluate(&view).unwrap();
+            assert_eq!(Tuples::<(i32, i32)>::from(vec![(1, 3), (4, 4)]), result);
         }
         {
             let mut database = Database::new();
             let r = database.add_relation::<(i32, i32)>("r").unwrap();
             let s = database.add_relation::<(i32, i32)>("s").unwrap();
             let t = database.add_relation::<(i32, i32)>("t").unwrap();
-            let rs = r.builder().difference(s).build();
+            let rs = r.builder().difference(s.clone()).build();
             let rs_t = rs
                 .builder()
                 .with_key(|t| t.0)
                 .join(t.builder().with_key(|t| t.0))
                 .on(|_, &l, &r| l.1 * r.1)
                 .build();
-            assert!(database.store_view(rs_t).is_err());
+            let view = database.store_view(rs_t).unwrap();
+
+            database
+                .insert(&r, vec![(1, 4), (2, 2), (1, 3)].into())
+                .unwrap();
+            database.insert(&s, vec![(1, 4), (3, 2)].into()).unwrap();
+            database
+                .insert(&t, vec![(1, 40), (2, 41), (3, 42), (4, 43)].into())
+                .unwrap();
+
+            let result = database.evaluate(&view).unwrap();
+            assert_eq!(Tuples::<i32>::from(vec![82, 120]), result);
+
+            database.insert(&s, vec![(1, 3)].into()).unwrap();
+            let result = database.evaluate(&view).unwrap();
+            assert_eq!(Tuples::<i32>::from(vec![82]), result);
+        }
+        {
+            // Removals propagate through views over views:
+            let mut database = Database::new();
+            let r = database.add_relation::<i32>("r").unwrap();
+            let s = database.add_relation::<i32>("s").unwrap();
+            let t = database.add_relation::<i32>("t").unwrap();
+            let v1 = database
+                .store_view(r.builder().difference(s.clone()).build())
+                .unwrap();
+            let v2 = database
+                .store_view(v1.builder().project(|&t| t % 2).build())
+                .unwrap();
+            let v3 = database
+                .store_view(t.builder().difference(v2.clone()).build())
+                .unwrap();
+
+            database.insert(&r, vec![1, 2, 3].into()).unwrap();
+            database.insert(&t, vec![0, 1, 2].into()).unwrap();
+            assert_eq!(vec![1, 2, 3], database.evaluate(&v1).unwrap().into_tuples());
+            assert_eq!(vec![0, 1], database.evaluate(&v2).unwrap().into_tuples());
+            assert_eq!(vec![2], database.evaluate(&v3).unwrap().into_tuples());
+
+            database.insert(&s, vec![1].into()).unwrap();
+            assert_eq!(vec![2, 3], database.evaluate(&v1).unwrap().into_tuples());
+            assert_eq!(vec![0, 1], database.evaluate(&v2).unwrap().into_tuples());
+            assert_eq!(vec![2], database.evaluate(&v3).unwrap().into_tuples());
+
+            database.insert(&s, vec![3].into()).unwrap();
+            assert_eq!(vec![2], database.evaluate(&v1).unwrap().into_tuples());
+            assert_eq!(vec![0], database.evaluate(&v2).unwrap().into_tuples());
+            assert_eq!(vec![1, 2], database.evaluate(&v3).unwrap().into_tuples());
         }
         {
             // Test new view initialization after a refering relation is already stable:
@@ -1602,4 +2807,473 @@ mod tests {
             assert_eq!(vec![1, 2], database.evaluate(&v3).unwrap().into_tuples());
         }
     }
+
+    #[test]
+    fn test_evaluate_view_remove() {
+        {
+            let mut database = Database::new();
+            let r = database.add_relation::<(i32, i32)>("r").unwrap();
+            let view = database
+                .store_view(
+                    r.builder()
+                        .group_by(|t| t.0)
+                        .aggregate(0, |s, t| s + t.1)
+                        .build(),
+                )
+                .unwrap();
+
+            database
+                .insert(&r, vec![(1, 1), (1, 2), (2, 3)].into())
+                .unwrap();
+            assert_eq!(
+                vec![(1, 3), (2, 3)],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+
+            database.insert(&r, vec![(1, 4), (3, 5)].into()).unwrap();
+            assert_eq!(
+                vec![(1, 7), (2, 3), (3, 5)],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+
+            // the value of a group that is unchanged by an update is not retracted:
+            database.remove(&r, vec![(1, 1), (2, 3)].into()).unwrap();
+            database.insert(&r, vec![(1, 1), (2, 6)].into()).unwrap();
+            assert_eq!(
+                vec![(1, 7), (2, 6), (3, 5)],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+
+            database.remove(&r, vec![(3, 5)].into()).unwrap();
+            assert_eq!(
+                vec![(1, 7), (2, 6)],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+        }
+        {
+            let mut database = Database::new();
+            let r = database.add_relation::<i32>("r").unwrap();
+            let view = database
+                .store_view(r.builder().select(|&t| t % 2 == 1).build())
+                .unwrap();
+
+            database.insert(&r, vec![1, 2, 3, 4, 5].into()).unwrap();
+            assert_eq!(
+                vec![1, 3, 5],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+
+            database.remove(&r, vec![2, 3].into()).unwrap();
+            assert_eq!(vec![1, 5], database.evaluate(&view).unwrap().into_tuples());
+        }
+        {
+            let mut database = Database::new();
+            let r = database.add_relation::<(i32, i32)>("r").unwrap();
+            let view = database
+                .store_view(r.builder().project(|t| t.0).build())
+                .unwrap();
+
+            database
+                .insert(&r, vec![(1, 1), (1, 2), (2, 1)].into())
+                .unwrap();
+            assert_eq!(vec![1, 2], database.evaluate(&view).unwrap().into_tuples());
+
+            // `1` is still projected from `(1, 2)`:
+            database.remove(&r, vec![(1, 1), (2, 1)].into()).unwrap();
+            assert_eq!(vec![1], database.evaluate(&view).unwrap().into_tuples());
+
+            database.remove(&r, vec![(1, 2)].into()).unwrap();
+            assert_eq!(
+                Vec::<i32>::new(),
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+        }
+        {
+            let mut database = Database::new();
+            let r = database.add_relation::<i32>("r").unwrap();
+            let s = database.add_relation::<i32>("s").unwrap();
+            let view = database
+                .store_view(r.builder().product(s.clone()).on(|&l, &r| l + r).build())
+                .unwrap();
+
+            database.insert(&r, vec![1, 2].into()).unwrap();
+            database.insert(&s, vec![10, 20].into()).unwrap();
+            assert_eq!(
+                vec![11, 12, 21, 22],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+
+            database.remove(&r, vec![1].into()).unwrap();
+            database.remove(&s, vec![20].into()).unwrap();
+            assert_eq!(vec![12], database.evaluate(&view).unwrap().into_tuples());
+        }
+        {
+            let mut database = Database::new();
+            let r = database.add_relation::<(i32, i32)>("r").unwrap();
+            let s = database.add_relation::<(i32, i32)>("s").unwrap();
+            let view = database
+                .store_view(
+                    r.builder()
+                        .with_key(|t| t.0)
+                        .join(s.builder().with_key(|t| t.0))
+                        .on(|_, l, r| l.1 + r.1)
+                        .build(),
+                )
+                .unwrap();
+
+            database
+                .insert(&r, vec![(1, 1), (1, 2), (2, 3)].into())
+                .unwrap();
+            database
+                .insert(&s, vec![(1, 10), (2, 20), (2, 30)].into())
+                .unwrap();
+            assert_eq!(
+                vec![11, 12, 23, 33],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+
+            database.remove(&r, vec![(1, 1)].into()).unwrap();
+            database.remove(&s, vec![(2, 20)].into()).unwrap();
+            assert_eq!(
+                vec![12, 33],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+
+            // `23` can be derived again:
+            database.insert(&s, vec![(2, 20)].into()).unwrap();
+            assert_eq!(
+                vec![12, 23, 33],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+        }
+        {
+            let mut database = Database::new();
+            let r = database.add_relation::<i32>("r").unwrap();
+            let s = database.add_relation::<i32>("s").unwrap();
+            let view = database
+                .store_view(r.builder().union(s.clone()).build())
+                .unwrap();
+
+            database.insert(&r, vec![1, 2, 3].into()).unwrap();
+            database.insert(&s, vec![3, 4].into()).unwrap();
+            assert_eq!(
+                vec![1, 2, 3, 4],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+
+            // `3` is still in `s`:
+            database.remove(&r, vec![1, 3].into()).unwrap();
+            assert_eq!(
+                vec![2, 3, 4],
+                database.evaluate(&view).unwrap().into_tuples()
+            );
+
+            database.remove(&s, vec![3].into()).unwrap();
+            assert_eq!(vec![2, 4], database.evaluate(&view).unwrap().into_tuples());
+        }
+        {
+            let mut database = Database::new();
+            let r = database.add_relation::<i32>("r").unwrap();
+            let s = database.add_relation::<i32>("s").unwrap();
+            let view = database
+                .store_view(r.builder().intersect(s.clone()).build())
+                .unwrap();
+
+            database.insert(&r, vec![1, 2, 3].into()).unwrap();
+            database.insert(&s, vec![2, 3, 4].into()).unwrap();
+            assert_eq!(vec![2, 3], database.evaluate(&view).unwrap().into_tuples());
+
+            database.remove(&s, vec![3].into()).unwrap();
+            assert_eq!(vec![2], database.evaluate(&view).unwrap().into_tuples());
+
+            database.insert(&s, vec![3].into()).unwrap();
+            database.remove(&r, vec![2].into()).unwrap();
+            assert_eq!(vec![3], database.evaluate(&view).unwrap().into_tuples());
+        }
+        {
+            // Removing from the right side of `Difference` adds tuples to the view:
+            let mut database = Database::new();
+            let r = database.add_relation::<i32>("r").unwrap();
+            let s = database.add_relation::<i32>("s").unwrap();
+            let view = database
+                .store_view(r.builder().difference(s.clone()).build())
+                .unwrap();
+
+            database.insert(&r, vec![1, 2, 3].into()).unwrap();
+            database.insert(&s, vec![2, 3].into()).unwrap();
+            assert_eq!(vec![1], database.evaluate(&view).unwrap().into_tuples());
+
+            database.remove(&s, vec![2].into()).unwrap();
+            database.remove(&r, vec![1].into()).unwrap();
+            assert_eq!(vec![2], database.evaluate(&view).unwrap().into_tuples());
+        }
+        {
+            // Removals propagate to views over views:
+            let mut database = Database::new();
+            let r = database.add_relation::<i32>("r").unwrap();
+            let v1 = database.store_view(r.clone()).unwrap();
+            let v2 = database
+                .store_view(v1.builder().project(|&t| t * 10).build())
+                .unwrap();
+
+            database.insert(&r, vec![1, 2, 3].into()).unwrap();
+            assert_eq!(
+                vec![10, 20, 30],
+                database.evaluate(&v2).unwrap().into_tuples()
+            );
+
+            database.remove(&r, vec![2].into()).unwrap();
+            assert_eq!(vec![1, 3], database.evaluate(&v1).unwrap().into_tuples());
+            assert_eq!(vec![10, 30], database.evaluate(&v2).unwrap().into_tuples());
+        }
+        {
+            // Removals from `Join` only join the tuples with the keys of removed tuples:
+            use std::sync::{
+                atomic::{AtomicUsize, Ordering},
+                Arc,
+            };
+
+            let mut database = Database::new();
+            let r = database.add_relation::<(i32, i32)>("r").unwrap();
+            let s = database.add_relation::<(i32, i32)>("s").unwrap();
+            let calls = Arc::new(AtomicUsize::new(0));
+            let counter = calls.clone();
+            let view = database
+                .store_view(
+                    r.builder()
+                        .with_key(|t| t.0)
+                        .join(s.builder().with_key(|t| t.0))
+                        .on(move |_, &l, &r| {
+                            counter.fetch_add(1, Ordering::SeqCst);
+                            l.1 + r.1
+                        })
+                        .build(),
+                )
+                .unwrap();
+
+            database
+                .insert(&r, (0..100).map(|i| (i, i)).into())
+                .unwrap();
+            database
+                .insert(&s, (0..100).map(|i| (i, 1000)).into())
+                .unwrap();
+            database.insert(&s, vec![(5, 2000)].into()).unwrap();
+            assert_eq!(101, database.evaluate(&view).unwrap().len());
+
+            calls.store(0, Ordering::SeqCst);
+            database.remove(&r, vec![(5, 5)].into()).unwrap();
+            database.remove(&s, vec![(7, 1000)].into()).unwrap();
+            let result = database.evaluate(&view).unwrap();
+            assert_eq!(98, result.len());
+            assert!(result.binary_search(&1005).is_err());
+            assert!(result.binary_search(&2005).is_err());
+            assert!(result.binary_search(&1007).is_err());
+
+            // the removed pairs (with keys 5 and 7) are joined, but no other pair:
+            assert_eq!(3, calls.load(Ordering::SeqCst));
+        }
+        {
+            // Removals from `Project` and `Product` map the current tuples (pairs) again:
+            use std::sync::{
+                atomic::{AtomicUsize, Ordering},
+                Arc,
+            };
+
+            let mut database = Database::new();
+            let r = database.add_relation::<i32>("r").unwrap();
+            let s = database.add_relation::<i32>("s").unwrap();
+            let project_calls = Arc::new(AtomicUsize::new(0));
+            let product_calls = Arc::new(AtomicUsize::new(0));
+            let project_counter = project_calls.clone();
+            let product_counter = product_calls.clone();
+            let project = database
+                .store_view(
+                    r.builder()
+                        .project(move |&t| {
+                            project_counter.fetch_add(1, Ordering::SeqCst);
+                            t * 2
+                        })
+                        .build(),
+                )
+                .unwrap();
+            let product = database
+                .store_view(
+                    r.builder()
+                        .product(s.clone())
+                        .on(move |&l, &r| {
+                            product_counter.fetch_add(1, Ordering::SeqCst);
+                            l * 100 + r
+                        })
+                        .build(),
+                )
+                .unwrap();
+
+            database
+                .insert(&r, (0..10).collect::<Vec<_>>().into())
+                .unwrap();
+            database
+                .insert(&s, (0..10).collect::<Vec<_>>().into())
+                .unwrap();
+            assert_eq!(10, database.evaluate(&project).unwrap().len());
+            assert_eq!(100, database.evaluate(&product).unwrap().len());
+
+            project_calls.store(0, Ordering::SeqCst);
+            product_calls.store(0, Ordering::SeqCst);
+            database.remove(&r, vec![5].into()).unwrap();
+            assert_eq!(9, database.evaluate(&project).unwrap().len());
+            assert_eq!(90, database.evaluate(&product).unwrap().len());
+
+            // the removed tuple is mapped once and every current tuple is mapped again:
+            assert_eq!(1 + 9, project_calls.load(Ordering::SeqCst));
+            // the pairs of the removed tuple are mapped and every current pair is mapped again:
+            assert_eq!(10 + 9 * 10, product_calls.load(Ordering::SeqCst));
+        }
+    }
+
+    #[test]
+    fn test_evaluate_recursive_view() {
+        fn closure(
+            database: &mut Database,
+            edges: &Relation<(i32, i32)>,
+        ) -> View<(i32, i32), Mono<(i32, i32)>> {
+            let edges = edges.clone();
+            let view = database
+                .store_recursive_view(|paths: View<(i32, i32), Mono<(i32, i32)>>| {
+                    let steps = paths
+                        .builder()
+                        .with_key(|t| t.1)
+                        .join(edges.builder().with_key(|t| t.0))
+                        .on(|_, l, r| (l.0, r.1))
+                        .build();
+                    edges.builder().union(steps).build()
+                })
+                .unwrap();
+            // the expression type of `view` cannot be named:
+            View::new(view.reference().clone())
+        }
+        {
+            let mut database = Database::new();
+            let edges = database.add_relation::<(i32, i32)>("edges").unwrap();
+            let paths = closure(&mut database, &edges);
+            assert_eq!(
+                Vec::<(i32, i32)>::new(),
+                database.evaluate(&paths).unwrap().into_tuples()
+            );
+
+            database
+                .insert(&edges, vec![(1, 2), (2, 3)].into())
+                .unwrap();
+            assert_eq!(
+                vec![(1, 2), (1, 3), (2, 3)],
+                database.evaluate(&paths).unwrap().into_tuples()
+            );
+
+            database.insert(&edges, vec![(3, 1)].into()).unwrap();
+            assert_eq!(
+                vec![
+                    (1, 1),
+                    (1, 2),
+                    (1, 3),
+                    (2, 1),
+                    (2, 2),
+                    (2, 3),
+                    (3, 1),
+                    (3, 2),
+                    (3, 3)
+                ],
+                database.evaluate(&paths).unwrap().into_tuples()
+            );
+        }
+        {
+            // Initialize a recursive view over existing tuples:
+            let mut database = Database::new();
+            let edges = database.add_relation::<(i32, i32)>("edges").unwrap();
+            database
+                .insert(&edges, vec![(1, 2), (2, 3), (3, 4)].into())
+                .unwrap();
+            database.evaluate(&edges).unwrap();
+
+            let paths = closure(&mut database, &edges);
+            assert_eq!(
+                vec![(1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)],
+                database.evaluate(&paths).unwrap().into_tuples()
+            );
+        }
+        {
+            // Tuples that only support themselves through recursion are removed:
+            let mut database = Database::new();
+            let edges = database.add_relation::<(i32, i32)>("edges").unwrap();
+            let paths = closure(&mut database, &edges);
+            let sources = database
+                .store_view(paths.builder().project(|t| t.0).build())
+                .unwrap();
+
+            database
+                .insert(&edges, vec![(1, 2), (2, 3), (3, 2)].into())
+                .unwrap();
+            assert_eq!(
+                vec![(1, 2), (1, 3), (2, 2), (2, 3), (3, 2), (3, 3)],
+                database.evaluate(&paths).unwrap().into_tuples()
+            );
+            assert_eq!(
+                vec![1, 2, 3],
+                database.evaluate(&sources).unwrap().into_tuples()
+            );
+
+            database.remove(&edges, vec![(1, 2)].into()).unwrap();
+            assert_eq!(
+                vec![(2, 2), (2, 3), (3, 2), (3, 3)],
+                database.evaluate(&paths).unwrap().into_tuples()
+            );
+            assert_eq!(
+                vec![2, 3],
+                database.evaluate(&sources).unwrap().into_tuples()
+            );
+        }
+        {
+            // The view may appear on the left side of `Difference`:
+            let mut database = Database::new();
+            let edges = database.add_relation::<(i32, i32)>("edges").unwrap();
+            let blocked = database.add_relation::<(i32, i32)>("blocked").unwrap();
+            let paths = database
+                .store_recursive_view(|paths| {
+                    let steps = paths
+                        .builder()
+                        .with_key(|t: &(i32, i32)| t.1)
+                        .join(edges.builder().with_key(|t| t.0))
+                        .on(|_, l, r| (l.0, r.1))
+                        .build();
+                    edges
+                        .builder()
+                        .union(steps)
+                        .build()
+                        .builder()
+                        .difference(blocked.clone())
+                        .build()
+                })
+                .unwrap();
+
+            database
+                .insert(&edges, vec![(1, 2), (2, 3), (3, 4)].into())
+                .unwrap();
+            database.insert(&blocked, vec![(1, 3)].into()).unwrap();
+            assert_eq!(
+                vec![(1, 2), (2, 3), (2, 4), (3, 4)],
+                database.evaluate(&paths).unwrap().into_tuples()
+            );
+
+            database.remove(&blocked, vec![(1, 3)].into()).unwrap();
+            assert_eq!(
+                vec![(1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)],
+                database.evaluate(&paths).unwrap().into_tuples()
+            );
+        }
+        {
+            let mut database = Database::new();
+            let r = database.add_relation::<i32>("r").unwrap();
+            let view = database
+                .store_recursive_view(|v: View<i32, Mono<i32>>| r.builder().difference(v).build());
+            assert!(view.is_err());
+        }
+    }
 }
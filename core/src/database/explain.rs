@@ -0,0 +1,494 @@
+use super::Database;
+use crate::{
+    expression::{
+        index::IndexRef, view::ViewRef, Aggregate, Difference, Empty, Expression, Full, Index,
+        Intersect, Join, Product, Project, Relation, Select, Singleton, Union, View, Visitor,
+    },
+    Error, Tuple,
+};
+use std::fmt;
+
+/// Contains the number of tuples in the batches of an instance in a database.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+pub struct Statistics {
+    stable: Vec<usize>,
+    recent: usize,
+    to_add: Vec<usize>,
+    removed: usize,
+    to_remove: Vec<usize>,
+}
+
+impl Statistics {
+    /// Creates a new `Statistics` from the number of tuples in the batches of an instance.
+    pub(super) fn new(
+        stable: Vec<usize>,
+        recent: usize,
+        to_add: Vec<usize>,
+        removed: usize,
+        to_remove: Vec<usize>,
+    ) -> Self {
+        Self {
+            stable,
+            recent,
+            to_add,
+            removed,
+            to_remove,
+        }
+    }
+
+    /// Returns the number of tuples in each `stable` batch of the instance.
+    #[inline(always)]
+    pub fn stable(&self) -> &[usize] {
+        &self.stable
+    }
+
+    /// Returns the number of `recent` tuples of the instance.
+    #[inline(always)]
+    pub fn recent(&self) -> usize {
+        self.recent
+    }
+
+    /// Returns the number of tuples in each batch of the instance that is inserted but
+    /// not yet stabilized.
+    #[inline(always)]
+    pub fn to_add(&self) -> &[usize] {
+        &self.to_add
+    }
+
+    /// Returns the number of recently `removed` tuples of the instance.
+    #[inline(always)]
+    pub fn removed(&self) -> usize {
+        self.removed
+    }
+
+    /// Returns the number of tuples in each batch of the instance that is removed but
+    /// not yet stabilized.
+    #[inline(always)]
+    pub fn to_remove(&self) -> &[usize] {
+        &self.to_remove
+    }
+}
+
+impl fmt::Display for Statistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "stable: {} batches / {} tuples, recent: {} tuples",
+            self.stable.len(),
+            self.stable.iter().sum::<usize>(),
+            self.recent,
+        )?;
+        if !self.to_add.is_empty() {
+            write!(
+                f,
+                ", to add: {} batches / {} tuples",
+                self.to_add.len(),
+                self.to_add.iter().sum::<usize>()
+            )?;
+        }
+        if self.removed > 0 {
+            write!(f, ", removed: {} tuples", self.removed)?;
+        }
+        if !self.to_remove.is_empty() {
+            write!(
+                f,
+                ", to remove: {} batches / {} tuples",
+                self.to_remove.len(),
+                self.to_remove.iter().sum::<usize>()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Is a node in the tree that is returned by [`Database::explain`], describing an operator
+/// of the explained expression.
+///
+/// [`Database::explain`]: ./struct.Database.html#method.explain
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Plan {
+    operator: &'static str,
+    name: Option<String>,
+    statistics: Option<Statistics>,
+    children: Vec<Plan>,
+}
+
+impl Plan {
+    /// Returns the name of the operator, e.g., `"Join"`.
+    #[inline(always)]
+    pub fn operator(&self) -> &str {
+        self.operator
+    }
+
+    /// Returns the name of the relation, index or view that the operator refers to.
+    #[inline(always)]
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the statistics of the database instance that the operator refers to.
+    #[inline(always)]
+    pub fn statistics(&self) -> Option<&Statistics> {
+        self.statistics.as_ref()
+    }
+
+    /// Returns the plans of the sub-expressions of the operator.
+    #[inline(always)]
+    pub fn children(&self) -> &[Plan] {
+        &self.children
+    }
+
+    fn write(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        write!(f, "{:indent$}{}", "", self.operator, indent = depth * 2)?;
+        if let Some(name) = &self.name {
+            write!(f, " {}", name)?;
+        }
+        if let Some(statistics) = &self.statistics {
+            write!(f, " ({})", statistics)?;
+        }
+        writeln!(f)?;
+        for child in self.children.iter() {
+            child.write(f, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+/// Returns the display name of the index identified by `reference`.
+fn index_name(reference: &IndexRef) -> String {
+    format!("`{}`[{}]", reference.relation, reference.position)
+}
+
+/// Returns the display name of the view identified by `reference`.
+fn view_name(reference: &ViewRef) -> String {
+    format!("#{}", reference.0)
+}
+
+/// Is a `crate::expression::Visitor` that builds the [`Plan`] of the visited expression
+/// with the statistics of the instances in `database`.
+///
+/// [`Plan`]: ./struct.Plan.html
+pub(super) struct ExplainVisitor<'d> {
+    database: &'d Database,
+    /// Contains the plans of the visited sub-expressions of the expression that is being
+    /// visited.
+    children: Vec<Plan>,
+    error: Option<Error>,
+}
+
+impl<'d> ExplainVisitor<'d> {
+    pub fn new(database: &'d Database) -> Self {
+        Self {
+            database,
+            children: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Consumes the receiver and returns the plan of the visited expression.
+    pub fn into_plan(mut self) -> Result<Plan, Error> {
+        if let Some(e) = self.error {
+            Err(e)
+        } else {
+            Ok(self
+                .children
+                .pop()
+                .expect("expecting the plan of the expression"))
+        }
+    }
+
+    /// Adds a node for `operator` after visiting its sub-expressions by `walk`.
+    fn node(
+        &mut self,
+        operator: &'static str,
+        name: Option<String>,
+        statistics: Option<Statistics>,
+        walk: impl FnOnce(&mut Self),
+    ) {
+        let siblings = std::mem::take(&mut self.children);
+        walk(self);
+        let children = std::mem::replace(&mut self.children, siblings);
+        self.children.push(Plan {
+            operator,
+            name,
+            statistics,
+            children,
+        });
+    }
+
+    /// Returns the statistics of the instance of the relation `name`.
+    fn relation_statistics(&mut self, name: &str) -> Option<Statistics> {
+        let statistics = self
+            .database
+            .relations
+            .get(name)
+            .map(|r| r.instance.statistics());
+        if statistics.is_none() {
+            self.error(name.into());
+        }
+        statistics
+    }
+
+    fn error(&mut self, name: String) {
+        if self.error.is_none() {
+            self.error = Some(Error::InstanceNotFound { name });
+        }
+    }
+}
+
+impl<'d> Visitor for ExplainVisitor<'d> {
+    fn visit_full<T>(&mut self, _: &Full<T>)
+    where
+        T: Tuple,
+    {
+        self.node("Full", None, None, |_| {});
+    }
+
+    fn visit_empty<T>(&mut self, _: &Empty<T>)
+    where
+        T: Tuple,
+    {
+        self.node("Empty", None, None, |_| {});
+    }
+
+    fn visit_singleton<T>(&mut self, singleton: &Singleton<T>)
+    where
+        T: Tuple,
+    {
+        self.node(
+            "Singleton",
+            Some(format!("{:?}", singleton.tuple())),
+            None,
+            |_| {},
+        );
+    }
+
+    fn visit_relation<T>(&mut self, relation: &Relation<T>)
+    where
+        T: Tuple,
+    {
+        let statistics = self.relation_statistics(relation.name());
+        self.node(
+            "Relation",
+            Some(format!("`{}`", relation.name())),
+            statistics,
+            |_| {},
+        );
+    }
+
+    fn visit_index<K, T>(&mut self, index: &Index<K, T>)
+    where
+        K: Tuple,
+        T: Tuple,
+    {
+        let reference = index.reference();
+        let statistics = self
+            .database
+            .relations
+            .get(&reference.relation)
+            .and_then(|r| r.indexes.get(reference.position))
+            .map(|i| i.instance().statistics());
+        if statistics.is_none() {
+            self.error(format!("{:?}", reference));
+        }
+        let (operator, name) = match index.lookup_key() {
+            None => ("Index", index_name(reference)),
+            Some(key) => ("Lookup", format!("{} = {:?}", index_name(reference), key)),
+        };
+        self.node(operator, Some(name), statistics, |_| {});
+    }
+
+    fn visit_select<T, E>(&mut self, select: &Select<T, E>)
+    where
+        T: Tuple,
+        E: Expression<T>,
+    {
+        self.node("Select", None, None, |v| select.expression().visit(v));
+    }
+
+    fn visit_union<T, L, R>(&mut self, union: &Union<T, L, R>)
+    where
+        T: Tuple,
+        L: Expression<T>,
+        R: Expression<T>,
+    {
+        self.node("Union", None, None, |v| {
+            union.left().visit(v);
+            union.right().visit(v);
+        });
+    }
+
+    fn visit_intersect<T, L, R>(&mut self, intersect: &Intersect<T, L, R>)
+    where
+        T: Tuple,
+        L: Expression<T>,
+        R: Expression<T>,
+    {
+        self.node("Intersect", None, None, |v| {
+            intersect.left().visit(v);
+            intersect.right().visit(v);
+        });
+    }
+
+    fn visit_difference<T, L, R>(&mut self, difference: &Difference<T, L, R>)
+    where
+        T: Tuple,
+        L: Expression<T>,
+        R: Expression<T>,
+    {
+        self.node("Difference", None, None, |v| {
+            difference.left().visit(v);
+            difference.right().visit(v);
+        });
+    }
+
+    fn visit_project<S, T, E>(&mut self, project: &Project<S, T, E>)
+    where
+        T: Tuple,
+        S: Tuple,
+        E: Expression<S>,
+    {
+        self.node("Project", None, None, |v| project.expression().visit(v));
+    }
+
+    fn visit_product<L, R, Left, Right, T>(&mut self, product: &Product<L, R, Left, Right, T>)
+    where
+        L: Tuple,
+        R: Tuple,
+        T: Tuple,
+        Left: Expression<L>,
+        Right: Expression<R>,
+    {
+        self.node("Product", None, None, |v| {
+            product.left().visit(v);
+            product.right().visit(v);
+        });
+    }
+
+    fn visit_join<K, L, R, Left, Right, T>(&mut self, join: &Join<K, L, R, Left, Right, T>)
+    where
+        K: Tuple,
+        L: Tuple,
+        R: Tuple,
+        T: Tuple,
+        Left: Expression<L>,
+        Right: Expression<R>,
+    {
+        let indexes: Vec<String> = join
+            .left_index()
+            .into_iter()
+            .chain(join.right_index())
+            .map(index_name)
+            .collect();
+        let name = if indexes.is_empty() {
+            None
+        } else {
+            Some(format!("using {}", indexes.join(", ")))
+        };
+        self.node("Join", name, None, |v| {
+            join.left().visit(v);
+            join.right().visit(v);
+        });
+    }
+
+    fn visit_aggregate<K, S, A, E>(&mut self, aggregate: &Aggregate<K, S, A, E>)
+    where
+        K: Tuple,
+        S: Tuple,
+        A: Tuple,
+        E: Expression<S>,
+    {
+        self.node("Aggregate", None, None, |v| aggregate.expression().visit(v));
+    }
+
+    fn visit_view<T, E>(&mut self, view: &View<T, E>)
+    where
+        T: Tuple,
+        E: Expression<T>,
+    {
+        let reference = view.reference();
+        let statistics = self
+            .database
+            .views
+            .get(reference)
+            .map(|v| v.instance.instance().statistics());
+        if statistics.is_none() {
+            self.error(format!("{:?}", reference));
+        }
+        self.node("View", Some(view_name(reference)), statistics, |_| {});
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{expression::Select, Database, Expression};
+
+    #[test]
+    fn test_explain() {
+        let mut database = Database::new();
+        let r = database.add_relation::<(i32, i32)>("r").unwrap();
+        let s = database.add_relation::<(i32, i32)>("s").unwrap();
+        let index = database.add_index(&s, |t| t.0).unwrap();
+        database.insert(&r, vec![(1, 10), (2, 20)].into()).unwrap();
+        database.insert(&s, vec![(1, 100)].into()).unwrap();
+        database.evaluate(&r).unwrap();
+        database.insert(&r, vec![(3, 30)].into()).unwrap();
+
+        let join = r
+            .builder()
+            .with_key(|t| t.0)
+            .join(index.with_key())
+            .on(|_, l, r| (l.1, r.1))
+            .build();
+        let select = Select::new(&join, |t| t.0 > 10);
+        let plan = database.explain(&select).unwrap();
+
+        assert_eq!("Select", plan.operator());
+        let join = &plan.children()[0];
+        assert_eq!("Join", join.operator());
+        assert_eq!(Some("using `s`[0]"), join.name());
+
+        let relation = &join.children()[0];
+        assert_eq!(Some("`r`"), relation.name());
+        let statistics = relation.statistics().unwrap();
+        assert_eq!(&[2], statistics.stable());
+        assert_eq!(0, statistics.recent());
+        assert_eq!(&[1], statistics.to_add());
+
+        let index = &join.children()[1];
+        assert_eq!("Index", index.operator());
+        assert_eq!(&[1], index.statistics().unwrap().to_add());
+
+        assert_eq!(
+            "Select\n  \
+             Join using `s`[0]\n    \
+             Relation `r` (stable: 1 batches / 2 tuples, recent: 0 tuples, to add: 1 batches / 1 tuples)\n    \
+             Index `s`[0] (stable: 0 batches / 0 tuples, recent: 0 tuples, to add: 1 batches / 1 tuples)\n",
+            plan.to_string()
+        );
+    }
+
+    #[test]
+    fn test_explain_view() {
+        let mut database = Database::new();
+        let r = database.add_relation::<i32>("r").unwrap();
+        let view = database
+            .store_view(Select::new(r.clone(), |&t| t > 1))
+            .unwrap();
+        database.insert(&r, vec![1, 2, 3].into()).unwrap();
+        database.evaluate(&view).unwrap();
+
+        let plan = database.explain(&view).unwrap();
+        assert_eq!("View", plan.operator());
+        assert_eq!(&[2], plan.statistics().unwrap().stable());
+
+        let missing = Database::new();
+        assert!(missing.explain(&r).is_err());
+    }
+}
@@ -1,3 +1,4 @@
+use super::instance::{ViewInstance, ViewInstanceBox};
 use crate::{
     expression::{view::ViewRef, *},
     Error, Tuple, Tuples,
@@ -31,6 +32,18 @@ pub trait ExpressionExt<T: Tuple>: Expression<T> {
     where
         C: StableCollector;
 
+    /// Visits this node by a [`RemovedCollector`] and returns the removed tuples of the
+    /// database according to the logic implemented by `collector`.
+    ///
+    /// **Note**:
+    /// Removed tuples are those tuples that got removed from (or retracted by) instances
+    /// of a database before any dependent (materialized) views are updated.
+    ///
+    /// [`RemovedCollector`]: ./trait.RemovedCollector.html
+    fn collect_removed<C>(&self, collector: &C) -> Result<Tuples<T>, Error>
+    where
+        C: RemovedCollector;
+
     /// Returns an iterator over the relation dependencies of this expression. These are
     /// the name of relations that show up in the receiver expression.
     fn relation_dependencies(&self) -> &[String];
@@ -38,6 +51,16 @@ pub trait ExpressionExt<T: Tuple>: Expression<T> {
     /// Returns an iterator over the view dependencies of this expression. These are
     /// references to views that show up in the receiver expression.
     fn view_dependencies(&self) -> &[ViewRef];
+
+    /// Consumes the receiver and returns the instance that maintains the tuples of a
+    /// view over the receiver.
+    fn into_view_instance(self) -> ViewInstanceBox
+    where
+        Self: Sized + 'static,
+        T: 'static,
+    {
+        ViewInstanceBox(Box::new(ViewInstance::new(self)))
+    }
 }
 
 impl<T, E> ExpressionExt<T> for &E
@@ -59,6 +82,13 @@ where
         (*self).collect_stable(collector)
     }
 
+    fn collect_removed<C>(&self, collector: &C) -> Result<Tuples<T>, Error>
+    where
+        C: RemovedCollector,
+    {
+        (*self).collect_removed(collector)
+    }
+
     fn relation_dependencies(&self) -> &[String] {
         (*self).relation_dependencies()
     }
@@ -87,6 +117,13 @@ where
         (**self).collect_stable(collector)
     }
 
+    fn collect_removed<C>(&self, collector: &C) -> Result<Tuples<T>, Error>
+    where
+        C: RemovedCollector,
+    {
+        (**self).collect_removed(collector)
+    }
+
     fn relation_dependencies(&self) -> &[String] {
         (**self).relation_dependencies()
     }
@@ -119,6 +156,12 @@ pub trait RecentCollector {
     where
         T: Tuple + 'static;
 
+    /// Collects the recent tuples for an `Index` expression.
+    fn collect_index<K, T>(&self, index: &Index<K, T>) -> Result<Tuples<T>, Error>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static;
+
     /// Collects the recent tuples for a `Select` expression.
     fn collect_select<T, E>(&self, select: &Select<T, E>) -> Result<Tuples<T>, Error>
     where
@@ -177,13 +220,24 @@ pub trait RecentCollector {
         join: &Join<K, L, R, Left, Right, T>,
     ) -> Result<Tuples<T>, Error>
     where
-        K: Tuple,
-        L: Tuple,
-        R: Tuple,
+        K: Tuple + 'static,
+        L: Tuple + 'static,
+        R: Tuple + 'static,
         T: Tuple,
         Left: ExpressionExt<L>,
         Right: ExpressionExt<R>;
 
+    /// Collects the recent tuples for an `Aggregate` expression.
+    fn collect_aggregate<K, S, A, E>(
+        &self,
+        aggregate: &Aggregate<K, S, A, E>,
+    ) -> Result<Tuples<(K, A)>, Error>
+    where
+        K: Tuple,
+        S: Tuple,
+        A: Tuple,
+        E: ExpressionExt<S>;
+
     /// Collects the recent tuples for a `View` expression.
     fn collect_view<T, E>(&self, view: &View<T, E>) -> Result<Tuples<T>, Error>
     where
@@ -214,6 +268,12 @@ pub trait StableCollector {
     where
         T: Tuple + 'static;
 
+    /// Collects the stable tuples for an `Index` expression.
+    fn collect_index<K, T>(&self, index: &Index<K, T>) -> Result<Vec<Tuples<T>>, Error>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static;
+
     /// Collects the stable tuples for a `Select` expression.            
     fn collect_select<T, E>(&self, select: &Select<T, E>) -> Result<Vec<Tuples<T>>, Error>
     where
@@ -271,23 +331,147 @@ pub trait StableCollector {
         &self,
         join: &Join<K, L, R, Left, Right, T>,
     ) -> Result<Vec<Tuples<T>>, Error>
+    where
+        K: Tuple + 'static,
+        L: Tuple + 'static,
+        R: Tuple + 'static,
+        T: Tuple,
+        Left: ExpressionExt<L>,
+        Right: ExpressionExt<R>;
+
+    /// Collects the stable tuples for an `Aggregate` expression.
+    fn collect_aggregate<K, S, A, E>(
+        &self,
+        aggregate: &Aggregate<K, S, A, E>,
+    ) -> Result<Vec<Tuples<(K, A)>>, Error>
     where
         K: Tuple,
+        S: Tuple,
+        A: Tuple,
+        E: ExpressionExt<S>;
+
+    /// Collects the stable tuples for a `View` expression.            
+    fn collect_view<T, E>(&self, view: &View<T, E>) -> Result<Vec<Tuples<T>>, Error>
+    where
+        T: Tuple + 'static,
+        E: ExpressionExt<T> + 'static;
+}
+
+/// Is the trait of objects that implement the logic for collecting the tuples that are
+/// removed from the result of the visited expression when its removed (and recent)
+/// tuples are reflected in the database.
+pub trait RemovedCollector {
+    /// Collects the removed tuples for the `Full` expression.
+    fn collect_full<T>(&self, full: &Full<T>) -> Result<Tuples<T>, Error>
+    where
+        T: Tuple;
+
+    /// Collects the removed tuples for the `Empty` expression.
+    fn collect_empty<T>(&self, empty: &Empty<T>) -> Result<Tuples<T>, Error>
+    where
+        T: Tuple;
+
+    /// Collects the removed tuples for a `Singleton` expression.
+    fn collect_singleton<T>(&self, singleton: &Singleton<T>) -> Result<Tuples<T>, Error>
+    where
+        T: Tuple;
+
+    /// Collects the removed tuples for a `Relation` expression.
+    fn collect_relation<T>(&self, relation: &Relation<T>) -> Result<Tuples<T>, Error>
+    where
+        T: Tuple + 'static;
+
+    /// Collects the removed tuples for an `Index` expression.
+    fn collect_index<K, T>(&self, index: &Index<K, T>) -> Result<Tuples<T>, Error>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static;
+
+    /// Collects the removed tuples for a `Select` expression.
+    fn collect_select<T, E>(&self, select: &Select<T, E>) -> Result<Tuples<T>, Error>
+    where
+        T: Tuple,
+        E: ExpressionExt<T>;
+
+    /// Collects the removed tuples for a `Union` expression.
+    fn collect_union<T, L, R>(&self, union: &Union<T, L, R>) -> Result<Tuples<T>, Error>
+    where
+        T: Tuple,
+        L: ExpressionExt<T>,
+        R: ExpressionExt<T>;
+
+    /// Collects the removed tuples for an `Intersect` expression.
+    fn collect_intersect<T, L, R>(
+        &self,
+        intersect: &Intersect<T, L, R>,
+    ) -> Result<Tuples<T>, Error>
+    where
+        T: Tuple,
+        L: ExpressionExt<T>,
+        R: ExpressionExt<T>;
+
+    /// Collects the removed tuples for a `Difference` expression.
+    fn collect_difference<T, L, R>(
+        &self,
+        difference: &Difference<T, L, R>,
+    ) -> Result<Tuples<T>, Error>
+    where
+        T: Tuple,
+        L: ExpressionExt<T>,
+        R: ExpressionExt<T>;
+
+    /// Collects the removed tuples for a `Project` expression.
+    fn collect_project<S, T, E>(&self, project: &Project<S, T, E>) -> Result<Tuples<T>, Error>
+    where
+        T: Tuple,
+        S: Tuple,
+        E: ExpressionExt<S>;
+
+    /// Collects the removed tuples for a `Product` expression.
+    fn collect_product<L, R, Left, Right, T>(
+        &self,
+        product: &Product<L, R, Left, Right, T>,
+    ) -> Result<Tuples<T>, Error>
+    where
         L: Tuple,
         R: Tuple,
         T: Tuple,
         Left: ExpressionExt<L>,
         Right: ExpressionExt<R>;
 
-    /// Collects the stable tuples for a `View` expression.            
-    fn collect_view<T, E>(&self, view: &View<T, E>) -> Result<Vec<Tuples<T>>, Error>
+    /// Collects the removed tuples for a `Join` expression.
+    fn collect_join<K, L, R, Left, Right, T>(
+        &self,
+        join: &Join<K, L, R, Left, Right, T>,
+    ) -> Result<Tuples<T>, Error>
+    where
+        K: Tuple + 'static,
+        L: Tuple + 'static,
+        R: Tuple + 'static,
+        T: Tuple,
+        Left: ExpressionExt<L>,
+        Right: ExpressionExt<R>;
+
+    /// Collects the removed tuples for an `Aggregate` expression.
+    fn collect_aggregate<K, S, A, E>(
+        &self,
+        aggregate: &Aggregate<K, S, A, E>,
+    ) -> Result<Tuples<(K, A)>, Error>
+    where
+        K: Tuple,
+        S: Tuple,
+        A: Tuple,
+        E: ExpressionExt<S>;
+
+    /// Collects the removed tuples for a `View` expression.
+    fn collect_view<T, E>(&self, view: &View<T, E>) -> Result<Tuples<T>, Error>
     where
         T: Tuple + 'static,
         E: ExpressionExt<T> + 'static;
 }
 
 mod r#impl {
-    use super::{ExpressionExt, RecentCollector, StableCollector};
+    use super::{ExpressionExt, RecentCollector, RemovedCollector, StableCollector};
     use crate::{
         expression::view::{View, ViewRef},
         Error, Tuple, Tuples,
@@ -312,6 +496,13 @@ mod r#impl {
             collector.collect_view(&self)
         }
 
+        fn collect_removed<C>(&self, collector: &C) -> Result<Tuples<T>, Error>
+        where
+            C: RemovedCollector,
+        {
+            collector.collect_view(self)
+        }
+
         fn relation_dependencies(&self) -> &[String] {
             &[]
         }
@@ -321,6 +512,91 @@ mod r#impl {
         }
     }
 
+    use super::super::instance::{AggregateViewInstance, ViewInstanceBox};
+    use crate::expression::Aggregate;
+
+    impl<K, S, A, E> ExpressionExt<(K, A)> for Aggregate<K, S, A, E>
+    where
+        K: Tuple,
+        S: Tuple,
+        A: Tuple,
+        E: ExpressionExt<S>,
+    {
+        fn collect_recent<C>(&self, collector: &C) -> Result<Tuples<(K, A)>, Error>
+        where
+            C: RecentCollector,
+        {
+            collector.collect_aggregate(self)
+        }
+
+        fn collect_stable<C>(&self, collector: &C) -> Result<Vec<Tuples<(K, A)>>, Error>
+        where
+            C: StableCollector,
+        {
+            collector.collect_aggregate(self)
+        }
+
+        fn collect_removed<C>(&self, collector: &C) -> Result<Tuples<(K, A)>, Error>
+        where
+            C: RemovedCollector,
+        {
+            collector.collect_aggregate(self)
+        }
+
+        fn relation_dependencies(&self) -> &[String] {
+            self.relation_deps()
+        }
+
+        fn view_dependencies(&self) -> &[ViewRef] {
+            self.view_deps()
+        }
+
+        fn into_view_instance(self) -> ViewInstanceBox
+        where
+            Self: Sized + 'static,
+            (K, A): 'static,
+        {
+            ViewInstanceBox(Box::new(AggregateViewInstance::new(self)))
+        }
+    }
+
+    use crate::expression::Index;
+
+    impl<K, T> ExpressionExt<T> for Index<K, T>
+    where
+        K: Tuple + 'static,
+        T: Tuple + 'static,
+    {
+        fn collect_recent<C>(&self, collector: &C) -> Result<Tuples<T>, Error>
+        where
+            C: RecentCollector,
+        {
+            collector.collect_index(self)
+        }
+
+        fn collect_stable<C>(&self, collector: &C) -> Result<Vec<Tuples<T>>, Error>
+        where
+            C: StableCollector,
+        {
+            collector.collect_index(self)
+        }
+
+        fn collect_removed<C>(&self, collector: &C) -> Result<Tuples<T>, Error>
+        where
+            C: RemovedCollector,
+        {
+            collector.collect_index(self)
+        }
+
+        fn relation_dependencies(&self) -> &[String] {
+            self.relation().relation_deps()
+        }
+
+        fn view_dependencies(&self) -> &[ViewRef] {
+            &[]
+        }
+    }
+
     use crate::expression::Intersect;
 
     impl<T, L, R> ExpressionExt<T> for Intersect<T, L, R>
@@ -343,6 +619,13 @@ mod r#impl {
             collector.collect_intersect(&self)
         }
 
+        fn collect_removed<C>(&self, collector: &C) -> Result<Tuples<T>, Error>
+        where
+            C: RemovedCollector,
+        {
+            collector.collect_intersect(self)
+        }
+
         fn relation_dependencies(&self) -> &[String] {
             self.relation_deps()
         }
@@ -374,6 +657,13 @@ mod r#impl {
             collector.collect_union(&self)
         }
 
+        fn collect_removed<C>(&self, collector: &C) -> Result<Tuples<T>, Error>
+        where
+            C: RemovedCollector,
+        {
+            collector.collect_union(self)
+        }
+
         fn relation_dependencies(&self) -> &[String] {
             self.relation_deps()
         }
@@ -405,6 +695,13 @@ mod r#impl {
             collector.collect_difference(&self)
         }
 
+        fn collect_removed<C>(&self, collector: &C) -> Result<Tuples<T>, Error>
+        where
+            C: RemovedCollector,
+        {
+            collector.collect_difference(self)
+        }
+
         fn relation_dependencies(&self) -> &[String] {
             self.relation_deps()
         }
@@ -434,6 +731,13 @@ mod r#impl {
             collector.collect_empty(&self)
         }
 
+        fn collect_removed<C>(&self, collector: &C) -> Result<Tuples<T>, Error>
+        where
+            C: RemovedCollector,
+        {
+            collector.collect_empty(self)
+        }
+
         fn relation_dependencies(&self) -> &[String] {
             &[]
         }
@@ -463,6 +767,13 @@ mod r#impl {
             collector.collect_full(&self)
         }
 
+        fn collect_removed<C>(&self, collector: &C) -> Result<Tuples<T>, Error>
+        where
+            C: RemovedCollector,
+        {
+            collector.collect_full(self)
+        }
+
         fn relation_dependencies(&self) -> &[String] {
             &[]
         }
@@ -476,9 +787,9 @@ mod r#impl {
 
     impl<K, L, R, Left, Right, T> ExpressionExt<T> for Join<K, L, R, Left, Right, T>
     where
-        K: Tuple,
-        L: Tuple,
-        R: Tuple,
+        K: Tuple + 'static,
+        L: Tuple + 'static,
+        R: Tuple + 'static,
         T: Tuple,
         Left: ExpressionExt<L>,
         Right: ExpressionExt<R>,
@@ -497,6 +808,13 @@ mod r#impl {
             collector.collect_join(&self)
         }
 
+        fn collect_removed<C>(&self, collector: &C) -> Result<Tuples<T>, Error>
+        where
+            C: RemovedCollector,
+        {
+            collector.collect_join(self)
+        }
+
         fn relation_dependencies(&self) -> &[String] {
             self.relation_deps()
         }
@@ -548,6 +866,26 @@ mod r#impl {
             }
         }
 
+        fn collect_removed<C>(&self, collector: &C) -> Result<Tuples<T>, Error>
+        where
+            C: RemovedCollector,
+        {
+            match self {
+                Mono::Full(exp) => exp.collect_removed(collector),
+                Mono::Empty(exp) => exp.collect_removed(collector),
+                Mono::Singleton(exp) => exp.collect_removed(collector),
+                Mono::Relation(exp) => exp.collect_removed(collector),
+                Mono::Select(exp) => exp.collect_removed(collector),
+                Mono::Project(exp) => exp.collect_removed(collector),
+                Mono::Union(exp) => exp.collect_removed(collector),
+                Mono::Intersect(exp) => exp.collect_removed(collector),
+                Mono::Difference(exp) => exp.collect_removed(collector),
+                Mono::Product(exp) => exp.collect_removed(collector),
+                Mono::Join(exp) => exp.collect_removed(collector),
+                Mono::View(exp) => exp.collect_removed(collector),
+            }
+        }
+
         fn relation_dependencies(&self) -> &[String] {
             match self {
                 Mono::Full(exp) => exp.relation_dependencies(),
@@ -607,6 +945,13 @@ mod r#impl {
             collector.collect_product(&self)
         }
 
+        fn collect_removed<C>(&self, collector: &C) -> Result<Tuples<T>, Error>
+        where
+            C: RemovedCollector,
+        {
+            collector.collect_product(self)
+        }
+
         fn relation_dependencies(&self) -> &[String] {
             self.relation_deps()
         }
@@ -638,6 +983,13 @@ mod r#impl {
             collector.collect_project(&self)
         }
 
+        fn collect_removed<C>(&self, collector: &C) -> Result<Tuples<T>, Error>
+        where
+            C: RemovedCollector,
+        {
+            collector.collect_project(self)
+        }
+
         fn relation_dependencies(&self) -> &[String] {
             self.relation_deps()
         }
@@ -667,6 +1019,13 @@ mod r#impl {
             collector.collect_relation(&self)
         }
 
+        fn collect_removed<C>(&self, collector: &C) -> Result<Tuples<T>, Error>
+        where
+            C: RemovedCollector,
+        {
+            collector.collect_relation(self)
+        }
+
         fn relation_dependencies(&self) -> &[String] {
             self.relation_deps()
         }
@@ -697,6 +1056,13 @@ mod r#impl {
             collector.collect_select(&self)
         }
 
+        fn collect_removed<C>(&self, collector: &C) -> Result<Tuples<T>, Error>
+        where
+            C: RemovedCollector,
+        {
+            collector.collect_select(self)
+        }
+
         fn relation_dependencies(&self) -> &[String] {
             self.relation_deps()
         }
@@ -726,6 +1092,13 @@ mod r#impl {
             collector.collect_singleton(&self)
         }
 
+        fn collect_removed<C>(&self, collector: &C) -> Result<crate::Tuples<T>, Error>
+        where
+            C: RemovedCollector,
+        {
+            collector.collect_singleton(self)
+        }
+
         fn relation_dependencies(&self) -> &[String] {
             &[]
         }
@@ -86,6 +86,84 @@ pub(crate) fn join_helper<Key: Ord, L, R>(
     }
 }
 
+/// Is the minimum number of tuples in each partition of a parallel join or product.
+#[cfg(feature = "parallel")]
+const PARTITION_SIZE: usize = 1024;
+
+/// Applies `mapper` on every pair of `left` and `right` slices in parallel and returns
+/// the results. The output is identical to that of applying `mapper` by `product_helper`.
+#[cfg(feature = "parallel")]
+pub(crate) fn par_product_helper<L, R, T>(
+    left: &[L],
+    right: &[R],
+    mapper: impl Fn(&L, &R) -> T + Sync,
+) -> Vec<T>
+where
+    L: Sync,
+    R: Sync,
+    T: Send,
+{
+    use rayon::prelude::*;
+
+    let chunk_size = (PARTITION_SIZE / right.len().max(1)).max(1);
+    left.par_chunks(chunk_size)
+        .map(|chunk| {
+            let mut result = Vec::with_capacity(chunk.len() * right.len());
+            product_helper(chunk, right, |l, r| result.push(mapper(l, r)));
+            result
+        })
+        .flatten()
+        .collect()
+}
+
+/// For two slices `left` and `right` that are sorted by the first element of their tuples,
+/// applies `mapper` in parallel on those pairs of `left` and `right` that agree on their
+/// first element as the key and returns the results. `left` is partitioned on the boundaries
+/// of its keys, and every partition is joined with the range of `right` with the same keys.
+/// The output is identical to that of applying `mapper` by `join_helper`.
+#[cfg(feature = "parallel")]
+pub(crate) fn par_join_helper<Key, L, R, T>(
+    left: &[(Key, L)],
+    right: &[(Key, R)],
+    mapper: impl Fn(&Key, &L, &R) -> T + Sync,
+) -> Vec<T>
+where
+    Key: Ord + Sync,
+    L: Sync,
+    R: Sync,
+    T: Send,
+{
+    use rayon::prelude::*;
+
+    let mut partitions = Vec::new();
+    let mut rest = left;
+    while !rest.is_empty() {
+        let mut end = PARTITION_SIZE.min(rest.len());
+        while end < rest.len() && rest[end].0 == rest[end - 1].0 {
+            end += 1;
+        }
+        partitions.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+
+    partitions
+        .into_par_iter()
+        .map(|partition| {
+            let first = &partition[0].0;
+            let last = &partition[partition.len() - 1].0;
+            let right = gallop(right, |x| &x.0 < first);
+            let len = right.len() - gallop(right, |x| &x.0 <= last).len();
+
+            let mut result = Vec::new();
+            join_helper(partition, &right[..len], |k, l, r| {
+                result.push(mapper(k, l, r))
+            });
+            result
+        })
+        .flatten()
+        .collect()
+}
+
 /// For two sorted slices `left` and `right`, applies `result` on those elements of `left` and `right`
 /// that are equal.
 #[inline(always)]
@@ -142,3 +220,36 @@ pub(crate) fn diff_helper<T: Ord>(left: &[T], right: &[&[T]], mut result: impl F
         }
     }
 }
+
+#[cfg(all(test, feature = "parallel"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_par_product_helper() {
+        let left = (0..3000).collect::<Vec<i32>>();
+        let right = (0..3).collect::<Vec<i32>>();
+
+        let mut expected = Vec::new();
+        product_helper(&left, &right, |l, r| expected.push(l * 10 + r));
+        assert_eq!(
+            expected,
+            par_product_helper(&left, &right, |l, r| l * 10 + r)
+        );
+        assert!(par_product_helper(&left, &[], |l, r: &i32| l + r).is_empty());
+    }
+
+    #[test]
+    fn test_par_join_helper() {
+        let left = (0..5000).map(|i| (i / 7, i)).collect::<Vec<(i32, i32)>>();
+        let right = (0..3000).map(|i| (i / 3, -i)).collect::<Vec<(i32, i32)>>();
+
+        let mut expected = Vec::new();
+        join_helper(&left, &right, |k, l, r| expected.push((*k, *l, *r)));
+        assert_eq!(
+            expected,
+            par_join_helper(&left, &right, |k, l, r| (*k, *l, *r))
+        );
+        assert!(par_join_helper(&left, &[], |_, l, r: &i32| l + r).is_empty());
+    }
+}
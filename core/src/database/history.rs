@@ -0,0 +1,502 @@
+use super::instance::{shared, DynInstance, Instance, Shared, Tuples};
+use crate::{MaybeSync, Tuple};
+use std::{any::Any, collections::BTreeSet, sync::Arc};
+
+/// Is used to store the histories of relations by hiding their (generic) types.
+pub(super) trait DynHistory: MaybeSync {
+    /// Records the `recent` and `removed` tuples of `instance`, the instance of the
+    /// relation of the history, as the changes to the relation at `epoch`.
+    fn record(&self, instance: &dyn DynInstance, epoch: u64);
+
+    /// Returns a new instance with the tuples of the relation at `epoch` as candidates to
+    /// be added, or `None` if the history starts after `epoch`. If `epoch` is `None`, the
+    /// instance is empty.
+    fn as_of(&self, epoch: Option<u64>) -> Option<Box<dyn DynInstance>>;
+
+    /// Discards the changes to the relation before `epoch`, so that the history starts with
+    /// the tuples of the relation at `epoch`. Does nothing if the history starts at or
+    /// after `epoch`.
+    fn trim(&mut self, epoch: u64);
+
+    /// Returns the net tuples that are added to and removed from the relation after `since`
+    /// (or from the start of the history if `since` is `None`) until `until`, as `Tuples`
+    /// hidden by `Any`, or `None` if the history starts after `since`.
+    fn changes(&self, since: Option<u64>, until: u64) -> Option<(Box<dyn Any>, Box<dyn Any>)>;
+
+    /// Clones the history in a `Box`.
+    fn clone_box(&self) -> Box<dyn DynHistory>;
+}
+
+/// Is the position of a consumer of [`Database::evaluate_since`] in the changes to a
+/// database, identified by the epoch at which the tuples were returned to the consumer.
+/// `Cursor::default()` is positioned before all changes.
+///
+/// [`Database::evaluate_since`]: ./struct.Database.html#method.evaluate_since
+#[derive(PartialEq, Eq, Clone, Copy, Default, Debug)]
+pub struct Cursor {
+    epoch: Option<u64>,
+}
+
+impl Cursor {
+    pub(super) fn new(epoch: u64) -> Self {
+        Self { epoch: Some(epoch) }
+    }
+
+    /// Returns the epoch of the cursor, or `None` if the cursor is positioned before all
+    /// changes.
+    #[inline(always)]
+    pub fn epoch(&self) -> Option<u64> {
+        self.epoch
+    }
+}
+
+/// Is the number of changes in a history after a checkpoint at which the tuples of the
+/// relation are stored as the next checkpoint.
+const CHECKPOINT_INTERVAL: usize = 64;
+
+/// Contains the tuples that are added to and removed from a relation when it is stabilized
+/// at an epoch.
+#[derive(Clone)]
+struct Change<T: Tuple> {
+    epoch: u64,
+    added: Tuples<T>,
+    removed: Tuples<T>,
+
+    /// Is the tuples of the relation after the change, if the change is a checkpoint.
+    checkpoint: Option<Tuples<T>>,
+}
+
+/// Returns the tuples of a relation after the changes in `changes` up to `position`. The
+/// tuples are reconstructed from the closest checkpoint at or before `position`; the first
+/// change of `changes` is always a checkpoint.
+fn state<T: Tuple>(changes: &[Change<T>], position: usize) -> Tuples<T> {
+    let start = changes[..=position]
+        .iter()
+        .rposition(|c| c.checkpoint.is_some())
+        .expect("the first change is a checkpoint");
+    let mut tuples = changes[start].checkpoint.clone().unwrap();
+    for change in changes[start + 1..=position].iter() {
+        // removals are applied before additions when a relation is stabilized:
+        if !change.removed.is_empty() {
+            tuples.retain(|t| change.removed.binary_search(t).is_err());
+        }
+        tuples = tuples.merge(change.added.clone());
+    }
+    tuples
+}
+
+/// Is the history of the changes to a relation since the epoch at which the history starts.
+///
+/// **Note**: the batches of tuples in the history are shared with the instance of the
+/// relation until they are merged in the instance. The list of changes is shared with the
+/// clones of the history and is copied when a clone records a change. Every
+/// `CHECKPOINT_INTERVAL` changes, the tuples of the relation are stored as a checkpoint, so
+/// that reconstructing the relation at an epoch replays fewer than `CHECKPOINT_INTERVAL`
+/// changes. The first change of the history adds the tuples of the relation at the epoch
+/// at which the history starts and is a checkpoint.
+pub(super) struct History<T: Tuple> {
+    since: u64,
+    changes: Shared<Arc<Vec<Change<T>>>>,
+}
+
+impl<T: Tuple> History<T> {
+    /// Creates a new history of `instance`, starting with its stable tuples at `epoch`.
+    pub fn new(instance: &Instance<T>, epoch: u64) -> Self {
+        let added = instance
+            .stable()
+            .iter()
+            .fold(Tuples::from(Vec::new()), |all, batch| {
+                all.merge(batch.clone())
+            });
+        Self {
+            since: epoch,
+            changes: shared(Arc::new(vec![Change {
+                epoch,
+                added: added.clone(),
+                removed: Vec::new().into(),
+                checkpoint: Some(added),
+            }])),
+        }
+    }
+}
+
+impl<T> DynHistory for History<T>
+where
+    T: Tuple + 'static,
+{
+    fn record(&self, instance: &dyn DynInstance, epoch: u64) {
+        let instance = instance
+            .as_any()
+            .downcast_ref::<Instance<T>>()
+            .expect("instance of the relation");
+        let added = instance.recent().clone();
+        let removed = instance.removed().clone();
+        if !added.is_empty() || !removed.is_empty() {
+            let mut changes = self.changes.borrow_mut();
+            let changes = Arc::make_mut(&mut changes);
+            changes.push(Change {
+                epoch,
+                added,
+                removed,
+                checkpoint: None,
+            });
+            let position = changes.len() - 1;
+            let last = changes
+                .iter()
+                .rposition(|c| c.checkpoint.is_some())
+                .expect("the first change is a checkpoint");
+            if position - last >= CHECKPOINT_INTERVAL {
+                changes[position].checkpoint = Some(state(changes, position));
+            }
+        }
+    }
+
+    fn as_of(&self, epoch: Option<u64>) -> Option<Box<dyn DynInstance>> {
+        let instance = Instance::new();
+        let epoch = match epoch {
+            Some(epoch) if epoch >= self.since => epoch,
+            Some(_) => return None,
+            None => return Some(Box::new(instance)),
+        };
+
+        // the first change is at `since`; so, at least one change is at or before `epoch`:
+        let changes = self.changes.borrow();
+        let position = changes.partition_point(|c| c.epoch <= epoch) - 1;
+        instance.insert(state(&changes, position));
+        Some(Box::new(instance))
+    }
+
+    fn trim(&mut self, epoch: u64) {
+        if epoch <= self.since {
+            return;
+        }
+
+        let mut changes = self.changes.borrow_mut();
+        let position = changes.partition_point(|c| c.epoch <= epoch) - 1;
+        let tuples = state(&changes, position);
+        let mut trimmed = vec![Change {
+            epoch,
+            added: tuples.clone(),
+            removed: Vec::new().into(),
+            checkpoint: Some(tuples),
+        }];
+        trimmed.extend(changes[position + 1..].iter().cloned());
+        *changes = Arc::new(trimmed);
+        self.since = epoch;
+    }
+
+    fn changes(&self, since: Option<u64>, until: u64) -> Option<(Box<dyn Any>, Box<dyn Any>)> {
+        if matches!(since, Some(since) if since < self.since) {
+            return None;
+        }
+
+        // the tuples of a change are added to or removed from the relation by the change;
+        // therefore, a tuple that is removed after it is added (or vice versa) is unchanged:
+        let mut added = BTreeSet::new();
+        let mut removed = BTreeSet::new();
+        let changes = self.changes.borrow();
+        // `Option::is_none_or` is not available in the older supported versions of Rust:
+        #[allow(clippy::unnecessary_map_or)]
+        let changes = changes
+            .iter()
+            .filter(|c| since.map_or(true, |since| c.epoch > since) && c.epoch <= until);
+        for change in changes {
+            for t in change.removed.iter() {
+                if !added.remove(t) {
+                    removed.insert(t.clone());
+                }
+            }
+            for t in change.added.iter() {
+                if !removed.remove(t) {
+                    added.insert(t.clone());
+                }
+            }
+        }
+
+        Some((
+            Box::new(Tuples::from(added)),
+            Box::new(Tuples::from(removed)),
+        ))
+    }
+
+    fn clone_box(&self) -> Box<dyn DynHistory> {
+        Box::new(Self {
+            since: self.since,
+            changes: shared(self.changes.borrow().clone()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Cursor, Database, Error, Expression, Tuples};
+
+    #[test]
+    fn test_evaluate_as_of() {
+        let mut database = Database::new();
+        let r = database.add_relation::<(i32, i32)>("r").unwrap();
+        let s = database.add_relation::<(i32, i32)>("s").unwrap();
+        database.insert(&r, vec![(1, 10)].into()).unwrap();
+        assert_eq!(1, database.epoch().unwrap());
+
+        database.track_history(&r).unwrap();
+        database.track_history(&s).unwrap();
+        let by_key = database.add_index(&r, |t| t.0).unwrap();
+        database
+            .insert(&s, vec![(1, 100), (2, 200)].into())
+            .unwrap();
+        database.insert(&r, vec![(2, 20)].into()).unwrap();
+        let epoch = database.epoch().unwrap();
+
+        database.remove(&r, vec![(1, 10)].into()).unwrap();
+        database.insert(&r, vec![(3, 30)].into()).unwrap();
+
+        let join = by_key
+            .with_key()
+            .join(s.builder().with_key(|t| t.0))
+            .on(|_, l, r| l.1 + r.1)
+            .build();
+        assert_eq!(Tuples::from(vec![220]), database.evaluate(&join).unwrap());
+        assert_eq!(
+            Tuples::from(vec![110, 220]),
+            database.evaluate_as_of(&join, epoch).unwrap()
+        );
+        assert_eq!(
+            Tuples::from(vec![(1, 10)]),
+            database.evaluate_as_of(&by_key.lookup(1), epoch).unwrap()
+        );
+        assert_eq!(
+            Tuples::from(vec![(1, 10)]),
+            database.evaluate_as_of(&r, 1).unwrap()
+        );
+        assert!(matches!(
+            database.evaluate_as_of(&r, 0),
+            Err(Error::HistoryNotFound { epoch: 0, .. })
+        ));
+
+        let cloned = database.clone();
+        assert_eq!(
+            cloned.evaluate_as_of(&join, epoch).unwrap(),
+            database.evaluate_as_of(&join, epoch).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_evaluate_since() {
+        let mut database = Database::new();
+        let r = database.add_relation::<(i32, i32)>("r").unwrap();
+        let s = database.add_relation::<(i32, i32)>("s").unwrap();
+        let t = database.add_relation::<i32>("t").unwrap();
+        database.insert(&r, vec![(1, 10)].into()).unwrap();
+        database.track_history(&r).unwrap();
+        database.track_history(&s).unwrap();
+        database
+            .insert(&s, vec![(1, 100), (2, 200)].into())
+            .unwrap();
+
+        let join = r
+            .builder()
+            .with_key(|t| t.0)
+            .join(s.builder().with_key(|t| t.0))
+            .on(|_, l, r| l.1 + r.1)
+            .build();
+        let (tuples, cursor) = database.evaluate_since(&join, &Cursor::default()).unwrap();
+        assert_eq!(Tuples::from(vec![110]), tuples);
+        assert_eq!(Some(database.epoch().unwrap()), cursor.epoch());
+
+        // nothing is new without changes:
+        let (tuples, cursor) = database.evaluate_since(&join, &cursor).unwrap();
+        assert!(tuples.is_empty());
+
+        // removals are not returned and tuples that are added and removed cancel out:
+        database.remove(&r, vec![(1, 10)].into()).unwrap();
+        database.insert(&r, vec![(2, 20), (3, 30)].into()).unwrap();
+        database.evaluate(&r).unwrap();
+        database.insert(&s, vec![(3, 300)].into()).unwrap();
+        database.remove(&r, vec![(3, 30)].into()).unwrap();
+        let (tuples, next) = database.evaluate_since(&join, &cursor).unwrap();
+        assert_eq!(Tuples::from(vec![220]), tuples);
+
+        // earlier cursors can be reused:
+        let (tuples, _) = database.evaluate_since(&join, &cursor).unwrap();
+        assert_eq!(Tuples::from(vec![220]), tuples);
+        database.insert(&r, vec![(1, 11)].into()).unwrap();
+        let (tuples, _) = database.evaluate_since(&join, &next).unwrap();
+        assert_eq!(Tuples::from(vec![111]), tuples);
+
+        assert!(matches!(
+            database.evaluate_since(&t, &cursor),
+            Err(Error::HistoryNotFound { .. })
+        ));
+        let view = database.store_view(t.clone()).unwrap();
+        assert!(matches!(
+            database.evaluate_since(&view, &Cursor::default()),
+            Err(Error::UnsupportedExpression { .. })
+        ));
+    }
+
+    #[test]
+    fn test_evaluate_as_of_checkpoints() {
+        let mut database = Database::new();
+        let r = database.add_relation::<i32>("r").unwrap();
+        database.track_history(&r).unwrap();
+
+        let mut epochs = Vec::new();
+        for i in 0..200 {
+            database.insert(&r, vec![i].into()).unwrap();
+            if i % 3 == 0 {
+                database.remove(&r, vec![i / 2].into()).unwrap();
+            }
+            epochs.push(database.epoch().unwrap());
+        }
+
+        let mut expected = std::collections::BTreeSet::new();
+        for (i, epoch) in epochs.into_iter().enumerate() {
+            let i = i as i32;
+            expected.insert(i);
+            if i % 3 == 0 {
+                expected.remove(&(i / 2));
+            }
+            assert_eq!(
+                Tuples::from(expected.iter().cloned()),
+                database.evaluate_as_of(&r, epoch).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_trim_history() {
+        let mut database = Database::new();
+        let r = database.add_relation::<i32>("r").unwrap();
+        let s = database.add_relation::<i32>("s").unwrap();
+        database.track_history(&r).unwrap();
+        database.insert(&r, vec![1, 2].into()).unwrap();
+        let first = database.epoch().unwrap();
+        database.remove(&r, vec![1].into()).unwrap();
+        let second = database.epoch().unwrap();
+        database.insert(&r, vec![3].into()).unwrap();
+        let third = database.epoch().unwrap();
+
+        let cloned = database.clone();
+        database.trim_history(&r, second).unwrap();
+        assert!(matches!(
+            database.evaluate_as_of(&r, first),
+            Err(Error::HistoryNotFound { .. })
+        ));
+        assert_eq!(
+            Tuples::from(vec![2]),
+            database.evaluate_as_of(&r, second).unwrap()
+        );
+        assert_eq!(
+            Tuples::from(vec![2, 3]),
+            database.evaluate_as_of(&r, third).unwrap()
+        );
+        assert_eq!(
+            Tuples::from(vec![1, 2]),
+            cloned.evaluate_as_of(&r, first).unwrap()
+        );
+
+        // trimming before the start of the history does nothing:
+        database.trim_history(&r, first).unwrap();
+        assert!(database.evaluate_as_of(&r, second).is_ok());
+
+        // the history is trimmed at most at the current epoch:
+        database.trim_history(&r, third + 10).unwrap();
+        database.insert(&r, vec![4].into()).unwrap();
+        let fourth = database.epoch().unwrap();
+        assert_eq!(
+            Tuples::from(vec![2, 3]),
+            database.evaluate_as_of(&r, third).unwrap()
+        );
+        assert_eq!(
+            Tuples::from(vec![2, 3, 4]),
+            database.evaluate_as_of(&r, fourth).unwrap()
+        );
+
+        assert!(matches!(
+            database.trim_history(&s, third),
+            Err(Error::HistoryNotFound { .. })
+        ));
+        database.untrack_history(&r).unwrap();
+        database.untrack_history(&s).unwrap();
+        assert!(matches!(
+            database.evaluate_as_of(&r, fourth),
+            Err(Error::HistoryNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_evaluate_since_delta() {
+        let mut database = Database::new();
+        let r = database.add_relation::<(i32, i32)>("r").unwrap();
+        let s = database.add_relation::<i32>("s").unwrap();
+        database.track_history(&r).unwrap();
+        database.track_history(&s).unwrap();
+        let by_key = database.add_index(&r, |t| t.0).unwrap();
+        database.insert(&r, vec![(1, 10), (2, 20)].into()).unwrap();
+        database.insert(&s, vec![1].into()).unwrap();
+
+        let tens = r.builder().project(|t| t.1 / 10).build();
+        let unmatched = r.builder().project(|t| t.0).difference(s.clone()).build();
+        let joined = by_key
+            .with_key()
+            .join(s.builder().with_key(|t| *t))
+            .on(|_, l, _| l.1)
+            .build();
+        let (_, cursor) = database.evaluate_since(&tens, &Cursor::default()).unwrap();
+
+        // a new tuple that is derived again is not returned:
+        database.insert(&r, vec![(1, 11), (3, 30)].into()).unwrap();
+        let (tuples, _) = database.evaluate_since(&tens, &cursor).unwrap();
+        assert_eq!(Tuples::from(vec![3]), tuples);
+        let sums = r
+            .builder()
+            .group_by(|t| t.0)
+            .aggregate(0, |s, t| s + t.1)
+            .build();
+        let (tuples, _) = database.evaluate_since(&sums, &cursor).unwrap();
+        assert_eq!(Tuples::from(vec![(1, 21), (3, 30)]), tuples);
+
+        // a tuple that is no longer removed by the right side of a difference is new:
+        database.remove(&s, vec![1].into()).unwrap();
+        let (tuples, _) = database.evaluate_since(&unmatched, &cursor).unwrap();
+        assert_eq!(Tuples::from(vec![1, 3]), tuples);
+
+        // the indexes of the relations are evaluated with the changes:
+        database.insert(&s, vec![1, 3].into()).unwrap();
+        let (tuples, _) = database.evaluate_since(&joined, &cursor).unwrap();
+        assert_eq!(Tuples::from(vec![11, 30]), tuples);
+        let (tuples, _) = database
+            .evaluate_since(&joined, &Cursor::default())
+            .unwrap();
+        assert_eq!(Tuples::from(vec![10, 11, 30]), tuples);
+
+        let mut database = Database::new();
+        let r = database.add_relation::<(i32, i32)>("r").unwrap();
+        database.insert(&r, vec![(1, 10)].into()).unwrap();
+        let cursor = Cursor::new(database.epoch().unwrap());
+        database.insert(&r, vec![(2, 20)].into()).unwrap();
+        database.track_history(&r).unwrap();
+        assert!(matches!(
+            database.evaluate_since(&r, &cursor),
+            Err(Error::HistoryNotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn test_evaluate_as_of_unsupported() {
+        let mut database = Database::new();
+        let r = database.add_relation::<i32>("r").unwrap();
+        let s = database.add_relation::<i32>("s").unwrap();
+        let view = database.store_view(r.clone()).unwrap();
+        database.track_history(&r).unwrap();
+        assert!(matches!(
+            database.evaluate_as_of(&s, 0),
+            Err(Error::HistoryNotFound { .. })
+        ));
+        assert!(matches!(
+            database.evaluate_as_of(&view, 0),
+            Err(Error::UnsupportedExpression { .. })
+        ));
+    }
+}
@@ -1,21 +1,60 @@
-use super::{evaluate, expression_ext::ExpressionExt, helpers::gallop, Database};
-use crate::{expression::Expression, Error, Tuple};
-use std::any::Any;
+use super::{
+    evaluate,
+    explain::Statistics,
+    expression_ext::ExpressionExt,
+    helpers::gallop,
+    history::{DynHistory, History},
+    Database,
+};
+use crate::{
+    expression::{Aggregate, Closure, Expression, UnaryFn},
+    Error, MaybeSync, Tuple,
+};
 use std::{
-    cell::{Ref, RefCell},
+    any::Any,
+    collections::{BTreeMap, BTreeSet},
     ops::Deref,
+};
+
+#[cfg(feature = "sync")]
+use std::sync::{Arc, RwLockReadGuard as Ref};
+#[cfg(not(feature = "sync"))]
+use std::{
+    cell::{Ref, RefCell},
     rc::Rc,
 };
 
+/// Is the type of the shared state of instances.
+#[cfg(not(feature = "sync"))]
+pub(super) type Shared<T> = Rc<RefCell<T>>;
+
+/// Is the type of the shared state of instances.
+#[cfg(feature = "sync")]
+pub(super) type Shared<T> = Arc<crate::sync::Lock<T>>;
+
+/// Creates a new shared state, containing `value`.
+#[cfg(not(feature = "sync"))]
+pub(super) fn shared<T>(value: T) -> Shared<T> {
+    Rc::new(RefCell::new(value))
+}
+
+/// Creates a new shared state, containing `value`.
+#[cfg(feature = "sync")]
+pub(super) fn shared<T>(value: T) -> Shared<T> {
+    Arc::new(crate::sync::Lock::new(value))
+}
+
 /// Is a wrapper around a vector of tuples. As an invariant, the content of `Tuples` is sorted.
 ///
-/// **Note**: `Tuples` is borrowed from `Relation` in [`datafrog`].
+/// **Note**: `Tuples` is borrowed from `Relation` in [`datafrog`]. The vector of tuples is
+/// reference-counted: cloning `Tuples` shares the vector, which is copied when a clone is
+/// mutated.
 ///
 /// [`datafrog`]: https://github.com/rust-lang/datafrog
 #[derive(Clone, Debug, PartialEq)]
 pub struct Tuples<T: Tuple> {
     /// Is the vector of tuples in this instance.
-    items: Vec<T>,
+    items: std::sync::Arc<Vec<T>>,
 }
 
 impl<T: Tuple, I: IntoIterator<Item = T>> From<I> for Tuples<T> {
@@ -23,7 +62,9 @@ impl<T: Tuple, I: IntoIterator<Item = T>> From<I> for Tuples<T> {
         let mut items: Vec<T> = iterator.into_iter().collect();
         items.sort_unstable();
         items.dedup();
-        Tuples { items }
+        Tuples {
+            items: std::sync::Arc::new(items),
+        }
     }
 }
 
@@ -31,9 +72,8 @@ impl<T: Tuple> Tuples<T> {
     /// Merges the instances of the reciver with `other` and returns a new `Tuples`
     /// instance.
     pub(crate) fn merge(self, other: Self) -> Self {
-        let mut tuples = Vec::with_capacity(self.items.len() + other.items.len());
-        tuples.extend(self.items.into_iter());
-        tuples.extend(other.items.into_iter());
+        let mut tuples = self.into_tuples();
+        tuples.extend(other.into_tuples());
         tuples.into()
     }
 
@@ -45,7 +85,31 @@ impl<T: Tuple> Tuples<T> {
     /// Consumes the receiver and returns the underlying (sorted) vector of tuples.
     #[inline(always)]
     pub fn into_tuples(self) -> Vec<T> {
-        self.items
+        std::sync::Arc::try_unwrap(self.items).unwrap_or_else(|items| (*items).clone())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T: Tuple + serde::Serialize> serde::Serialize for Tuples<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.items.as_slice().serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T> serde::Deserialize<'de> for Tuples<T>
+where
+    T: Tuple + serde::Deserialize<'de>,
+{
+    /// Deserializes a sequence of tuples, sorting the tuples and removing duplicates.
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Vec::<T>::deserialize(deserializer).map(Tuples::from)
     }
 }
 
@@ -59,41 +123,96 @@ impl<T: Tuple> Deref for Tuples<T> {
 
 impl<T: Tuple> core::ops::DerefMut for Tuples<T> {
     fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.items
+        std::sync::Arc::make_mut(&mut self.items)
     }
 }
 
 /// Is used to store database `Instance`s in a map by hiding their (generic) type.
-pub(super) trait DynInstance {
+pub(super) trait DynInstance: MaybeSync {
     /// Returns the instance as `Any`
     fn as_any(&self) -> &dyn Any;
 
     /// Returns true if the instance has been affected by last updates. It also moves all
-    /// `to_add` tuples to `recent` and `recent` tuples to `stable`.
+    /// `to_add` tuples to `recent` and `recent` tuples to `stable`, and removes the
+    /// `to_remove` tuples from `stable`, keeping them in `removed`.
     fn changed(&self) -> bool;
 
+    /// Returns true if tuples have been recently removed from the instance.
+    fn has_removed(&self) -> bool;
+
+    /// Returns the number of tuples in the batches of the instance.
+    fn statistics(&self) -> Statistics;
+
+    /// Returns a new history of the changes to the instance, starting with its stable
+    /// tuples at `epoch`.
+    fn history(&self, epoch: u64) -> Box<dyn DynHistory>;
+
+    /// Returns a new instance like `Instance::delta` with `added`, `recent` and `removed`,
+    /// the `Tuples` of the relation of the instance.
+    fn delta(&self, added: &dyn Any, recent: &dyn Any, removed: &dyn Any) -> Box<dyn DynInstance>;
+
     /// Clones the instance in a `Box`.
     fn clone_box(&self) -> Box<dyn DynInstance>;
 }
 
 /// Is used to store `ViewInstance`s in a map by hiding their (generic) types.
-pub(super) trait DynViewInstance {
-    /// Returns the view instance as `Any`.
-    fn as_any(&self) -> &dyn Any;
-
+pub(super) trait DynViewInstance: MaybeSync {
     /// Returns the `Instance` storing the tuples of the view as a trait object.
     fn instance(&self) -> &dyn DynInstance;
 
     /// Initializes the view with the existing tuples in `db`.
     fn initialize(&self, db: &Database) -> Result<(), Error>;
 
-    /// Stabilizes the view from the `recent` tuples in the instances of `db`.
+    /// Stabilizes the view from the `recent` and `removed` tuples in the instances of `db`.
     fn stabilize(&self, db: &Database) -> Result<(), Error>;
 
     /// Clones the instance in a `Box`.
     fn clone_box(&self) -> Box<dyn DynViewInstance>;
 }
 
+/// Is a `DynViewInstance` in a `Box`, returned by `ExpressionExt::into_view_instance`
+/// without exposing `DynViewInstance` in the interface of `ExpressionExt`.
+pub struct ViewInstanceBox(pub(super) Box<dyn DynViewInstance>);
+
+/// Is used to store the `IndexInstance`s of a relation by hiding their (generic) types.
+pub(super) trait DynIndexInstance: MaybeSync {
+    /// Returns the index as `Any`.
+    fn as_any(&self) -> &dyn Any;
+
+    /// Returns the `Instance` storing the keyed tuples of the index as a trait object.
+    fn instance(&self) -> &dyn DynInstance;
+
+    /// Inserts `tuples`, the `Tuples` being inserted into the relation of the index, into
+    /// the index.
+    fn insert(&self, tuples: &dyn Any);
+
+    /// Removes `tuples`, the `Tuples` being removed from the relation of the index, from
+    /// the index.
+    fn remove(&self, tuples: &dyn Any);
+
+    /// Returns the tuples of `tuples`, the `Tuples` being inserted into the relation of the
+    /// index, and the current tuples of the index that share a key with a different tuple,
+    /// formatted by `Debug`. The tuples of `removing`, the `Tuples` being removed from the
+    /// relation along with the insertion, are not current.
+    fn conflicts(&self, tuples: &dyn Any, removing: &dyn Any) -> Vec<String>;
+
+    /// Returns a new index with the key of the receiver on `instance`, the `Instance` of
+    /// the relation of the index.
+    fn rebuild(&self, instance: &dyn Any) -> Box<dyn DynIndexInstance>;
+
+    /// Returns a new index with the keyed tuples of the receiver like `Instance::delta`
+    /// with `added`, `recent` and `removed`, the `Tuples` of the relation of the index.
+    fn delta(
+        &self,
+        added: &dyn Any,
+        recent: &dyn Any,
+        removed: &dyn Any,
+    ) -> Box<dyn DynIndexInstance>;
+
+    /// Clones the instance in a `Box`.
+    fn clone_box(&self) -> Box<dyn DynIndexInstance>;
+}
+
 /// Contains the tuples of a relation in the database.
 ///
 /// **Note**: `Instance` is a replica of `Variable` in [`datafrog`].
@@ -102,34 +221,60 @@ pub(super) trait DynViewInstance {
 #[derive(Debug, PartialEq)]
 pub(super) struct Instance<T: Tuple> {
     /// Is the set of tuples that are already considered when updating views.
-    stable: Rc<RefCell<Vec<Tuples<T>>>>,
+    stable: Shared<Vec<Tuples<T>>>,
 
     /// Is the set of tuples that have not yet been reflected in views.
-    recent: Rc<RefCell<Tuples<T>>>,
+    recent: Shared<Tuples<T>>,
 
     /// Is the set of tuples to add: they may be duplicates of existing tuples
     /// in which case they are ignored.
-    to_add: Rc<RefCell<Vec<Tuples<T>>>>,
+    to_add: Shared<Vec<Tuples<T>>>,
+
+    /// Is the set of tuples that have been recently removed from `stable` but
+    /// their removal has not yet been reflected in views.
+    removed: Shared<Tuples<T>>,
+
+    /// Is the set of tuples to remove: they may not exist in the instance in
+    /// which case they are ignored.
+    to_remove: Shared<Vec<Tuples<T>>>,
 }
 
 impl<T: Tuple> Instance<T> {
     /// Creates a new empty isntance.
     pub fn new() -> Self {
         Self {
-            stable: Rc::new(RefCell::new(Vec::new())),
-            recent: Rc::new(RefCell::new(Vec::new().into())),
-            to_add: Rc::new(RefCell::new(Vec::new())),
+            stable: shared(Vec::new()),
+            recent: shared(Vec::new().into()),
+            to_add: shared(Vec::new()),
+            removed: shared(Vec::new().into()),
+            to_remove: shared(Vec::new()),
         }
     }
 
     /// Adds a `Tuples` instance to `to_add` tuples. These tuples will be ultimately
-    /// added to the instance if they already don't exist.
+    /// added to the instance if they already don't exist. Pending removals of the
+    /// same tuples are cancelled.
     pub fn insert(&self, tuples: Tuples<T>) {
         if !tuples.is_empty() {
+            for batch in self.to_remove.borrow_mut().iter_mut() {
+                retain_missing(batch, &tuples, |_| {});
+            }
             self.to_add.borrow_mut().push(tuples);
         }
     }
 
+    /// Adds a `Tuples` instance to `to_remove` tuples. These tuples will be ultimately
+    /// removed from the instance if they exist. Pending insertions of the same tuples
+    /// are cancelled.
+    pub fn remove(&self, tuples: Tuples<T>) {
+        if !tuples.is_empty() {
+            for batch in self.to_add.borrow_mut().iter_mut() {
+                retain_missing(batch, &tuples, |_| {});
+            }
+            self.to_remove.borrow_mut().push(tuples);
+        }
+    }
+
     /// Returns an immutable reference (of type `std::cell::Ref`) to the stable tuples
     /// of this instance.
     #[inline(always)]
@@ -150,14 +295,95 @@ impl<T: Tuple> Instance<T> {
     pub fn to_add(&self) -> Ref<Vec<Tuples<T>>> {
         self.to_add.borrow()
     }
+
+    /// Returns an immutable reference (of type `std::cell::Ref`) to the tuples that
+    /// have been recently removed from this instance.
+    #[inline(always)]
+    pub fn removed(&self) -> Ref<Tuples<T>> {
+        self.removed.borrow()
+    }
+
+    /// Returns an immutable reference (of type `std::cell::Ref`) to the candidates to
+    /// be removed from this instance (if they exist).
+    #[inline(always)]
+    pub fn to_remove(&self) -> Ref<Vec<Tuples<T>>> {
+        self.to_remove.borrow()
+    }
+
+    /// Returns a new instance with the stable tuples of this instance, except for the
+    /// tuples of `added`, as its stable tuples, `recent` as its recent tuples and `removed`
+    /// as its removed tuples; that is, the new instance looks as if it is stabilized with
+    /// `recent` and `removed`. The stable batches that contain none of the tuples of `added`
+    /// are shared with this instance.
+    pub fn delta(&self, added: &Tuples<T>, recent: Tuples<T>, removed: Tuples<T>) -> Self {
+        let mut stable = self.stable.borrow().clone();
+        for batch in stable.iter_mut() {
+            if added.iter().any(|t| batch.binary_search(t).is_ok()) {
+                retain_missing(batch, added, |_| {});
+            }
+        }
+        stable.retain(|batch| !batch.is_empty());
+        Self {
+            stable: shared(stable),
+            recent: shared(recent),
+            to_add: shared(Vec::new()),
+            removed: shared(removed),
+            to_remove: shared(Vec::new()),
+        }
+    }
+
+    /// Returns all tuples of this instance, including the candidates to be added and
+    /// excluding the candidates to be removed.
+    #[cfg(feature = "serde")]
+    pub fn current(&self) -> Tuples<T> {
+        let mut items = self.recent.borrow().items().to_vec();
+        for batch in self.stable.borrow().iter() {
+            items.extend(batch.iter().cloned());
+        }
+        for batch in self.to_add.borrow().iter() {
+            items.extend(batch.iter().cloned());
+        }
+        let mut result = Tuples::from(items);
+        for batch in self.to_remove.borrow().iter() {
+            retain_missing(&mut result, batch, |_| {});
+        }
+        result
+    }
+}
+
+/// Removes the elements of `batch` that appear in the sorted slice `tuples` and
+/// applies `removed` on every removed element.
+fn retain_missing<T: Tuple>(batch: &mut Tuples<T>, tuples: &[T], mut removed: impl FnMut(&T)) {
+    // avoid copying a shared batch that contains none of `tuples`:
+    let mut slice = tuples;
+    let missing = batch.iter().all(|x| {
+        slice = gallop(slice, |y| y < x);
+        slice.is_empty() || &slice[0] != x
+    });
+    if missing {
+        return;
+    }
+
+    let mut slice = tuples;
+    batch.retain(|x| {
+        slice = gallop(slice, |y| y < x);
+        if !slice.is_empty() && &slice[0] == x {
+            removed(x);
+            false
+        } else {
+            true
+        }
+    });
 }
 
 impl<T: Tuple> Clone for Instance<T> {
     fn clone(&self) -> Self {
         Self {
-            stable: Rc::new(RefCell::new(self.stable.borrow().clone())),
-            recent: Rc::new(RefCell::new(self.recent.borrow().clone())),
-            to_add: Rc::new(RefCell::new(self.to_add.borrow().clone())),
+            stable: shared(self.stable.borrow().clone()),
+            recent: shared(self.recent.borrow().clone()),
+            to_add: shared(self.to_add.borrow().clone()),
+            removed: shared(self.removed.borrow().clone()),
+            to_remove: shared(self.to_remove.borrow().clone()),
         }
     }
 }
@@ -187,6 +413,20 @@ where
             self.stable.borrow_mut().push(recent);
         }
 
+        self.removed.borrow_mut().clear();
+        let to_remove = self.to_remove.borrow_mut().pop();
+        if let Some(mut to_remove) = to_remove {
+            while let Some(to_remove_more) = self.to_remove.borrow_mut().pop() {
+                to_remove = to_remove.merge(to_remove_more);
+            }
+            let mut removed = Vec::new();
+            for batch in self.stable.borrow_mut().iter_mut() {
+                retain_missing(batch, &to_remove, |t| removed.push(t.clone()));
+            }
+            self.stable.borrow_mut().retain(|batch| !batch.is_empty());
+            *self.removed.borrow_mut() = removed.into();
+        }
+
         let to_add = self.to_add.borrow_mut().pop();
         if let Some(mut to_add) = to_add {
             while let Some(to_add_more) = self.to_add.borrow_mut().pop() {
@@ -194,7 +434,7 @@ where
             }
             for batch in self.stable.borrow().iter() {
                 let mut slice = &batch[..];
-                to_add.items.retain(|x| {
+                to_add.retain(|x| {
                     slice = gallop(slice, |y| y < x);
                     slice.is_empty() || &slice[0] != x
                 });
@@ -202,7 +442,41 @@ where
             *self.recent.borrow_mut() = to_add;
         }
 
-        !self.recent.borrow().is_empty()
+        !self.recent.borrow().is_empty() || !self.removed.borrow().is_empty()
+    }
+
+    fn has_removed(&self) -> bool {
+        !self.removed.borrow().is_empty()
+    }
+
+    fn statistics(&self) -> Statistics {
+        let lengths = |batches: &Vec<Tuples<T>>| batches.iter().map(|b| b.len()).collect();
+        Statistics::new(
+            lengths(&self.stable.borrow()),
+            self.recent.borrow().len(),
+            lengths(&self.to_add.borrow()),
+            self.removed.borrow().len(),
+            lengths(&self.to_remove.borrow()),
+        )
+    }
+
+    fn history(&self, epoch: u64) -> Box<dyn DynHistory> {
+        Box::new(History::new(self, epoch))
+    }
+
+    fn delta(&self, added: &dyn Any, recent: &dyn Any, removed: &dyn Any) -> Box<dyn DynInstance> {
+        let tuples = |tuples: &dyn Any| -> Tuples<T> {
+            tuples
+                .downcast_ref::<Tuples<T>>()
+                .expect("tuples of the relation")
+                .clone()
+        };
+        Box::new(Instance::delta(
+            self,
+            &tuples(added),
+            tuples(recent),
+            tuples(removed),
+        ))
     }
 
     fn clone_box(&self) -> Box<dyn DynInstance> {
@@ -218,10 +492,217 @@ where
             stable.push(batch.clone());
         }
 
+        let mut to_remove = Vec::new();
+        for batch in self.to_remove.borrow().iter() {
+            to_remove.push(batch.clone());
+        }
+
+        let removed = (*self.removed.borrow()).clone();
+
         Box::new(Self {
-            stable: Rc::new(RefCell::new(stable)),
-            recent: Rc::new(RefCell::new(recent)),
-            to_add: Rc::new(RefCell::new(to_add)),
+            stable: shared(stable),
+            recent: shared(recent),
+            to_add: shared(to_add),
+            removed: shared(removed),
+            to_remove: shared(to_remove),
+        })
+    }
+}
+
+/// Is an index on a relation, storing the tuples of the relation paired with their keys.
+/// Because the keyed tuples are inserted, removed and stabilized together with the tuples
+/// of the relation, the batches of the index mirror the batches of the relation.
+pub(super) struct IndexInstance<K, T>
+where
+    K: Tuple,
+    T: Tuple,
+{
+    /// Is the `Instance` storing the keyed tuples of the index.
+    instance: Instance<(K, T)>,
+
+    /// Is the closure that returns the keys of the tuples.
+    key: Closure<dyn UnaryFn<T, K>>,
+}
+
+impl<K, T> IndexInstance<K, T>
+where
+    K: Tuple,
+    T: Tuple,
+{
+    /// Creates a new index with the keyed tuples of the relation `instance`.
+    pub fn new(instance: &Instance<T>, key: Closure<dyn UnaryFn<T, K>>) -> Self {
+        let mut key_fn = key.get();
+        let mut keyed = |batch: &Tuples<T>| -> Tuples<(K, T)> {
+            batch.iter().map(|t| (key_fn(t), t.clone())).into()
+        };
+        let index = Instance {
+            stable: shared(instance.stable().iter().map(&mut keyed).collect()),
+            recent: shared(keyed(&instance.recent())),
+            to_add: shared(instance.to_add().iter().map(&mut keyed).collect()),
+            removed: shared(keyed(&instance.removed())),
+            to_remove: shared(instance.to_remove().iter().map(&mut keyed).collect()),
+        };
+        drop(key_fn);
+
+        Self {
+            instance: index,
+            key,
+        }
+    }
+
+    /// Returns a reference to the closure that returns the keys of the tuples.
+    pub fn key(&self) -> &Closure<dyn UnaryFn<T, K>> {
+        &self.key
+    }
+
+    /// Returns the tuples of the index with `key`, including the candidates to be added and
+    /// excluding the candidates to be removed.
+    pub fn lookup(&self, key: &K) -> Vec<T> {
+        let stable = self.instance.stable();
+        let recent = self.instance.recent();
+        let to_add = self.instance.to_add();
+        let to_remove = self.instance.to_remove();
+        let batches = stable
+            .iter()
+            .chain(std::iter::once(&*recent))
+            .chain(to_add.iter());
+        let mut result = Vec::new();
+        for batch in batches {
+            let start = batch.partition_point(|(k, _)| k < key);
+            for t in batch[start..].iter().take_while(|(k, _)| k == key) {
+                if !to_remove.iter().any(|r| r.binary_search(t).is_ok()) {
+                    result.push(t.1.clone());
+                }
+            }
+        }
+        result.sort();
+        result.dedup();
+        result
+    }
+
+    /// Returns true if the index contains a tuple with `key`, including the candidates to
+    /// be added and excluding the candidates to be removed.
+    pub fn contains(&self, key: &K) -> bool {
+        let stable = self.instance.stable();
+        let recent = self.instance.recent();
+        let to_add = self.instance.to_add();
+        let to_remove = self.instance.to_remove();
+        let mut batches = stable
+            .iter()
+            .chain(std::iter::once(&*recent))
+            .chain(to_add.iter());
+        batches.any(|batch| {
+            let start = batch.partition_point(|(k, _)| k < key);
+            batch[start..]
+                .iter()
+                .take_while(|(k, _)| k == key)
+                .any(|t| !to_remove.iter().any(|r| r.binary_search(t).is_ok()))
+        })
+    }
+
+    /// Returns the tuples of `tuples` paired with their keys.
+    fn keyed(&self, tuples: &dyn Any) -> Tuples<(K, T)>
+    where
+        T: 'static,
+    {
+        let tuples = tuples
+            .downcast_ref::<Tuples<T>>()
+            .expect("tuples of the indexed relation");
+        tuples
+            .iter()
+            .map(self.key.get())
+            .zip(tuples.iter().cloned())
+            .into()
+    }
+}
+
+impl<K, T> DynIndexInstance for IndexInstance<K, T>
+where
+    K: Tuple + 'static,
+    T: Tuple + 'static,
+{
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn instance(&self) -> &dyn DynInstance {
+        &self.instance
+    }
+
+    fn insert(&self, tuples: &dyn Any) {
+        self.instance.insert(self.keyed(tuples));
+    }
+
+    fn remove(&self, tuples: &dyn Any) {
+        self.instance.remove(self.keyed(tuples));
+    }
+
+    fn conflicts(&self, tuples: &dyn Any, removing: &dyn Any) -> Vec<String> {
+        let keyed = self.keyed(tuples);
+        let removing = self.keyed(removing);
+        let mut conflicts = BTreeSet::new();
+        for pair in keyed.windows(2) {
+            if pair[0].0 == pair[1].0 {
+                conflicts.insert(&pair[0]);
+                conflicts.insert(&pair[1]);
+            }
+        }
+
+        let stable = self.instance.stable();
+        let recent = self.instance.recent();
+        let to_add = self.instance.to_add();
+        let to_remove = self.instance.to_remove();
+        let batches = stable
+            .iter()
+            .chain(std::iter::once(&*recent))
+            .chain(to_add.iter());
+        for batch in batches {
+            for tuple in keyed.iter() {
+                let start = batch.partition_point(|(k, _)| k < &tuple.0);
+                for existing in batch[start..].iter().take_while(|(k, _)| k == &tuple.0) {
+                    if existing.1 != tuple.1
+                        && removing.binary_search(existing).is_err()
+                        && !to_remove.iter().any(|r| r.binary_search(existing).is_ok())
+                    {
+                        conflicts.insert(tuple);
+                        conflicts.insert(existing);
+                    }
+                }
+            }
+        }
+        conflicts
+            .into_iter()
+            .map(|(_, t)| format!("{:?}", t))
+            .collect()
+    }
+
+    fn rebuild(&self, instance: &dyn Any) -> Box<dyn DynIndexInstance> {
+        let instance = instance
+            .downcast_ref::<Instance<T>>()
+            .expect("instance of the indexed relation");
+        Box::new(Self::new(instance, self.key.clone()))
+    }
+
+    fn delta(
+        &self,
+        added: &dyn Any,
+        recent: &dyn Any,
+        removed: &dyn Any,
+    ) -> Box<dyn DynIndexInstance> {
+        Box::new(Self {
+            instance: self.instance.delta(
+                &self.keyed(added),
+                self.keyed(recent),
+                self.keyed(removed),
+            ),
+            key: self.key.clone(),
+        })
+    }
+
+    fn clone_box(&self) -> Box<dyn DynIndexInstance> {
+        Box::new(Self {
+            instance: self.instance.clone(),
+            key: self.key.clone(),
         })
     }
 }
@@ -238,6 +719,9 @@ where
 
     /// Is the view expression.
     expression: E,
+
+    /// Is true if the view appears in its own expression.
+    recursive: bool,
 }
 
 impl<T, E> ViewInstance<T, E>
@@ -249,12 +733,17 @@ where
         Self {
             instance: Instance::new(),
             expression,
+            recursive: false,
         }
     }
 
-    /// Returns the `Instance` storing the tuples of this view.
-    pub fn instance(&self) -> &Instance<T> {
-        &self.instance
+    /// Creates a new instance for a view that appears in its own `expression`.
+    pub fn recursive(expression: E) -> Self {
+        Self {
+            instance: Instance::new(),
+            expression,
+            recursive: true,
+        }
     }
 }
 
@@ -263,10 +752,6 @@ where
     T: Tuple + 'static,
     E: ExpressionExt<T> + 'static,
 {
-    fn as_any(&self) -> &dyn Any {
-        self
-    }
-
     fn instance(&self) -> &dyn DynInstance {
         &self.instance
     }
@@ -282,9 +767,17 @@ where
     }
 
     fn stabilize(&self, db: &Database) -> Result<(), Error> {
+        // retracting the tuples of a recursive view incrementally is unsound because
+        // tuples may support themselves through the recursion:
+        if self.recursive && self.has_removed_dependencies(db) {
+            return self.recompute(db);
+        }
+
         let incremental = evaluate::IncrementalCollector::new(db);
         let recent = self.expression.collect_recent(&incremental)?;
+        let removed = self.expression.collect_removed(&incremental)?;
 
+        self.instance.remove(removed);
         self.instance.insert(recent);
         Ok(())
     }
@@ -293,10 +786,248 @@ where
         Box::new(Self {
             instance: self.instance.clone(),
             expression: self.expression.clone(),
+            recursive: self.recursive,
+        })
+    }
+}
+
+impl<T, E> ViewInstance<T, E>
+where
+    T: Tuple + 'static,
+    E: ExpressionExt<T> + 'static,
+{
+    /// Returns true if tuples have been recently removed from any of the relations or
+    /// views in the view expression.
+    fn has_removed_dependencies(&self, db: &Database) -> bool {
+        let relations = self
+            .expression
+            .relation_dependencies()
+            .iter()
+            .filter_map(|r| db.relations.get(r))
+            .any(|r| r.instance.has_removed());
+        let views = self
+            .expression
+            .view_dependencies()
+            .iter()
+            .filter_map(|v| db.views.get(v))
+            .any(|v| v.instance.instance().has_removed());
+        relations || views
+    }
+
+    /// Evaluates the expression of a recursive view from scratch by iterating it to a
+    /// fixpoint, then schedules the difference between the result and the current
+    /// tuples of the view to be added or removed.
+    fn recompute(&self, db: &Database) -> Result<(), Error> {
+        let stable = self.instance.stable.replace(Vec::new());
+        let recent = self.instance.recent.replace(Vec::new().into());
+        self.instance.to_add.borrow_mut().clear();
+        self.instance.to_remove.borrow_mut().clear();
+
+        let incremental = evaluate::IncrementalCollector::new(db);
+        let fixpoint = || -> Result<Tuples<T>, Error> {
+            let mut current = Tuples::from(Vec::new());
+            loop {
+                let mut next = Tuples::from(Vec::new());
+                for batch in incremental.collect_current(&self.expression)? {
+                    next = next.merge(batch);
+                }
+                if next == current {
+                    return Ok(current);
+                }
+                *self.instance.stable.borrow_mut() = vec![next.clone()];
+                current = next;
+            }
+        };
+        let result = fixpoint();
+
+        // restore the view's instance before scheduling the changes:
+        let mut existing = recent.clone();
+        for batch in stable.iter() {
+            existing = existing.merge(batch.clone());
+        }
+        *self.instance.stable.borrow_mut() = stable;
+        *self.instance.recent.borrow_mut() = recent;
+
+        let result = result?;
+        let mut removed = existing;
+        retain_missing(&mut removed, &result, |_| {});
+        self.instance.remove(removed);
+        self.instance.insert(result);
+        Ok(())
+    }
+}
+
+/// Is a `ViewInstance` for views over [`Aggregate`] expressions that maintains an
+/// accumulator for every group, so that recent tuples are folded into the existing
+/// accumulators instead of folding the groups from scratch. Groups with removed tuples
+/// are folded again because accumulators cannot be retracted.
+///
+/// [`Aggregate`]: ../../expression/struct.Aggregate.html
+pub(super) struct AggregateViewInstance<K, S, A, E>
+where
+    K: Tuple,
+    S: Tuple,
+    A: Tuple,
+    E: Expression<S>,
+{
+    /// Is the `Instance` storing the tuples of the view.
+    instance: Instance<(K, A)>,
+
+    /// Is the view expression.
+    expression: Aggregate<K, S, A, E>,
+
+    /// Is the `Instance` storing the tuples of the sub-expression of the aggregate that
+    /// have been folded into the accumulators.
+    inputs: Instance<S>,
+
+    /// Maps the key of every group to its accumulated value. The map is shared with the
+    /// clones of the view instance and is copied when a clone is updated.
+    accumulators: Shared<std::sync::Arc<BTreeMap<K, A>>>,
+}
+
+impl<K, S, A, E> AggregateViewInstance<K, S, A, E>
+where
+    K: Tuple,
+    S: Tuple,
+    A: Tuple,
+    E: Expression<S>,
+{
+    pub fn new(expression: Aggregate<K, S, A, E>) -> Self {
+        Self {
+            instance: Instance::new(),
+            expression,
+            inputs: Instance::new(),
+            accumulators: shared(std::sync::Arc::new(BTreeMap::new())),
+        }
+    }
+}
+
+impl<K, S, A, E> DynViewInstance for AggregateViewInstance<K, S, A, E>
+where
+    K: Tuple + 'static,
+    S: Tuple + 'static,
+    A: Tuple + 'static,
+    E: ExpressionExt<S> + 'static,
+{
+    fn instance(&self) -> &dyn DynInstance {
+        &self.instance
+    }
+
+    fn initialize(&self, db: &Database) -> Result<(), Error> {
+        let incremental = evaluate::IncrementalCollector::new(db);
+        let stable = self.expression.expression().collect_stable(&incremental)?;
+
+        for batch in stable {
+            self.inputs.insert(batch);
+        }
+        self.update()
+    }
+
+    fn stabilize(&self, db: &Database) -> Result<(), Error> {
+        let incremental = evaluate::IncrementalCollector::new(db);
+        let recent = self.expression.expression().collect_recent(&incremental)?;
+        let removed = self.expression.expression().collect_removed(&incremental)?;
+
+        // a view may be stabilized more than once for the same recent tuples; `inputs`
+        // ignores the tuples that have already been folded:
+        self.inputs.remove(removed);
+        self.inputs.insert(recent);
+        self.update()
+    }
+
+    fn clone_box(&self) -> Box<dyn DynViewInstance> {
+        Box::new(Self {
+            instance: self.instance.clone(),
+            expression: self.expression.clone(),
+            inputs: self.inputs.clone(),
+            accumulators: shared(self.accumulators.borrow().clone()),
         })
     }
 }
 
+impl<K, S, A, E> AggregateViewInstance<K, S, A, E>
+where
+    K: Tuple + 'static,
+    S: Tuple + 'static,
+    A: Tuple + 'static,
+    E: ExpressionExt<S> + 'static,
+{
+    /// Folds the pending changes of `inputs` into the accumulators of the affected groups
+    /// and schedules the old values of these groups to be replaced by their new values.
+    fn update(&self) -> Result<(), Error> {
+        if !self.inputs.changed() {
+            return Ok(());
+        }
+        let recent = self.inputs.recent();
+        let removed = self.inputs.removed();
+        let mut changes: Vec<(K, Option<A>)> = Vec::new();
+
+        // groups with removed tuples are folded again from their current tuples:
+        let groups: Tuples<K> = removed.iter().map(self.expression.key_mut()).into();
+        if !groups.is_empty() {
+            let stable = self.inputs.stable();
+            let mut current = stable.iter().map(|t| &t[..]).collect::<Vec<_>>();
+            current.push(&recent);
+            let folded = evaluate::aggregate_batches(&self.expression, &current, |k| {
+                groups.binary_search(k).is_ok()
+            });
+            for k in groups.iter() {
+                let value = folded
+                    .binary_search_by(|(g, _)| g.cmp(k))
+                    .ok()
+                    .map(|i| folded[i].1.clone());
+                changes.push((k.clone(), value));
+            }
+        }
+
+        // recent tuples of the other groups are folded into their accumulators:
+        {
+            let accumulators = self.accumulators.borrow();
+            let mut key = self.expression.key_mut();
+            let keyed: Tuples<(K, &S)> = recent
+                .iter()
+                .map(|t| (key(t), t))
+                .filter(|(k, _)| groups.binary_search(k).is_err())
+                .into();
+
+            let mut fold = self.expression.fold_mut();
+            for (k, t) in keyed.iter() {
+                match changes.last_mut() {
+                    Some((last, Some(value))) if last == k => *value = fold(value, t),
+                    _ => {
+                        let value = accumulators
+                            .get(k)
+                            .unwrap_or_else(|| self.expression.init());
+                        changes.push((k.clone(), Some(fold(value, t))));
+                    }
+                }
+            }
+        }
+
+        let mut accumulators = self.accumulators.borrow_mut();
+        let accumulators = std::sync::Arc::make_mut(&mut accumulators);
+        let mut to_remove = Vec::new();
+        let mut to_add = Vec::new();
+        for (k, value) in changes {
+            let old = match &value {
+                Some(value) => accumulators.insert(k.clone(), value.clone()),
+                None => accumulators.remove(&k),
+            };
+            if old != value {
+                if let Some(old) = old {
+                    to_remove.push((k.clone(), old));
+                }
+                if let Some(value) = value {
+                    to_add.push((k, value));
+                }
+            }
+        }
+        self.instance.remove(to_remove.into());
+        self.instance.insert(to_add.into());
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -309,13 +1040,37 @@ mod tests {
         }
         {
             let instance = Instance::<i32> {
-                stable: Rc::new(RefCell::new(vec![vec![1, 2].into()])),
-                recent: Rc::new(RefCell::new(vec![2, 3, 4].into())),
-                to_add: Rc::new(RefCell::new(vec![vec![4, 5].into()])),
+                stable: shared(vec![vec![1, 2].into()]),
+                recent: shared(vec![2, 3, 4].into()),
+                to_add: shared(vec![vec![4, 5].into()]),
+                removed: shared(vec![].into()),
+                to_remove: shared(vec![]),
             };
             let cloned = instance.clone();
             assert_eq!(instance, cloned);
         }
+        {
+            let instance = Instance::<i32> {
+                stable: shared(vec![vec![1, 2].into(), vec![3].into()]),
+                recent: shared(vec![].into()),
+                to_add: shared(vec![]),
+                removed: shared(vec![].into()),
+                to_remove: shared(vec![]),
+            };
+            let cloned = instance.clone();
+            cloned.remove(vec![3].into());
+            cloned.changed();
+
+            // only the changed batch is copied:
+            let stable = instance.stable();
+            let cloned_stable = cloned.stable();
+            assert!(std::sync::Arc::ptr_eq(
+                &stable[0].items,
+                &cloned_stable[0].items
+            ));
+            assert_eq!(2, stable.len());
+            assert_eq!(1, cloned_stable.len());
+        }
     }
 
     #[test]
@@ -354,37 +1109,43 @@ mod tests {
     fn test_instance_insert() {
         {
             let relation = Instance::<i32> {
-                stable: Rc::new(RefCell::new(vec![])),
-                recent: Rc::new(RefCell::new(vec![].into())),
-                to_add: Rc::new(RefCell::new(vec![])),
+                stable: shared(vec![]),
+                recent: shared(vec![].into()),
+                to_add: shared(vec![]),
+                removed: shared(vec![].into()),
+                to_remove: shared(vec![]),
             };
             relation.insert(vec![].into());
             assert_eq!(Vec::<Tuples<i32>>::new(), *relation.stable.borrow());
-            assert_eq!(Vec::<i32>::new(), relation.recent.borrow().items);
+            assert_eq!(Vec::<i32>::new(), relation.recent.borrow().items());
             assert_eq!(Vec::<Tuples<i32>>::new(), *relation.to_add.borrow());
         }
 
         {
             let relation: Instance<i32> = Instance {
-                stable: Rc::new(RefCell::new(vec![])),
-                recent: Rc::new(RefCell::new(vec![1, 2, 3].into())),
-                to_add: Rc::new(RefCell::new(vec![])),
+                stable: shared(vec![]),
+                recent: shared(vec![1, 2, 3].into()),
+                to_add: shared(vec![]),
+                removed: shared(vec![].into()),
+                to_remove: shared(vec![]),
             };
             relation.insert(vec![].into());
             assert_eq!(Vec::<Tuples<i32>>::new(), *relation.stable.borrow());
-            assert_eq!(vec![1, 2, 3], relation.recent.borrow().items);
+            assert_eq!(vec![1, 2, 3], relation.recent.borrow().items());
             assert_eq!(Vec::<Tuples<i32>>::new(), *relation.to_add.borrow());
         }
 
         {
             let relation: Instance<i32> = Instance {
-                stable: Rc::new(RefCell::new(vec![])),
-                recent: Rc::new(RefCell::new(vec![1, 2, 3].into())),
-                to_add: Rc::new(RefCell::new(vec![])),
+                stable: shared(vec![]),
+                recent: shared(vec![1, 2, 3].into()),
+                to_add: shared(vec![]),
+                removed: shared(vec![].into()),
+                to_remove: shared(vec![]),
             };
             relation.insert(vec![5, 4].into());
             assert_eq!(Vec::<Tuples<i32>>::new(), *relation.stable.borrow());
-            assert_eq!(vec![1, 2, 3], relation.recent.borrow().items);
+            assert_eq!(vec![1, 2, 3], relation.recent.borrow().items());
             assert_eq!(
                 Vec::<Tuples<i32>>::from(vec![vec![4, 5].into()]),
                 *relation.to_add.borrow(),
@@ -396,86 +1157,137 @@ mod tests {
     fn test_instance_changed() {
         {
             let relation: Instance<i32> = Instance {
-                stable: Rc::new(RefCell::new(vec![])),
-                recent: Rc::new(RefCell::new(vec![].into())),
-                to_add: Rc::new(RefCell::new(vec![])),
+                stable: shared(vec![]),
+                recent: shared(vec![].into()),
+                to_add: shared(vec![]),
+                removed: shared(vec![].into()),
+                to_remove: shared(vec![]),
             };
             relation.changed();
             assert_eq!(Vec::<Tuples<i32>>::new(), *relation.stable.borrow());
-            assert_eq!(Vec::<i32>::new(), relation.recent.borrow().items);
+            assert_eq!(Vec::<i32>::new(), relation.recent.borrow().items());
             assert_eq!(Vec::<Tuples<i32>>::new(), *relation.to_add.borrow());
         }
 
         {
             let relation = Instance::<i32> {
-                stable: Rc::new(RefCell::new(vec![])),
-                recent: Rc::new(RefCell::new(vec![].into())),
-                to_add: Rc::new(RefCell::new(vec![vec![1, 2].into()])),
+                stable: shared(vec![]),
+                recent: shared(vec![].into()),
+                to_add: shared(vec![vec![1, 2].into()]),
+                removed: shared(vec![].into()),
+                to_remove: shared(vec![]),
             };
             assert!(relation.changed());
             assert_eq!(Vec::<Tuples<i32>>::new(), *relation.stable.borrow());
-            assert_eq!(vec![1, 2], relation.recent.borrow().items);
+            assert_eq!(vec![1, 2], relation.recent.borrow().items());
             assert_eq!(Vec::<Tuples<i32>>::new(), *relation.to_add.borrow());
         }
 
         {
             let relation = Instance::<i32> {
-                stable: Rc::new(RefCell::new(vec![])),
-                recent: Rc::new(RefCell::new(vec![1, 2].into())),
-                to_add: Rc::new(RefCell::new(vec![])),
+                stable: shared(vec![]),
+                recent: shared(vec![1, 2].into()),
+                to_add: shared(vec![]),
+                removed: shared(vec![].into()),
+                to_remove: shared(vec![]),
             };
             assert!(!relation.changed());
             assert_eq!(
                 Vec::<Tuples<i32>>::from(vec![vec![1, 2].into()]),
                 *relation.stable.borrow()
             );
-            assert_eq!(Vec::<i32>::new(), relation.recent.borrow().items);
+            assert_eq!(Vec::<i32>::new(), relation.recent.borrow().items());
             assert_eq!(Vec::<Tuples<i32>>::new(), *relation.to_add.borrow());
         }
 
         {
             let relation = Instance::<i32> {
-                stable: Rc::new(RefCell::new(vec![])),
-                recent: Rc::new(RefCell::new(vec![1, 2].into())),
-                to_add: Rc::new(RefCell::new(vec![vec![3, 4].into()])),
+                stable: shared(vec![]),
+                recent: shared(vec![1, 2].into()),
+                to_add: shared(vec![vec![3, 4].into()]),
+                removed: shared(vec![].into()),
+                to_remove: shared(vec![]),
             };
             assert!(relation.changed());
             assert_eq!(
                 Vec::<Tuples<i32>>::from(vec![vec![1, 2].into()]),
                 *relation.stable.borrow()
             );
-            assert_eq!(vec![3, 4], relation.recent.borrow().items);
+            assert_eq!(vec![3, 4], relation.recent.borrow().items());
             assert_eq!(Vec::<Tuples<i32>>::new(), *relation.to_add.borrow());
         }
 
         {
             let relation = Instance::<i32> {
-                stable: Rc::new(RefCell::new(vec![vec![1, 2].into()])),
-                recent: Rc::new(RefCell::new(vec![2, 3, 4].into())),
-                to_add: Rc::new(RefCell::new(vec![vec![4, 5].into()])),
+                stable: shared(vec![vec![1, 2].into()]),
+                recent: shared(vec![2, 3, 4].into()),
+                to_add: shared(vec![vec![4, 5].into()]),
+                removed: shared(vec![].into()),
+                to_remove: shared(vec![]),
             };
             assert!(relation.changed());
             assert_eq!(
                 Vec::<Tuples<i32>>::from(vec![vec![1, 2, 3, 4].into()]),
                 *relation.stable.borrow()
             );
-            assert_eq!(vec![5], relation.recent.borrow().items);
+            assert_eq!(vec![5], relation.recent.borrow().items());
             assert_eq!(Vec::<Tuples<i32>>::new(), *relation.to_add.borrow());
         }
 
         {
             let relation = Instance::<i32> {
-                stable: Rc::new(RefCell::new(vec![vec![1, 2].into()])),
-                recent: Rc::new(RefCell::new(vec![2, 3, 4].into())),
-                to_add: Rc::new(RefCell::new(vec![vec![1, 5].into()])),
+                stable: shared(vec![vec![1, 2].into()]),
+                recent: shared(vec![2, 3, 4].into()),
+                to_add: shared(vec![vec![1, 5].into()]),
+                removed: shared(vec![].into()),
+                to_remove: shared(vec![]),
             };
             assert!(relation.changed());
             assert_eq!(
                 Vec::<Tuples<i32>>::from(vec![vec![1, 2, 3, 4].into()]),
                 *relation.stable.borrow()
             );
-            assert_eq!(vec![5], relation.recent.borrow().items);
+            assert_eq!(vec![5], relation.recent.borrow().items());
             assert_eq!(Vec::<Tuples<i32>>::new(), *relation.to_add.borrow());
         }
+        {
+            let relation = Instance::<i32> {
+                stable: shared(vec![vec![1, 2].into()]),
+                recent: shared(vec![3, 4].into()),
+                to_add: shared(vec![]),
+                removed: shared(vec![].into()),
+                to_remove: shared(vec![vec![2, 4, 6].into()]),
+            };
+            assert!(relation.changed());
+            assert_eq!(vec![Tuples::from(vec![1, 3])], *relation.stable.borrow());
+            assert_eq!(Vec::<i32>::new(), relation.recent.borrow().items());
+            assert_eq!(vec![2, 4], relation.removed.borrow().items());
+            assert_eq!(Vec::<Tuples<i32>>::new(), *relation.to_remove.borrow());
+
+            assert!(!relation.changed());
+            assert_eq!(Vec::<i32>::new(), relation.removed.borrow().items());
+        }
+    }
+
+    #[test]
+    fn test_instance_insert_remove() {
+        {
+            let relation = Instance::<i32>::new();
+            relation.insert(vec![1, 2, 3].into());
+            relation.remove(vec![2, 4].into());
+            assert_eq!(vec![Tuples::from(vec![1, 3])], *relation.to_add.borrow());
+            assert_eq!(vec![Tuples::from(vec![2, 4])], *relation.to_remove.borrow());
+        }
+        {
+            let relation = Instance::<i32>::new();
+            relation.remove(vec![2, 4].into());
+            relation.insert(vec![1, 2].into());
+            assert_eq!(vec![Tuples::from(vec![1, 2])], *relation.to_add.borrow());
+            assert_eq!(vec![Tuples::from(vec![4])], *relation.to_remove.borrow());
+
+            assert!(relation.changed());
+            assert_eq!(vec![1, 2], relation.recent.borrow().items());
+            assert_eq!(Vec::<i32>::new(), relation.removed.borrow().items());
+        }
     }
 }
@@ -0,0 +1,416 @@
+/*! Implements exporting databases to and importing them from JSON and bincode when the
+`serde` feature is enabled.
+*/
+#[cfg(feature = "storage")]
+use super::wal::Operation;
+use super::{
+    instance::{DynInstance, Instance},
+    Database, RelationEntry,
+};
+use crate::{expression::Relation, Error, MaybeSync, Tuple, Tuples};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    any::Any,
+    collections::{BTreeMap, HashMap},
+    io::{Read, Write},
+    marker::PhantomData,
+    sync::Arc,
+};
+
+/// Is used to read and write the instances of relations whose tuple types are hidden by
+/// `DynInstance`.
+pub(super) trait RelationCodec: MaybeSync {
+    /// Returns a new empty instance.
+    #[cfg(feature = "storage")]
+    fn empty(&self) -> Box<dyn DynInstance>;
+
+    /// Reads an instance from `reader`, containing its tuples in bincode.
+    fn load(&self, reader: &mut dyn Read) -> bincode::Result<Box<dyn DynInstance>>;
+
+    /// Reads an instance from `value`, containing its tuples in JSON.
+    fn load_json(&self, value: serde_json::Value) -> serde_json::Result<Box<dyn DynInstance>>;
+
+    /// Writes the current tuples of `instance` to `writer` in bincode.
+    fn save(&self, instance: &dyn DynInstance, writer: &mut dyn Write) -> bincode::Result<()>;
+
+    /// Returns the current tuples of `instance` in JSON.
+    fn save_json(&self, instance: &dyn DynInstance) -> serde_json::Result<serde_json::Value>;
+
+    /// Writes the batch of tuples in `tuples` (of type `Tuples`) to `writer` in bincode.
+    #[cfg(feature = "storage")]
+    fn encode(&self, tuples: &dyn Any, writer: &mut dyn Write) -> bincode::Result<()>;
+
+    /// Reads a batch of tuples from `bytes` and returns it as `Tuples`.
+    #[cfg(feature = "storage")]
+    fn decode(&self, bytes: &[u8]) -> bincode::Result<Box<dyn Any>>;
+
+    /// Applies `operation` on `instance` with `tuples`, a batch that is read by `decode`.
+    #[cfg(feature = "storage")]
+    fn replay(&self, instance: &dyn DynInstance, operation: Operation, tuples: Box<dyn Any>);
+}
+
+/// Implements `RelationCodec` for relations with tuples of type `T`.
+struct Codec<T>(PhantomData<T>);
+
+impl<T> Codec<T>
+where
+    T: Tuple + 'static,
+{
+    /// Returns `value` as a value of type `V`, or an error if its type is not `V`.
+    fn downcast<V: 'static, E: serde::ser::Error>(value: &dyn Any) -> Result<&V, E> {
+        value.downcast_ref::<V>().ok_or_else(|| {
+            E::custom(format!(
+                "expecting tuples of type `{}`",
+                std::any::type_name::<T>()
+            ))
+        })
+    }
+}
+
+impl<T> RelationCodec for Codec<T>
+where
+    T: Tuple + Serialize + DeserializeOwned + 'static,
+{
+    #[cfg(feature = "storage")]
+    fn empty(&self) -> Box<dyn DynInstance> {
+        Box::new(Instance::<T>::new())
+    }
+
+    fn load(&self, reader: &mut dyn Read) -> bincode::Result<Box<dyn DynInstance>> {
+        let tuples: Vec<T> = bincode::deserialize_from(reader)?;
+        let instance = Instance::<T>::new();
+        instance.insert(tuples.into());
+        Ok(Box::new(instance))
+    }
+
+    fn load_json(&self, value: serde_json::Value) -> serde_json::Result<Box<dyn DynInstance>> {
+        let tuples: Vec<T> = serde_json::from_value(value)?;
+        let instance = Instance::<T>::new();
+        instance.insert(tuples.into());
+        Ok(Box::new(instance))
+    }
+
+    fn save(&self, instance: &dyn DynInstance, writer: &mut dyn Write) -> bincode::Result<()> {
+        let instance = Self::downcast::<Instance<T>, bincode::Error>(instance.as_any())?;
+        bincode::serialize_into(writer, instance.current().items())
+    }
+
+    fn save_json(&self, instance: &dyn DynInstance) -> serde_json::Result<serde_json::Value> {
+        let instance = Self::downcast::<Instance<T>, serde_json::Error>(instance.as_any())?;
+        serde_json::to_value(instance.current().items())
+    }
+
+    #[cfg(feature = "storage")]
+    fn encode(&self, tuples: &dyn Any, writer: &mut dyn Write) -> bincode::Result<()> {
+        let tuples = Self::downcast::<Tuples<T>, bincode::Error>(tuples)?;
+        bincode::serialize_into(writer, tuples.items())
+    }
+
+    #[cfg(feature = "storage")]
+    fn decode(&self, bytes: &[u8]) -> bincode::Result<Box<dyn Any>> {
+        let tuples: Vec<T> = bincode::deserialize(bytes)?;
+        Ok(Box::new(Tuples::from(tuples)))
+    }
+
+    #[cfg(feature = "storage")]
+    fn replay(&self, instance: &dyn DynInstance, operation: Operation, tuples: Box<dyn Any>) {
+        let tuples = *tuples
+            .downcast::<Tuples<T>>()
+            .expect("the tuples must be read by the codec");
+        let instance = instance
+            .as_any()
+            .downcast_ref::<Instance<T>>()
+            .expect("the type of the instance must match the schema");
+        match operation {
+            Operation::Insert => instance.insert(tuples),
+            Operation::Remove => instance.remove(tuples),
+        }
+    }
+}
+
+/// Describes the relations of a database that is exported by [`Database::to_json`] or
+/// [`Database::to_bincode`] and imported by [`Database::from_json`] or
+/// [`Database::from_bincode`] (or saved to and loaded from disk with the `storage`
+/// feature). Because the instances of a database are serialized without their types, the
+/// schema provides the tuple type of every relation.
+///
+/// [`Database::to_json`]: ./struct.Database.html#method.to_json
+/// [`Database::to_bincode`]: ./struct.Database.html#method.to_bincode
+/// [`Database::from_json`]: ./struct.Database.html#method.from_json
+/// [`Database::from_bincode`]: ./struct.Database.html#method.from_bincode
+#[derive(Clone, Default)]
+pub struct Schema {
+    relations: HashMap<String, Arc<dyn RelationCodec>>,
+}
+
+impl Schema {
+    /// Creates a new empty schema.
+    pub fn new() -> Self {
+        Self {
+            relations: HashMap::new(),
+        }
+    }
+
+    /// Adds a relation identified by `name` with tuples of type `T` to the schema and
+    /// returns a [`Relation`] object that can be used to access the relation instance in
+    /// the loaded database.
+    ///
+    /// [`Relation`]: ./expression/struct.Relation.html
+    pub fn add_relation<T>(&mut self, name: &str) -> Result<Relation<T>, Error>
+    where
+        T: Tuple + Serialize + DeserializeOwned + 'static,
+    {
+        if !self.relations.contains_key(name) {
+            self.relations
+                .insert(name.into(), Arc::new(Codec::<T>(PhantomData)));
+            Ok(Relation::new(name))
+        } else {
+            Err(Error::InstanceExists { name: name.into() })
+        }
+    }
+
+    /// Returns the codec of relation `name` if it exists.
+    pub(super) fn codec(&self, name: &str) -> Option<&dyn RelationCodec> {
+        self.relations.get(name).map(|c| c.as_ref())
+    }
+
+    /// Returns an iterator over the names of the relations in the schema and their codecs.
+    pub(super) fn codecs(&self) -> impl Iterator<Item = (&String, &dyn RelationCodec)> {
+        self.relations.iter().map(|(name, c)| (name, c.as_ref()))
+    }
+}
+
+/// Creates an `Error::Serialization` from `error`.
+fn serialization_error(error: impl std::fmt::Display) -> Error {
+    Error::Serialization {
+        message: error.to_string(),
+    }
+}
+
+/// Returns the instance of relation `name` in `instances`, read by `load`, or an error if
+/// the relation doesn't exist in `schema`.
+fn import<V>(
+    instances: impl IntoIterator<Item = (String, V)>,
+    schema: &Schema,
+    load: impl Fn(&dyn RelationCodec, V) -> Result<Box<dyn DynInstance>, Error>,
+) -> Result<Database, Error> {
+    let mut database = Database::new();
+    for (name, value) in instances {
+        let codec = schema
+            .codec(&name)
+            .ok_or_else(|| Error::InstanceNotFound { name: name.clone() })?;
+        let instance = load(codec, value)?;
+        database
+            .relations
+            .insert(name, RelationEntry::with_instance(instance));
+    }
+    if let Some((name, _)) = schema
+        .codecs()
+        .find(|(name, _)| !database.relations.contains_key(*name))
+    {
+        return Err(Error::InstanceNotFound { name: name.clone() });
+    }
+    Ok(database)
+}
+
+/// Is a relation of a schema, paired with its name and its codec, and its instance in a
+/// database.
+pub(super) type SchemaInstance<'a> = (&'a String, &'a dyn RelationCodec, &'a dyn DynInstance);
+
+impl Database {
+    /// Returns the instances of the relations of `schema` in the database, paired with
+    /// their names and codecs, or an error if a relation of `schema` doesn't exist in the
+    /// database.
+    pub(super) fn schema_instances<'a>(
+        &'a self,
+        schema: &'a Schema,
+    ) -> Result<Vec<SchemaInstance<'a>>, Error> {
+        schema
+            .codecs()
+            .map(|(name, codec)| {
+                self.relations
+                    .get(name)
+                    .map(|entry| (name, codec, entry.instance.as_ref()))
+                    .ok_or_else(|| Error::InstanceNotFound { name: name.clone() })
+            })
+            .collect()
+    }
+
+    /// Exports the instances of the relations of `schema` in the database to a JSON
+    /// object that maps the name of every relation to the array of its tuples.
+    ///
+    /// **Note**: views are not exported; views that are stored in an imported database
+    /// are computed from its relations.
+    ///
+    /// **Example**:
+    /// ```rust
+    /// use codd::{Database, Schema};
+    ///
+    /// let mut schema = Schema::new();
+    /// let numbers = schema.add_relation::<u32>("numbers").unwrap();
+    ///
+    /// let mut db = Database::new();
+    /// db.add_relation::<u32>("numbers").unwrap();
+    /// db.insert(&numbers, vec![4, 8, 15].into()).unwrap();
+    ///
+    /// let json = db.to_json(&schema).unwrap();
+    /// assert_eq!(r#"{"numbers":[4,8,15]}"#, json);
+    ///
+    /// let imported = Database::from_json(&json, &schema).unwrap();
+    /// assert_eq!(vec![4, 8, 15], imported.evaluate(&numbers).unwrap().into_tuples());
+    /// ```
+    pub fn to_json(&self, schema: &Schema) -> Result<String, Error> {
+        let _guard = self.lock();
+        let mut object = serde_json::Map::new();
+        for (name, codec, instance) in self.schema_instances(schema)? {
+            let value = codec.save_json(instance).map_err(serialization_error)?;
+            object.insert(name.clone(), value);
+        }
+        serde_json::to_string(&object).map_err(serialization_error)
+    }
+
+    /// Imports a database with the relations of `schema` from `json`, exported by
+    /// [`Database::to_json`].
+    ///
+    /// [`Database::to_json`]: ./struct.Database.html#method.to_json
+    pub fn from_json(json: &str, schema: &Schema) -> Result<Self, Error> {
+        let object: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(json).map_err(serialization_error)?;
+        import(object, schema, |codec, value| {
+            codec.load_json(value).map_err(serialization_error)
+        })
+    }
+
+    /// Exports the instances of the relations of `schema` in the database to bincode, as
+    /// a map from the name of every relation to its tuples.
+    ///
+    /// **Note**: views are not exported; views that are stored in an imported database
+    /// are computed from its relations.
+    pub fn to_bincode(&self, schema: &Schema) -> Result<Vec<u8>, Error> {
+        let _guard = self.lock();
+        let mut map = BTreeMap::new();
+        for (name, codec, instance) in self.schema_instances(schema)? {
+            let mut bytes = Vec::new();
+            codec
+                .save(instance, &mut bytes)
+                .map_err(serialization_error)?;
+            map.insert(name.clone(), bytes);
+        }
+        bincode::serialize(&map).map_err(serialization_error)
+    }
+
+    /// Imports a database with the relations of `schema` from `bytes`, exported by
+    /// [`Database::to_bincode`].
+    ///
+    /// [`Database::to_bincode`]: ./struct.Database.html#method.to_bincode
+    pub fn from_bincode(bytes: &[u8], schema: &Schema) -> Result<Self, Error> {
+        let map: BTreeMap<String, Vec<u8>> =
+            bincode::deserialize(bytes).map_err(serialization_error)?;
+        import(map, schema, |codec, bytes| {
+            codec.load(&mut &bytes[..]).map_err(serialization_error)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{expression::Select, Tuples};
+
+    #[test]
+    fn test_serialize_tuples() {
+        let tuples = Tuples::from(vec![(2, "b".to_string()), (1, "a".to_string())]);
+        let json = serde_json::to_string(&tuples).unwrap();
+        assert_eq!(r#"[[1,"a"],[2,"b"]]"#, json);
+
+        let tuples: Tuples<i32> = serde_json::from_str("[3, 1, 2, 1]").unwrap();
+        assert_eq!(vec![1, 2, 3], tuples.into_tuples());
+
+        let tuples = Tuples::from(vec![5, 4]);
+        let bytes = bincode::serialize(&tuples).unwrap();
+        assert_eq!(tuples, bincode::deserialize(&bytes).unwrap());
+    }
+
+    #[test]
+    fn test_export_import() {
+        let mut database = Database::new();
+        let r = database.add_relation::<(i32, String)>("r").unwrap();
+        database.add_relation::<i32>("s").unwrap();
+        database
+            .insert(&r, vec![(1, "a".to_string()), (2, "b".to_string())].into())
+            .unwrap();
+        database.evaluate(&r).unwrap();
+        database
+            .remove(&r, vec![(1, "a".to_string())].into())
+            .unwrap();
+
+        let mut schema = Schema::new();
+        let r = schema.add_relation::<(i32, String)>("r").unwrap();
+        let s = schema.add_relation::<i32>("s").unwrap();
+        {
+            let json = database.to_json(&schema).unwrap();
+            assert_eq!(r#"{"r":[[2,"b"]],"s":[]}"#, json);
+
+            let mut imported = Database::from_json(&json, &schema).unwrap();
+            assert_eq!(
+                vec![(2, "b".to_string())],
+                imported.evaluate(&r).unwrap().into_tuples()
+            );
+            assert!(imported.evaluate(&s).unwrap().is_empty());
+
+            let view = imported
+                .store_view(Select::new(r.clone(), |t| t.0 > 1))
+                .unwrap();
+            assert_eq!(1, imported.evaluate(&view).unwrap().len());
+        }
+        {
+            let bytes = database.to_bincode(&schema).unwrap();
+            let imported = Database::from_bincode(&bytes, &schema).unwrap();
+            assert_eq!(
+                vec![(2, "b".to_string())],
+                imported.evaluate(&r).unwrap().into_tuples()
+            );
+            assert!(imported.evaluate(&s).unwrap().is_empty());
+        }
+        {
+            let json = database.to_json(&schema).unwrap();
+            let mut schema = Schema::new();
+            schema.add_relation::<(i32, String)>("r").unwrap();
+            assert!(matches!(
+                Database::from_json(&json, &schema),
+                Err(Error::InstanceNotFound { .. })
+            ));
+
+            schema.add_relation::<i32>("s").unwrap();
+            schema.add_relation::<i32>("t").unwrap();
+            assert!(matches!(
+                Database::from_json(&json, &schema),
+                Err(Error::InstanceNotFound { .. })
+            ));
+        }
+        {
+            let json = database.to_json(&schema).unwrap();
+            let mut schema = Schema::new();
+            schema.add_relation::<String>("r").unwrap();
+            schema.add_relation::<i32>("s").unwrap();
+            assert!(matches!(
+                Database::from_json(&json, &schema),
+                Err(Error::Serialization { .. })
+            ));
+            assert!(matches!(
+                database.to_json(&schema),
+                Err(Error::Serialization { .. })
+            ));
+            assert!(matches!(
+                database.to_bincode(&schema),
+                Err(Error::Serialization { .. })
+            ));
+
+            schema.add_relation::<i32>("t").unwrap();
+            assert!(matches!(
+                database.to_json(&schema),
+                Err(Error::InstanceNotFound { .. })
+            ));
+        }
+    }
+}
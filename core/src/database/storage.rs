@@ -0,0 +1,308 @@
+/*! Implements saving the relation instances of a database to disk and loading them back
+when the `storage` feature is enabled.
+*/
+use super::{
+    instance::DynInstance,
+    serialize::{RelationCodec, Schema},
+    wal::Log,
+    Database, RelationEntry,
+};
+use crate::Error;
+use std::{
+    fs,
+    io::{self, BufReader, BufWriter, ErrorKind},
+    path::{Component, Path, PathBuf},
+};
+
+/// Is the extension of the files storing relation instances.
+const EXTENSION: &str = "bin";
+
+/// Returns the path to the file storing the instance of relation `name` in `dir`, or an
+/// error if `name` is not a plain file name (e.g., it contains a path separator or is
+/// `..`), which would place the file outside of `dir`.
+fn relation_file(dir: &Path, name: &str) -> Result<PathBuf, Error> {
+    let mut components = Path::new(name).components();
+    let plain = matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    ) && !name.contains(&['/', '\\'][..]);
+    if !plain {
+        return Err(storage_error(
+            dir,
+            io::Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid relation file name `{}`", name),
+            ),
+        ));
+    }
+    Ok(dir.join(format!("{}.{}", name, EXTENSION)))
+}
+
+/// Reads the instance of relation `name` from `dir` by `codec`. Returns `None` if the
+/// file of the instance doesn't exist.
+fn read_instance(
+    dir: &Path,
+    name: &str,
+    codec: &dyn RelationCodec,
+) -> Result<Option<Box<dyn DynInstance>>, Error> {
+    let file = relation_file(dir, name)?;
+    let mut reader = match fs::File::open(&file) {
+        Ok(f) => BufReader::new(f),
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(storage_error(&file, e)),
+    };
+    let instance = codec
+        .load(&mut reader)
+        .map_err(|e| storage_error(&file, io_error(*e)))?;
+    Ok(Some(instance))
+}
+
+/// Creates an `Error::Storage` for accessing `path`, caused by `source`.
+pub(super) fn storage_error(path: &Path, source: impl Into<io::Error>) -> Error {
+    Error::Storage {
+        path: path.into(),
+        source: source.into(),
+    }
+}
+
+/// Returns the I/O error that causes `error` if it exists; otherwise, returns an I/O error
+/// for reading or writing invalid data, caused by `error`.
+pub(super) fn io_error(error: bincode::ErrorKind) -> io::Error {
+    match error {
+        bincode::ErrorKind::Io(e) => e,
+        e => io::Error::new(ErrorKind::InvalidData, e),
+    }
+}
+
+impl Database {
+    /// Saves the instances of the relations of `schema` in the database to the directory
+    /// `path`, creating the directory if it doesn't exist. Every relation is written to
+    /// its own file, named after the relation; the file is replaced only after it is
+    /// entirely written.
+    /// If the database is opened from `path` by [`Database::open`], its write-ahead log
+    /// is truncated after all relations are saved.
+    ///
+    /// **Note**: views are not saved because their expressions cannot be serialized;
+    /// views that are stored again in a loaded database are computed from its relations.
+    ///
+    /// [`Database::open`]: ./struct.Database.html#method.open
+    pub fn save<P: AsRef<Path>>(&self, path: P, schema: &Schema) -> Result<(), Error> {
+        let _guard = self.lock();
+        let dir = path.as_ref();
+        let instances = self.schema_instances(schema)?;
+        fs::create_dir_all(dir).map_err(|e| storage_error(dir, e))?;
+
+        for (name, codec, instance) in instances {
+            let file = relation_file(dir, name)?;
+            let temp = file.with_extension(format!("{}.tmp", EXTENSION));
+            {
+                let mut writer =
+                    BufWriter::new(fs::File::create(&temp).map_err(|e| storage_error(&temp, e))?);
+                codec
+                    .save(instance, &mut writer)
+                    .map_err(|e| storage_error(&temp, io_error(*e)))?;
+                writer
+                    .into_inner()
+                    .map_err(|e| storage_error(&temp, e))?
+                    .sync_all()
+                    .map_err(|e| storage_error(&temp, e))?;
+            }
+            fs::rename(&temp, &file).map_err(|e| storage_error(&file, e))?;
+        }
+
+        if let Some(log) = &self.log {
+            let dir = dir.canonicalize().map_err(|e| storage_error(dir, e))?;
+            if log.dir() == dir {
+                log.truncate()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Loads a database with the relations of `schema` from the directory `path`, where
+    /// the database had been saved by [`Database::save`].
+    ///
+    /// **Example**:
+    /// ```rust
+    /// use codd::{Database, Schema};
+    ///
+    /// let dir = std::env::temp_dir().join("codd-load-example");
+    ///
+    /// let mut schema = Schema::new();
+    /// let numbers = schema.add_relation::<u32>("numbers").unwrap();
+    ///
+    /// let mut db = Database::new();
+    /// db.add_relation::<u32>("numbers").unwrap();
+    /// db.insert(&numbers, vec![4, 8, 15, 16, 23, 42].into()).unwrap();
+    /// db.save(&dir, &schema).unwrap();
+    ///
+    /// let loaded = Database::load(&dir, &schema).unwrap();
+    ///
+    /// assert_eq!(
+    ///     vec![4, 8, 15, 16, 23, 42],
+    ///     loaded.evaluate(&numbers).unwrap().into_tuples()
+    /// );
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    ///
+    /// [`Database::save`]: ./struct.Database.html#method.save
+    pub fn load<P: AsRef<Path>>(path: P, schema: &Schema) -> Result<Self, Error> {
+        let dir = path.as_ref();
+        let mut database = Database::new();
+
+        for (name, codec) in schema.codecs() {
+            let instance = match read_instance(dir, name, codec)? {
+                Some(instance) => instance,
+                None => {
+                    return Err(storage_error(
+                        &relation_file(dir, name)?,
+                        io::Error::new(ErrorKind::NotFound, "relation file not found"),
+                    ))
+                }
+            };
+            database
+                .relations
+                .insert(name.clone(), RelationEntry::with_instance(instance));
+        }
+        Ok(database)
+    }
+
+    /// Opens a database with the relations of `schema` in the directory `path`, creating
+    /// the directory if it doesn't exist. The database is loaded from the instances that
+    /// are last saved to `path` by [`Database::save`] (relations without a saved instance
+    /// start empty), then the insertions and removals in the write-ahead log of `path`
+    /// are replayed. Every following insertion and removal is appended to the log before
+    /// it is applied, so the tuples of an acknowledged insertion survive a crash.
+    ///
+    /// **Example**:
+    /// ```rust
+    /// use codd::{Database, Schema};
+    ///
+    /// let dir = std::env::temp_dir().join("codd-open-example");
+    /// let mut schema = Schema::new();
+    /// let numbers = schema.add_relation::<u32>("numbers").unwrap();
+    ///
+    /// let db = Database::open(&dir, &schema).unwrap();
+    /// db.insert(&numbers, vec![4, 8, 15].into()).unwrap();
+    /// drop(db); // no snapshot is saved
+    ///
+    /// let db = Database::open(&dir, &schema).unwrap();
+    /// assert_eq!(vec![4, 8, 15], db.evaluate(&numbers).unwrap().into_tuples());
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    ///
+    /// [`Database::save`]: ./struct.Database.html#method.save
+    pub fn open<P: AsRef<Path>>(path: P, schema: &Schema) -> Result<Self, Error> {
+        let dir = path.as_ref();
+        fs::create_dir_all(dir).map_err(|e| storage_error(dir, e))?;
+        let mut database = Database::new();
+
+        for (name, codec) in schema.codecs() {
+            let instance = read_instance(dir, name, codec)?.unwrap_or_else(|| codec.empty());
+            database
+                .relations
+                .insert(name.clone(), RelationEntry::with_instance(instance));
+        }
+        database.log = Some(Log::open(dir, &database, schema)?);
+        Ok(database)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::expression::Select;
+
+    /// Returns a fresh temporary directory for the test `name`.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("codd-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_save_load() {
+        {
+            let dir = temp_dir("save-load");
+            let mut database = Database::new();
+            let r = database.add_relation::<(i32, String)>("r").unwrap();
+            database.add_relation::<i32>("s").unwrap();
+            database
+                .insert(&r, vec![(1, "a".to_string()), (2, "b".to_string())].into())
+                .unwrap();
+            database.evaluate(&r).unwrap();
+            database
+                .insert(&r, vec![(3, "c".to_string())].into())
+                .unwrap();
+            database
+                .remove(&r, vec![(1, "a".to_string())].into())
+                .unwrap();
+
+            let mut schema = Schema::new();
+            let r = schema.add_relation::<(i32, String)>("r").unwrap();
+            let s = schema.add_relation::<i32>("s").unwrap();
+            assert!(schema.add_relation::<i32>("s").is_err());
+            database.save(&dir, &schema).unwrap();
+
+            let mut loaded = Database::load(&dir, &schema).unwrap();
+            assert_eq!(
+                vec![(2, "b".to_string()), (3, "c".to_string())],
+                loaded.evaluate(&r).unwrap().into_tuples()
+            );
+            assert!(loaded.evaluate(&s).unwrap().is_empty());
+
+            let view = loaded
+                .store_view(Select::new(r.clone(), |t| t.0 > 2))
+                .unwrap();
+            assert_eq!(
+                vec![(3, "c".to_string())],
+                loaded.evaluate(&view).unwrap().into_tuples()
+            );
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        {
+            let dir = temp_dir("load-missing");
+            let mut schema = Schema::new();
+            schema.add_relation::<i32>("r").unwrap();
+            assert!(matches!(
+                Database::load(&dir, &schema),
+                Err(Error::Storage { source, .. }) if source.kind() == ErrorKind::NotFound
+            ));
+        }
+        {
+            let dir = temp_dir("save-escape");
+            for name in &["../escape", "a/b", "a\\b", "..", ".", ""] {
+                let mut database = Database::new();
+                let r = database.add_relation::<i32>(name).unwrap();
+                database.insert(&r, vec![1].into()).unwrap();
+                let mut schema = Schema::new();
+                schema.add_relation::<i32>(name).unwrap();
+
+                assert!(matches!(
+                    database.save(&dir, &schema),
+                    Err(Error::Storage { source, .. }) if source.kind() == ErrorKind::InvalidInput
+                ));
+                assert!(matches!(
+                    Database::open(&dir, &schema),
+                    Err(Error::Storage { source, .. }) if source.kind() == ErrorKind::InvalidInput
+                ));
+            }
+            assert!(!dir.with_file_name("escape.bin").exists());
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        {
+            let dir = temp_dir("load-mismatch");
+            let mut database = Database::new();
+            let r = database.add_relation::<u8>("r").unwrap();
+            database.insert(&r, vec![1].into()).unwrap();
+            let mut schema = Schema::new();
+            schema.add_relation::<u8>("r").unwrap();
+            database.save(&dir, &schema).unwrap();
+
+            let mut schema = Schema::new();
+            schema.add_relation::<(u64, String)>("r").unwrap();
+            assert!(Database::load(&dir, &schema).is_err());
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use super::instance::{DynInstance, Instance, Tuples};
+use crate::{
+    expression::{Closure, UnaryFn},
+    MaybeSync, Tuple,
+};
+
+/// Is used to store the subscribers of a view by hiding their (generic) types.
+pub(super) trait DynSubscriber: MaybeSync {
+    /// Returns a notification that delivers the `recent` tuples of `instance`, the instance
+    /// of the view, to the subscriber if they are not empty.
+    fn notification(&self, instance: &dyn DynInstance) -> Option<Box<dyn DynNotification>>;
+}
+
+/// Is used to queue the notifications of subscribers by hiding their (generic) types.
+pub(super) trait DynNotification: MaybeSync {
+    /// Passes the tuples of the notification to the callback of its subscriber.
+    fn deliver(&self);
+}
+
+/// Is a subscriber to the changes of a view, whose callback receives the batches of
+/// tuples that are added to the view.
+pub(super) struct Subscriber<T: Tuple> {
+    callback: Closure<dyn UnaryFn<Tuples<T>, ()>>,
+}
+
+impl<T: Tuple> Subscriber<T> {
+    pub fn new(callback: impl UnaryFn<Tuples<T>, ()> + 'static) -> Self {
+        Self {
+            callback: Closure::unary(callback),
+        }
+    }
+}
+
+impl<T> DynSubscriber for Subscriber<T>
+where
+    T: Tuple + 'static,
+{
+    fn notification(&self, instance: &dyn DynInstance) -> Option<Box<dyn DynNotification>> {
+        let instance = instance
+            .as_any()
+            .downcast_ref::<Instance<T>>()
+            .expect("instance of the view");
+        // the batch is shared with the instance:
+        let recent = instance.recent().clone();
+        if recent.is_empty() {
+            return None;
+        }
+        Some(Box::new(Notification {
+            callback: self.callback.clone(),
+            tuples: recent,
+        }))
+    }
+}
+
+/// Is a batch of tuples that is added to a view, waiting to be delivered to a subscriber
+/// of the view.
+struct Notification<T: Tuple> {
+    callback: Closure<dyn UnaryFn<Tuples<T>, ()>>,
+    tuples: Tuples<T>,
+}
+
+impl<T> DynNotification for Notification<T>
+where
+    T: Tuple + 'static,
+{
+    fn deliver(&self) {
+        (self.callback.get())(&self.tuples);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Database, Expression, Tuples};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_subscribe() {
+        let mut database = Database::new();
+        let r = database.add_relation::<i32>("r").unwrap();
+        let s = database.add_relation::<i32>("s").unwrap();
+        let view = database
+            .store_view(r.builder().union(s.clone()).build())
+            .unwrap();
+        let doubled = database
+            .store_view(view.builder().project(|t| t * 2).build())
+            .unwrap();
+
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let sink = batches.clone();
+        database
+            .subscribe(&doubled, move |batch: &Tuples<i32>| {
+                sink.lock().unwrap().push(batch.clone().into_tuples())
+            })
+            .unwrap();
+
+        // the view is stabilized once with the changes to both relations:
+        database.insert(&r, vec![1, 2].into()).unwrap();
+        database.insert(&s, vec![2, 3].into()).unwrap();
+        database.evaluate(&r).unwrap();
+        database.evaluate(&doubled).unwrap();
+        assert_eq!(vec![vec![2, 4, 6]], *batches.lock().unwrap());
+
+        // removals and existing tuples are not delivered:
+        database.remove(&r, vec![1].into()).unwrap();
+        database.insert(&s, vec![2].into()).unwrap();
+        database.evaluate(&doubled).unwrap();
+        assert_eq!(1, batches.lock().unwrap().len());
+
+        database.insert(&r, vec![4].into()).unwrap();
+        database.evaluate(&r).unwrap();
+        let mut transaction = database.transaction();
+        transaction.insert(&r, vec![5].into()).unwrap();
+        transaction.insert(&s, vec![5, 6].into()).unwrap();
+        transaction.commit().unwrap();
+        assert_eq!(
+            vec![vec![2, 4, 6], vec![8], vec![10, 12]],
+            *batches.lock().unwrap()
+        );
+
+        let cloned = database.clone();
+        cloned.insert(&r, vec![7].into()).unwrap();
+        assert!(cloned.evaluate(&doubled).is_ok());
+        assert_eq!(3, batches.lock().unwrap().len());
+    }
+
+    #[test]
+    // the database is shared with the callback by `Arc` so that the test builds with the
+    // `sync` feature as well:
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn test_subscribe_evaluate() {
+        let mut database = Database::new();
+        let r = database.add_relation::<i32>("r").unwrap();
+        let s = database.add_relation::<i32>("s").unwrap();
+        let doubled = database
+            .store_view(r.builder().project(|t| t * 2).build())
+            .unwrap();
+        let negated = database
+            .store_view(s.builder().project(|t| -t).build())
+            .unwrap();
+
+        let handle: Arc<Mutex<Option<Arc<Database>>>> = Arc::new(Mutex::new(None));
+        let events = Arc::new(Mutex::new(Vec::new()));
+        {
+            let handle = handle.clone();
+            let sink = events.clone();
+            let negated = negated.clone();
+            database
+                .subscribe(&doubled, move |batch: &Tuples<i32>| {
+                    let database = handle.lock().unwrap().clone().unwrap();
+                    // the callback is called after the database is stabilized and unlocked:
+                    let tuples = database.evaluate(&negated).unwrap();
+                    sink.lock()
+                        .unwrap()
+                        .push(("doubled", batch.clone().into_tuples()));
+                    sink.lock()
+                        .unwrap()
+                        .push(("evaluated", tuples.into_tuples()));
+                })
+                .unwrap();
+        }
+        {
+            let sink = events.clone();
+            database
+                .subscribe(&negated, move |batch: &Tuples<i32>| {
+                    sink.lock()
+                        .unwrap()
+                        .push(("negated", batch.clone().into_tuples()))
+                })
+                .unwrap();
+        }
+
+        let database = Arc::new(database);
+        *handle.lock().unwrap() = Some(database.clone());
+        database.insert(&r, vec![1, 2].into()).unwrap();
+        database.insert(&s, vec![3].into()).unwrap();
+        database.evaluate(&r).unwrap();
+
+        // the notifications of the evaluation in the callback are delivered after it:
+        assert_eq!(
+            vec![
+                ("doubled", vec![2, 4]),
+                ("evaluated", vec![-3]),
+                ("negated", vec![-3])
+            ],
+            *events.lock().unwrap()
+        );
+        *handle.lock().unwrap() = None;
+    }
+
+    #[test]
+    fn test_subscribe_panic() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let mut database = Database::new();
+        let r = database.add_relation::<i32>("r").unwrap();
+        let view = database.store_view(r.clone()).unwrap();
+
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let sink = batches.clone();
+        database
+            .subscribe(&view, move |batch: &Tuples<i32>| {
+                if batch.contains(&0) {
+                    panic!("callback");
+                }
+                sink.lock().unwrap().push(batch.clone().into_tuples())
+            })
+            .unwrap();
+
+        database.insert(&r, vec![0, 1].into()).unwrap();
+        assert!(catch_unwind(AssertUnwindSafe(|| database.evaluate(&view))).is_err());
+
+        // the notifications after the panic are delivered:
+        database.insert(&r, vec![2].into()).unwrap();
+        database.evaluate(&view).unwrap();
+        database.insert(&r, vec![3].into()).unwrap();
+        database.evaluate(&view).unwrap();
+        assert_eq!(vec![vec![2], vec![3]], *batches.lock().unwrap());
+    }
+}
@@ -0,0 +1,349 @@
+#[cfg(feature = "storage")]
+use super::wal;
+use super::{constraint::Pending, Database, Tuples};
+use crate::{expression::Relation, Error, Tuple};
+use std::any::Any;
+
+/// Is used to buffer the changes of a transaction to relations by hiding their (generic)
+/// types.
+trait DynChanges {
+    /// Returns the changes as `Any`.
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+
+    /// Returns the name of the relation of the changes.
+    fn relation(&self) -> &str;
+
+    /// Returns the tuples that are inserted into the relation as `Any`.
+    fn inserted(&self) -> &dyn Any;
+
+    /// Returns the tuples that are removed from the relation as `Any`.
+    fn removed(&self) -> &dyn Any;
+
+    /// Returns an error if the changes violate the schema or the key of the relation in
+    /// `db`.
+    fn check(&self, db: &Database) -> Result<(), Error>;
+
+    /// Returns the entries of the changes in a record of the write-ahead log.
+    #[cfg(feature = "storage")]
+    fn entries(&self) -> Vec<wal::Entry<'_>>;
+
+    /// Applies the changes on the relation in `db`.
+    fn apply(self: Box<Self>, db: &Database) -> Result<(), Error>;
+}
+
+/// Contains the tuples that a transaction inserts into and removes from a relation.
+struct Changes<T: Tuple> {
+    relation: Relation<T>,
+    inserted: Tuples<T>,
+    removed: Tuples<T>,
+}
+
+impl<T: Tuple> Changes<T> {
+    fn new(relation: &Relation<T>) -> Self {
+        Self {
+            relation: relation.clone(),
+            inserted: Vec::new().into(),
+            removed: Vec::new().into(),
+        }
+    }
+
+    /// Buffers `tuples` to be inserted, cancelling the buffered removals of the same tuples.
+    fn insert(&mut self, tuples: Tuples<T>) {
+        self.removed.retain(|t| tuples.binary_search(t).is_err());
+        let inserted = std::mem::replace(&mut self.inserted, Vec::new().into());
+        self.inserted = inserted.merge(tuples);
+    }
+
+    /// Buffers `tuples` to be removed, cancelling the buffered insertions of the same tuples.
+    fn remove(&mut self, tuples: Tuples<T>) {
+        self.inserted.retain(|t| tuples.binary_search(t).is_err());
+        let removed = std::mem::replace(&mut self.removed, Vec::new().into());
+        self.removed = removed.merge(tuples);
+    }
+}
+
+impl<T: Tuple + 'static> DynChanges for Changes<T> {
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn relation(&self) -> &str {
+        self.relation.name()
+    }
+
+    fn inserted(&self) -> &dyn Any {
+        &self.inserted
+    }
+
+    fn removed(&self) -> &dyn Any {
+        &self.removed
+    }
+
+    fn check(&self, db: &Database) -> Result<(), Error> {
+        db.check_changes(&self.relation, &self.inserted, &self.removed)
+    }
+
+    #[cfg(feature = "storage")]
+    fn entries(&self) -> Vec<wal::Entry<'_>> {
+        let mut entries = Vec::new();
+        if !self.removed.is_empty() {
+            let removed: &dyn Any = &self.removed;
+            entries.push((wal::Operation::Remove, self.relation.name(), removed));
+        }
+        if !self.inserted.is_empty() {
+            let inserted: &dyn Any = &self.inserted;
+            entries.push((wal::Operation::Insert, self.relation.name(), inserted));
+        }
+        entries
+    }
+
+    fn apply(self: Box<Self>, db: &Database) -> Result<(), Error> {
+        // the buffered insertions and removals are disjoint:
+        if !self.removed.is_empty() {
+            db.apply_remove(&self.relation, self.removed)?;
+        }
+        if !self.inserted.is_empty() {
+            db.apply_insert(&self.relation, self.inserted)?;
+        }
+        Ok(())
+    }
+}
+
+impl Pending for Vec<Box<dyn DynChanges>> {
+    fn inserted(&self, name: &str) -> Option<&dyn Any> {
+        self.iter()
+            .find(|c| c.relation() == name)
+            .map(|c| c.inserted())
+    }
+
+    fn removed(&self, name: &str) -> Option<&dyn Any> {
+        self.iter()
+            .find(|c| c.relation() == name)
+            .map(|c| c.removed())
+    }
+}
+
+/// Buffers insertions into and removals from the relations of a [`Database`], which are
+/// either committed together by [`Transaction::commit`] or discarded when the transaction
+/// is rolled back or dropped.
+///
+/// **Example**:
+/// ```rust
+/// use codd::Database;
+///
+/// let mut db = Database::new();
+/// let fruit = db.add_relation::<String>("fruit").unwrap();
+/// let price = db.add_relation::<(String, i32)>("price").unwrap();
+///
+/// let mut transaction = db.transaction();
+/// transaction.insert(&fruit, vec!["apple".to_string()].into()).unwrap();
+/// transaction.insert(&price, vec![("apple".to_string(), 2)].into()).unwrap();
+/// assert!(db.evaluate(&fruit).unwrap().is_empty());
+///
+/// transaction.commit().unwrap();
+/// assert_eq!(vec!["apple".to_string()], db.evaluate(&fruit).unwrap().into_tuples());
+///
+/// let mut transaction = db.transaction();
+/// transaction.remove(&fruit, vec!["apple".to_string()].into()).unwrap();
+/// transaction.rollback();
+/// assert_eq!(1, db.evaluate(&fruit).unwrap().len());
+/// ```
+///
+/// [`Database`]: ./struct.Database.html
+/// [`Transaction::commit`]: ./struct.Transaction.html#method.commit
+pub struct Transaction<'d> {
+    database: &'d Database,
+
+    /// Contains the buffered changes to relations in the order of their first change.
+    changes: Vec<Box<dyn DynChanges>>,
+}
+
+impl<'d> Transaction<'d> {
+    pub(super) fn new(database: &'d Database) -> Self {
+        Self {
+            database,
+            changes: Vec::new(),
+        }
+    }
+
+    /// Buffers `tuples` to be inserted into `relation`. Pending removals of the same tuples
+    /// in the transaction are cancelled.
+    pub fn insert<T>(&mut self, relation: &Relation<T>, tuples: Tuples<T>) -> Result<(), Error>
+    where
+        T: Tuple + 'static,
+    {
+        self.changes(relation)?.insert(tuples);
+        Ok(())
+    }
+
+    /// Buffers `tuples` to be removed from `relation`. Pending insertions of the same tuples
+    /// in the transaction are cancelled.
+    pub fn remove<T>(&mut self, relation: &Relation<T>, tuples: Tuples<T>) -> Result<(), Error>
+    where
+        T: Tuple + 'static,
+    {
+        self.changes(relation)?.remove(tuples);
+        Ok(())
+    }
+
+    /// Applies the buffered changes on the database and stabilizes the changed relations
+    /// and the views that depend on them, once for all changes. If a change violates the
+    /// schema or the key of its relation, or the changes together violate an inclusion
+    /// dependency, no change is applied and the error is returned. If the database is
+    /// opened with a write-ahead log, the changes are written in one record of the log,
+    /// which is replayed entirely or not at all.
+    pub fn commit(mut self) -> Result<(), Error> {
+        let _guard = self.database.lock();
+        let changes = std::mem::take(&mut self.changes);
+        for change in changes.iter() {
+            change.check(self.database)?;
+        }
+        let relations: Vec<String> = changes.iter().map(|c| c.relation().into()).collect();
+        {
+            let names: Vec<&str> = relations.iter().map(String::as_str).collect();
+            self.database.check_inclusions(&names, &changes)?;
+        }
+        #[cfg(feature = "storage")]
+        {
+            let entries: Vec<wal::Entry> = changes.iter().flat_map(|c| c.entries()).collect();
+            self.database.log(&entries)?;
+        }
+        for change in changes.into_iter() {
+            change.apply(self.database)?;
+        }
+        let relations: Vec<&str> = relations.iter().map(String::as_str).collect();
+        self.database.stabilize_relations(&relations)
+    }
+
+    /// Discards the buffered changes.
+    pub fn rollback(self) {}
+
+    /// Returns the buffered changes to `relation`, which are created if they don't exist.
+    fn changes<T>(&mut self, relation: &Relation<T>) -> Result<&mut Changes<T>, Error>
+    where
+        T: Tuple + 'static,
+    {
+        self.database.relation_instance(relation)?;
+        let position = match self
+            .changes
+            .iter()
+            .position(|c| c.relation() == relation.name())
+        {
+            Some(position) => position,
+            None => {
+                self.changes.push(Box::new(Changes::new(relation)));
+                self.changes.len() - 1
+            }
+        };
+        Ok(self.changes[position]
+            .as_any_mut()
+            .downcast_mut::<Changes<T>>()
+            .expect("changes of the relation"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{expression::Relation, Database, Error, Expression, Tuples};
+
+    #[test]
+    fn test_commit() {
+        let mut database = Database::new();
+        let r = database.add_relation::<(i32, i32)>("r").unwrap();
+        let s = database.add_relation::<i32>("s").unwrap();
+        let dummy = Relation::<i32>::new("t");
+        let view = database
+            .store_view(r.builder().project(|t| t.1).build())
+            .unwrap();
+
+        let mut transaction = database.transaction();
+        assert!(transaction.insert(&dummy, vec![1].into()).is_err());
+        transaction
+            .insert(&r, vec![(1, 10), (2, 20)].into())
+            .unwrap();
+        transaction.insert(&s, vec![1, 2].into()).unwrap();
+        transaction.remove(&s, vec![2].into()).unwrap();
+        assert!(database.evaluate(&view).unwrap().is_empty());
+
+        transaction.commit().unwrap();
+        assert_eq!(
+            Tuples::from(vec![10, 20]),
+            database.evaluate(&view).unwrap()
+        );
+        assert_eq!(Tuples::from(vec![1]), database.evaluate(&s).unwrap());
+
+        let mut transaction = database.transaction();
+        transaction.remove(&r, vec![(1, 10)].into()).unwrap();
+        transaction.insert(&s, vec![3].into()).unwrap();
+        drop(transaction);
+        assert_eq!(
+            Tuples::from(vec![10, 20]),
+            database.evaluate(&view).unwrap()
+        );
+        assert_eq!(Tuples::from(vec![1]), database.evaluate(&s).unwrap());
+    }
+
+    #[test]
+    fn test_commit_violation() {
+        let mut database = Database::new();
+        let r = database
+            .add_relation_with_key::<i32, (i32, i32)>("r", |t| t.0)
+            .unwrap();
+        let s = database.add_relation::<i32>("s").unwrap();
+        database.insert(&r, vec![(1, 10)].into()).unwrap();
+
+        let mut transaction = database.transaction();
+        transaction.insert(&s, vec![1].into()).unwrap();
+        transaction.insert(&r, vec![(1, 11)].into()).unwrap();
+        assert!(matches!(
+            transaction.commit(),
+            Err(Error::ConstraintViolation { .. })
+        ));
+        assert!(database.evaluate(&s).unwrap().is_empty());
+        assert_eq!(Tuples::from(vec![(1, 10)]), database.evaluate(&r).unwrap());
+
+        // replacing a tuple by its key in one transaction:
+        let mut transaction = database.transaction();
+        transaction.remove(&r, vec![(1, 10)].into()).unwrap();
+        transaction.insert(&r, vec![(1, 11)].into()).unwrap();
+        transaction.commit().unwrap();
+        assert_eq!(Tuples::from(vec![(1, 11)]), database.evaluate(&r).unwrap());
+    }
+
+    #[test]
+    fn test_commit_inclusion() {
+        let mut database = Database::new();
+        let r = database.add_relation::<(i32, i32)>("r").unwrap();
+        let s = database.add_relation::<i32>("s").unwrap();
+        let t = database.add_relation::<i32>("t").unwrap();
+        database.add_inclusion(&r, |t| t.0, &s, |t| *t).unwrap();
+
+        // the referenced tuples are inserted in the same transaction:
+        let mut transaction = database.transaction();
+        transaction.insert(&r, vec![(1, 10)].into()).unwrap();
+        transaction.insert(&s, vec![1].into()).unwrap();
+        transaction.commit().unwrap();
+        assert_eq!(Tuples::from(vec![(1, 10)]), database.evaluate(&r).unwrap());
+
+        let mut transaction = database.transaction();
+        transaction.insert(&t, vec![1].into()).unwrap();
+        transaction.insert(&s, vec![2].into()).unwrap();
+        transaction.remove(&s, vec![1].into()).unwrap();
+        transaction.insert(&r, vec![(2, 20)].into()).unwrap();
+        assert!(matches!(
+            transaction.commit(),
+            Err(Error::ConstraintViolation { .. })
+        ));
+        assert_eq!(Tuples::from(vec![(1, 10)]), database.evaluate(&r).unwrap());
+        assert_eq!(Tuples::from(vec![1]), database.evaluate(&s).unwrap());
+        assert!(database.evaluate(&t).unwrap().is_empty());
+
+        // the referencing tuples are removed in the same transaction:
+        let mut transaction = database.transaction();
+        transaction.remove(&r, vec![(1, 10)].into()).unwrap();
+        transaction.remove(&s, vec![1].into()).unwrap();
+        transaction.commit().unwrap();
+        assert!(database.evaluate(&r).unwrap().is_empty());
+        assert!(database.evaluate(&s).unwrap().is_empty());
+    }
+}
@@ -1,47 +1,90 @@
 use crate::{
-    expression::{Difference, Expression, Visitor},
+    expression::{view::ViewRef, Aggregate, Difference, Expression, View, Visitor},
     Error, Tuple,
 };
 
-/// Is a `crate::expression::Visitor` that validates if an expression can be turned into
-/// a [`View`]. Currently, expressions containing `Difference` are not supported.
+/// Is a `crate::expression::Visitor` that validates if an expression can be the expression
+/// of a recursive [`View`], identified by `reference`. Because a recursive view evaluates
+/// to the least fixpoint of its expression, the view cannot appear on the right side of a
+/// [`Difference`] or inside an [`Aggregate`].
 ///
 /// [`View`]: ../../struct.View.html
 /// [`Difference`]: ../../struct.Difference.html
-pub(crate) struct ViewExpressionValidator(Option<Error>);
+/// [`Aggregate`]: ../../struct.Aggregate.html
+pub(crate) struct RecursiveViewValidator<'a> {
+    reference: &'a ViewRef,
+    /// Is the name of the innermost non-monotonic expression that contains the visited
+    /// expression, if any.
+    negated: Option<&'static str>,
+    error: Option<Error>,
+}
 
-impl ViewExpressionValidator {
-    pub fn new() -> Self {
-        Self(None)
+impl<'a> RecursiveViewValidator<'a> {
+    pub fn new(reference: &'a ViewRef) -> Self {
+        Self {
+            reference,
+            negated: None,
+            error: None,
+        }
     }
 
     #[inline]
     pub fn into_error(self) -> Option<Error> {
-        self.0
+        self.error
     }
 }
 
-impl Visitor for ViewExpressionValidator {
-    fn visit_difference<T, L, R>(&mut self, _: &Difference<T, L, R>)
+impl<'a> Visitor for RecursiveViewValidator<'a> {
+    fn visit_difference<T, L, R>(&mut self, difference: &Difference<T, L, R>)
     where
         T: Tuple,
         L: Expression<T>,
         R: Expression<T>,
     {
-        self.0 = Some(Error::UnsupportedExpression {
-            name: "Difference".to_string(),
-            operation: "Create View".to_string(),
-        })
+        difference.left().visit(self);
+
+        let negated = self.negated.replace("Difference");
+        difference.right().visit(self);
+        self.negated = negated;
+    }
+
+    fn visit_aggregate<K, S, A, E>(&mut self, aggregate: &Aggregate<K, S, A, E>)
+    where
+        K: Tuple,
+        S: Tuple,
+        A: Tuple,
+        E: Expression<S>,
+    {
+        let negated = self.negated.replace("Aggregate");
+        aggregate.expression().visit(self);
+        self.negated = negated;
+    }
+
+    fn visit_view<T, E>(&mut self, view: &View<T, E>)
+    where
+        T: Tuple,
+        E: Expression<T>,
+    {
+        if let Some(name) = self.negated.filter(|_| view.reference() == self.reference) {
+            self.error = Some(Error::UnsupportedExpression {
+                name: name.to_string(),
+                operation: "Create Recursive View".to_string(),
+            })
+        }
     }
 }
 
-/// Validates `expression` and returns an error if it cannot be turned into a [`View`].
-pub(super) fn validate_view_expression<T, E>(expression: &E) -> Result<(), Error>
+/// Validates `expression` and returns an error if it cannot be the expression of the
+/// recursive view identified by `reference`.
+pub(super) fn validate_recursive_view_expression<T, E>(
+    expression: &E,
+    reference: &ViewRef,
+) -> Result<(), Error>
 where
     T: Tuple,
     E: Expression<T>,
 {
-    let mut validator = ViewExpressionValidator::new();
+    let mut validator = RecursiveViewValidator::new(reference);
     expression.visit(&mut validator);
     if let Some(e) = validator.into_error() {
         Err(e)
@@ -0,0 +1,318 @@
+/*! Implements the write-ahead log of databases that are opened by `Database::open` when
+the `storage` feature is enabled.
+
+Every record of the log is a list of batches of tuples that are inserted into or removed
+from relation instances together, by one insertion, removal or committed transaction. A
+record is stored as its length (a little endian `u64`), followed by its entries: the
+operation, the name of the relation and the encoded tuples of every batch. A record is
+replayed only if it is complete and all of its entries can be read, so the batches of a
+record are applied together or not at all.
+*/
+use super::{
+    serialize::Schema,
+    storage::{io_error, storage_error},
+    Database,
+};
+use crate::Error;
+use serde::{Deserialize, Serialize};
+use std::{
+    any::Any,
+    convert::TryInto,
+    fs::{File, OpenOptions},
+    io::{Read, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Is the name of the log file in the directory of a database.
+const LOG_FILE: &str = "wal.log";
+
+/// Is the size of the length prefix of records.
+const LENGTH_SIZE: usize = std::mem::size_of::<u64>();
+
+/// Is the operation of a record in the log.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub(super) enum Operation {
+    Insert,
+    Remove,
+}
+
+/// Is an entry of a record in the log: an operation with a batch of tuples (of type
+/// `Tuples`) on the instance of a relation.
+pub(super) type Entry<'a> = (Operation, &'a str, &'a dyn Any);
+
+/// Is the write-ahead log of a database.
+pub(super) struct Log {
+    /// Is the canonical path to the directory of the database.
+    dir: PathBuf,
+
+    /// Is the log file, opened for appending records.
+    file: Mutex<File>,
+
+    /// Is the schema of the database, providing the codecs for writing records.
+    schema: Schema,
+}
+
+impl Log {
+    /// Opens the log in `dir`, creating it if it doesn't exist, and replays its records
+    /// on the relation instances of `database` with the types in `schema`. An incomplete
+    /// record at the end of the log (i.e., an insertion, a removal or a commit that has
+    /// not been acknowledged) is discarded.
+    pub fn open(dir: &Path, database: &Database, schema: &Schema) -> Result<Self, Error> {
+        let dir = dir.canonicalize().map_err(|e| storage_error(dir, e))?;
+        let path = dir.join(LOG_FILE);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&path)
+            .map_err(|e| storage_error(&path, e))?;
+
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)
+            .map_err(|e| storage_error(&path, e))?;
+
+        let mut offset = 0;
+        while let Some(record) = next_record(&bytes[offset..]) {
+            replay(record, database, schema, &path)?;
+            offset += LENGTH_SIZE + record.len();
+        }
+        if offset < bytes.len() {
+            file.set_len(offset as u64)
+                .map_err(|e| storage_error(&path, e))?;
+        }
+
+        Ok(Self {
+            dir,
+            file: Mutex::new(file),
+            schema: schema.clone(),
+        })
+    }
+
+    /// Returns the canonical path to the directory of the database.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Appends one record for applying the operations of `entries` together and returns
+    /// after the record is written to disk. Returns an error if the relation of an entry
+    /// is not a relation of the schema of the log.
+    pub fn append(&self, entries: &[Entry]) -> Result<(), Error> {
+        let path = self.dir.join(LOG_FILE);
+        let mut encoded = Vec::new();
+        for (operation, name, tuples) in entries {
+            let codec = self
+                .schema
+                .codec(name)
+                .ok_or_else(|| Error::InstanceNotFound {
+                    name: name.to_string(),
+                })?;
+            let mut bytes = Vec::new();
+            codec
+                .encode(*tuples, &mut bytes)
+                .map_err(|e| storage_error(&path, io_error(*e)))?;
+            encoded.push((*operation, *name, bytes));
+        }
+        let mut record = vec![0; LENGTH_SIZE];
+        bincode::serialize_into(&mut record, &encoded)
+            .map_err(|e| storage_error(&path, io_error(*e)))?;
+        let length = (record.len() - LENGTH_SIZE) as u64;
+        record[..LENGTH_SIZE].copy_from_slice(&length.to_le_bytes());
+
+        let mut file = self.file.lock().unwrap();
+        file.write_all(&record)
+            .and_then(|_| file.sync_data())
+            .map_err(|e| storage_error(&path, e))
+    }
+
+    /// Removes all records of the log.
+    pub fn truncate(&self) -> Result<(), Error> {
+        let path = self.dir.join(LOG_FILE);
+        let file = self.file.lock().unwrap();
+        file.set_len(0)
+            .and_then(|_| file.sync_data())
+            .map_err(|e| storage_error(&path, e))
+    }
+}
+
+/// Returns the first complete record in `bytes` if it exists.
+fn next_record(bytes: &[u8]) -> Option<&[u8]> {
+    if bytes.len() < LENGTH_SIZE {
+        return None;
+    }
+    let length = u64::from_le_bytes(bytes[..LENGTH_SIZE].try_into().unwrap()) as usize;
+    bytes[LENGTH_SIZE..].get(..length)
+}
+
+/// Applies the entries of `record` of the log at `path` on the instances of their relations
+/// in `database`. No entry is applied unless all entries are read.
+fn replay(record: &[u8], database: &Database, schema: &Schema, path: &Path) -> Result<(), Error> {
+    let entries: Vec<(Operation, String, Vec<u8>)> =
+        bincode::deserialize(record).map_err(|e| storage_error(path, io_error(*e)))?;
+
+    let mut batches = Vec::new();
+    for (operation, name, bytes) in entries {
+        let entry = database
+            .relations
+            .get(&name)
+            .ok_or_else(|| Error::InstanceNotFound { name: name.clone() })?;
+        let codec = schema
+            .codec(&name)
+            .ok_or_else(|| Error::InstanceNotFound { name: name.clone() })?;
+        let tuples = codec
+            .decode(&bytes)
+            .map_err(|e| storage_error(path, io_error(*e)))?;
+        batches.push((codec, entry.instance.as_ref(), operation, tuples));
+    }
+    for (codec, instance, operation, tuples) in batches {
+        codec.replay(instance, operation, tuples);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// Returns a fresh temporary directory for the test `name`.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("codd-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_replay_log() {
+        {
+            let dir = temp_dir("replay-log");
+            let mut schema = Schema::new();
+            let r = schema.add_relation::<(i32, String)>("r").unwrap();
+            let s = schema.add_relation::<i32>("s").unwrap();
+
+            let database = Database::open(&dir, &schema).unwrap();
+            database
+                .insert(&r, vec![(1, "a".to_string()), (2, "b".to_string())].into())
+                .unwrap();
+            database.insert(&s, vec![1, 2, 3].into()).unwrap();
+            database.remove(&s, vec![2].into()).unwrap();
+            database
+                .insert(&r, vec![(3, "c".to_string())].into())
+                .unwrap();
+            drop(database);
+
+            let database = Database::open(&dir, &schema).unwrap();
+            {
+                // the batch structure of `to_add` is preserved:
+                let instance = database.relation_instance(&r).unwrap();
+                assert_eq!(2, instance.to_add().len());
+            }
+            assert_eq!(
+                vec![
+                    (1, "a".to_string()),
+                    (2, "b".to_string()),
+                    (3, "c".to_string())
+                ],
+                database.evaluate(&r).unwrap().into_tuples()
+            );
+            assert_eq!(vec![1, 3], database.evaluate(&s).unwrap().into_tuples());
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        {
+            let dir = temp_dir("replay-save");
+            let mut schema = Schema::new();
+            let r = schema.add_relation::<i32>("r").unwrap();
+
+            let database = Database::open(&dir, &schema).unwrap();
+            database.insert(&r, vec![1, 2].into()).unwrap();
+            database.save(&dir, &schema).unwrap();
+            assert_eq!(0, fs::metadata(dir.join(LOG_FILE)).unwrap().len());
+
+            database.remove(&r, vec![1].into()).unwrap();
+            database.insert(&r, vec![3].into()).unwrap();
+            drop(database);
+
+            let database = Database::open(&dir, &schema).unwrap();
+            assert_eq!(vec![2, 3], database.evaluate(&r).unwrap().into_tuples());
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        {
+            let dir = temp_dir("replay-torn");
+            let mut schema = Schema::new();
+            let r = schema.add_relation::<i32>("r").unwrap();
+
+            let database = Database::open(&dir, &schema).unwrap();
+            database.insert(&r, vec![1, 2].into()).unwrap();
+            drop(database);
+
+            // simulate a crash in the middle of appending a record:
+            let length = fs::metadata(dir.join(LOG_FILE)).unwrap().len();
+            let mut file = OpenOptions::new()
+                .append(true)
+                .open(dir.join(LOG_FILE))
+                .unwrap();
+            file.write_all(&[42, 0, 0, 0, 0, 0, 0, 0, 1, 2]).unwrap();
+            drop(file);
+
+            let database = Database::open(&dir, &schema).unwrap();
+            assert_eq!(length, fs::metadata(dir.join(LOG_FILE)).unwrap().len());
+            database.insert(&r, vec![3].into()).unwrap();
+            drop(database);
+
+            let database = Database::open(&dir, &schema).unwrap();
+            assert_eq!(vec![1, 2, 3], database.evaluate(&r).unwrap().into_tuples());
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        {
+            let dir = temp_dir("replay-commit");
+            let mut schema = Schema::new();
+            let r = schema.add_relation::<i32>("r").unwrap();
+            let s = schema.add_relation::<i32>("s").unwrap();
+
+            let database = Database::open(&dir, &schema).unwrap();
+            let mut transaction = database.transaction();
+            transaction.insert(&r, vec![1, 2].into()).unwrap();
+            transaction.insert(&s, vec![3].into()).unwrap();
+            transaction.commit().unwrap();
+            let length = fs::metadata(dir.join(LOG_FILE)).unwrap().len();
+
+            let mut transaction = database.transaction();
+            transaction.remove(&r, vec![1].into()).unwrap();
+            transaction.insert(&s, vec![4].into()).unwrap();
+            transaction.commit().unwrap();
+            drop(database);
+
+            let database = Database::open(&dir, &schema).unwrap();
+            assert_eq!(vec![2], database.evaluate(&r).unwrap().into_tuples());
+            assert_eq!(vec![3, 4], database.evaluate(&s).unwrap().into_tuples());
+            drop(database);
+
+            // simulate a crash in the middle of appending the record of the second commit:
+            let file = OpenOptions::new()
+                .write(true)
+                .open(dir.join(LOG_FILE))
+                .unwrap();
+            let torn = fs::metadata(dir.join(LOG_FILE)).unwrap().len() - 1;
+            assert!(torn > length);
+            file.set_len(torn).unwrap();
+            drop(file);
+
+            let database = Database::open(&dir, &schema).unwrap();
+            assert_eq!(length, fs::metadata(dir.join(LOG_FILE)).unwrap().len());
+            assert_eq!(vec![1, 2], database.evaluate(&r).unwrap().into_tuples());
+            assert_eq!(vec![3], database.evaluate(&s).unwrap().into_tuples());
+            fs::remove_dir_all(&dir).unwrap();
+        }
+        {
+            let dir = temp_dir("replay-missing");
+            let mut schema = Schema::new();
+            let r = schema.add_relation::<i32>("r").unwrap();
+            let database = Database::open(&dir, &schema).unwrap();
+            database.insert(&r, vec![1].into()).unwrap();
+            drop(database);
+
+            assert!(Database::open(&dir, &Schema::new()).is_err());
+            fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+}
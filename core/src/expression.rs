@@ -1,12 +1,16 @@
 /*! Defines relational algebraic expressions as generic types over `Tuple` types.*/
+mod aggregate;
 mod builder;
+mod closure;
 pub(crate) mod dependency;
 mod difference;
 mod empty;
 mod full;
+pub(crate) mod index;
 mod intersect;
 mod join;
 mod mono;
+mod optimize;
 mod product;
 mod project;
 mod relation;
@@ -15,24 +19,28 @@ mod singleton;
 mod union;
 pub(crate) mod view;
 
-use crate::Tuple;
+use crate::{MaybeSync, Tuple};
+pub use aggregate::Aggregate;
 pub use builder::Builder;
+pub(crate) use closure::Closure;
+pub use closure::{BinaryFn, TernaryFn, UnaryFn};
 pub use difference::Difference;
 pub use empty::Empty;
 pub use full::Full;
+pub use index::Index;
 pub use intersect::Intersect;
 pub use join::Join;
 pub use mono::Mono;
 pub use product::Product;
 pub use project::Project;
 pub use relation::Relation;
-pub use select::Select;
+pub use select::{Select, Side};
 pub use singleton::Singleton;
 pub use union::Union;
 pub use view::View;
 
 /// Is the trait of expressions in relational algebra that can be evaluated in a database.
-pub trait Expression<T: Tuple>: Clone + std::fmt::Debug {
+pub trait Expression<T: Tuple>: Clone + std::fmt::Debug + MaybeSync {
     /// Visits this expression by a [`Visitor`].
     ///
     /// [`Visitor`]: ./trait.Visitor.html
@@ -132,6 +140,15 @@ pub trait Visitor: Sized {
         walk_relation(self, relation)
     }
 
+    /// Visits an `Index` expression.
+    fn visit_index<K, T>(&mut self, index: &Index<K, T>)
+    where
+        K: Tuple,
+        T: Tuple,
+    {
+        walk_index(self, index)
+    }
+
     /// Visits a `Select` expression.
     fn visit_select<T, E>(&mut self, select: &Select<T, E>)
     where
@@ -206,6 +223,17 @@ pub trait Visitor: Sized {
         walk_join(self, join);
     }
 
+    /// Visits an `Aggregate` expression.
+    fn visit_aggregate<K, S, A, E>(&mut self, aggregate: &Aggregate<K, S, A, E>)
+    where
+        K: Tuple,
+        S: Tuple,
+        A: Tuple,
+        E: Expression<S>,
+    {
+        walk_aggregate(self, aggregate);
+    }
+
     /// Visits a `View` expression.    
     fn visit_view<T, E>(&mut self, view: &View<T, E>)
     where
@@ -248,6 +276,15 @@ where
     // nothing to do
 }
 
+fn walk_index<K, T, V>(visitor: &mut V, index: &Index<K, T>)
+where
+    K: Tuple,
+    T: Tuple,
+    V: Visitor,
+{
+    visitor.visit_relation(index.relation());
+}
+
 fn walk_select<T, E, V>(visitor: &mut V, select: &Select<T, E>)
 where
     T: Tuple,
@@ -327,6 +364,17 @@ where
     join.right().visit(visitor);
 }
 
+fn walk_aggregate<K, S, A, E, V>(visitor: &mut V, aggregate: &Aggregate<K, S, A, E>)
+where
+    K: Tuple,
+    S: Tuple,
+    A: Tuple,
+    E: Expression<S>,
+    V: Visitor,
+{
+    aggregate.expression().visit(visitor);
+}
+
 fn walk_view<T, E, V>(_: &mut V, _: &View<T, E>)
 where
     T: Tuple,
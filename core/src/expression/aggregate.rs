@@ -0,0 +1,175 @@
+use super::{view::ViewRef, BinaryFn, Closure, Expression, IntoExpression, UnaryFn, Visitor};
+use crate::Tuple;
+
+/// Groups the tuples of its sub-expression by a key and folds the tuples of every group
+/// into an accumulated value. The resulting tuples are pairs of keys (of type `K`) and
+/// their accumulated values (of type `A`).
+///
+/// **Note**: the tuples of a group are folded in their (sorted) order; however, because
+/// the aggregated values may be updated incrementally in views, the folding closure is
+/// expected to be insensitive to the order of tuples (e.g., count, sum, min and max).
+///
+/// **Example**:
+/// ```rust
+/// use codd::{Database, expression::Aggregate};
+///
+/// let mut db = Database::new();
+/// let songs = db.add_relation::<(String, String)>("songs").unwrap();
+///
+/// db.insert(&songs, vec![
+///    ("Dream Theater".to_string(), "Pull Me Under".to_string()),
+///    ("Dream Theater".to_string(), "Panic Attack".to_string()),
+///    ("Metallica".to_string(), "Enter Sandman".to_string()),
+/// ].into());
+///
+/// let counts = Aggregate::new(
+///     &songs,
+///     |s| s.0.clone(),        // group songs by artist
+///     0,                      // initial value of the counter
+///     |count, _| count + 1,   // count the songs of each group
+/// );
+///
+/// assert_eq!(
+///     vec![("Dream Theater".to_string(), 2), ("Metallica".to_string(), 1)],
+///     db.evaluate(&counts).unwrap().into_tuples(),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct Aggregate<K, S, A, E>
+where
+    K: Tuple,
+    S: Tuple,
+    A: Tuple,
+    E: Expression<S>,
+{
+    expression: E,
+    key: Closure<dyn UnaryFn<S, K>>,
+    init: A,
+    fold: Closure<dyn BinaryFn<A, S, A>>,
+    relation_deps: Vec<String>,
+    view_deps: Vec<ViewRef>,
+}
+
+impl<K, S, A, E> Aggregate<K, S, A, E>
+where
+    K: Tuple,
+    S: Tuple,
+    A: Tuple,
+    E: Expression<S>,
+{
+    /// Creates a new `Aggregate` expression over `expression` where `key` is a closure
+    /// that returns the grouping key of the tuples of `expression`. The tuples of every
+    /// group are folded by the closure `fold`, starting from the initial value `init`.
+    pub fn new<I>(
+        expression: I,
+        key: impl UnaryFn<S, K> + 'static,
+        init: A,
+        fold: impl BinaryFn<A, S, A> + 'static,
+    ) -> Self
+    where
+        I: IntoExpression<S, E>,
+    {
+        use super::dependency;
+        let expression = expression.into_expression();
+
+        let mut deps = dependency::DependencyVisitor::new();
+        expression.visit(&mut deps);
+        let (relation_deps, view_deps) = deps.into_dependencies();
+
+        Self {
+            expression,
+            key: Closure::unary(key),
+            init,
+            fold: Closure::binary(fold),
+            relation_deps: relation_deps.into_iter().collect(),
+            view_deps: view_deps.into_iter().collect(),
+        }
+    }
+
+    /// Returns a reference to the underlying sub-expression.
+    #[inline(always)]
+    pub fn expression(&self) -> &E {
+        &self.expression
+    }
+
+    /// Returns a reference to the initial value of the accumulator of every group.
+    #[inline(always)]
+    pub fn init(&self) -> &A {
+        &self.init
+    }
+
+    /// Returns the grouping key closure as an `FnMut` closure.
+    #[inline(always)]
+    pub(crate) fn key_mut(&self) -> impl FnMut(&S) -> K + '_ {
+        self.key.get()
+    }
+
+    /// Returns the folding closure as an `FnMut` closure.
+    #[inline(always)]
+    pub(crate) fn fold_mut(&self) -> impl FnMut(&A, &S) -> A + '_ {
+        self.fold.get()
+    }
+
+    /// Returns a reference to the relation dependencies of the receiver.
+    #[inline(always)]
+    pub(crate) fn relation_deps(&self) -> &[String] {
+        &self.relation_deps
+    }
+
+    /// Returns a reference to the view dependencies of the receiver.
+    #[inline(always)]
+    pub(crate) fn view_deps(&self) -> &[ViewRef] {
+        &self.view_deps
+    }
+}
+
+impl<K, S, A, E> Expression<(K, A)> for Aggregate<K, S, A, E>
+where
+    K: Tuple,
+    S: Tuple,
+    A: Tuple,
+    E: Expression<S>,
+{
+    fn visit<V>(&self, visitor: &mut V)
+    where
+        V: Visitor,
+    {
+        visitor.visit_aggregate(self);
+    }
+}
+
+impl<K, S, A, E> std::fmt::Debug for Aggregate<K, S, A, E>
+where
+    K: Tuple,
+    S: Tuple,
+    A: Tuple,
+    E: Expression<S>,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Aggregate")
+            .field("expression", &self.expression)
+            .field("init", &self.init)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Database, Tuples};
+
+    #[test]
+    fn test_clone() {
+        let mut database = Database::new();
+        let r = database.add_relation::<(i32, i32)>("r").unwrap();
+        database
+            .insert(&r, vec![(1, 10), (1, 20), (2, 30)].into())
+            .unwrap();
+        let a = Aggregate::new(&r, |t| t.0, 0, |sum, t| sum + t.1).clone();
+        assert_eq!(
+            Tuples::from(vec![(1, 30), (2, 30)]),
+            database.evaluate(&a).unwrap()
+        );
+        assert!(format!("{:?}", a).contains("init: 0"));
+    }
+}
@@ -1,4 +1,4 @@
-use super::*;
+use super::{index::IndexRef, *};
 use crate::Tuple;
 use std::marker::PhantomData;
 
@@ -37,7 +37,7 @@ where
     ///
     /// assert_eq!(vec!["apple", "banana", "cherry"], db.evaluate(&lower).unwrap().into_tuples());
     /// ```
-    pub fn project<T>(self, f: impl FnMut(&L) -> T + 'static) -> Builder<T, Project<L, T, Left>>
+    pub fn project<T>(self, f: impl UnaryFn<L, T> + 'static) -> Builder<T, Project<L, T, Left>>
     where
         T: Tuple,
     {
@@ -64,7 +64,7 @@ where
     ///
     /// assert_eq!(vec!["Apple", "BANANA"], db.evaluate(&select).unwrap().into_tuples());
     /// ```
-    pub fn select(self, f: impl FnMut(&L) -> bool + 'static) -> Builder<L, Select<L, Left>> {
+    pub fn select(self, f: impl UnaryFn<L, bool> + 'static) -> Builder<L, Select<L, Left>> {
         Builder {
             expression: Select::new(self.expression, f),
             _marker: PhantomData,
@@ -227,11 +227,49 @@ where
     ///     
     /// assert_eq!(vec!["Apple0", "Cherry4"], db.evaluate(&join).unwrap().into_tuples());
     /// ```
-    pub fn with_key<K>(self, f: impl FnMut(&L) -> K + 'static) -> WithKeyBuilder<K, L, Left>
+    pub fn with_key<K>(self, f: impl UnaryFn<L, K> + 'static) -> WithKeyBuilder<K, L, Left>
     where
         K: Tuple,
     {
-        WithKeyBuilder {
+        WithKeyBuilder::new(self.expression, f, None)
+    }
+
+    /// Combines the receiver's expression with closure `f` as the grouping key in a temporary
+    /// builder, which then can be turned into an [`Aggregate`] expression using an initial
+    /// value and a folding closure provided by method `aggregate`.
+    ///
+    /// [`Aggregate`]: ./struct.Aggregate.html
+    ///
+    /// **Example**:
+    /// ```rust
+    /// use codd::{Database, Expression};
+    ///
+    /// let mut db = Database::new();
+    /// let fruit = db.add_relation::<(String, i32)>("R").unwrap();
+    ///
+    /// db.insert(&fruit, vec![
+    ///    ("Apple".to_string(), 3),
+    ///    ("Apple".into(), 5),
+    ///    ("Banana".into(), 2),
+    /// ].into());
+    ///
+    /// let total = fruit
+    ///     .builder()
+    ///     .group_by(|t| t.0.clone()) // group tuples by the name of the fruit
+    ///     .aggregate(0, |sum, t| sum + t.1)
+    ///         // sum up the second element of tuples in every group, starting from `0`
+    ///     .build();
+    ///
+    /// assert_eq!(
+    ///     vec![("Apple".to_string(), 8), ("Banana".to_string(), 2)],
+    ///     db.evaluate(&total).unwrap().into_tuples()
+    /// );
+    /// ```
+    pub fn group_by<K>(self, f: impl UnaryFn<L, K> + 'static) -> GroupByBuilder<K, L, Left>
+    where
+        K: Tuple,
+    {
+        GroupByBuilder {
             expression: self.expression,
             key: Box::new(f),
         }
@@ -287,7 +325,7 @@ where
 {
     pub fn on<T: Tuple>(
         self,
-        f: impl FnMut(&L, &R) -> T + 'static,
+        f: impl BinaryFn<L, R, T> + 'static,
     ) -> Builder<T, Product<L, R, Left, Right, T>> {
         Builder {
             expression: Product::new(self.left, self.right, f),
@@ -303,7 +341,8 @@ where
     Left: Expression<L>,
 {
     expression: Left,
-    key: Box<dyn FnMut(&L) -> K>,
+    key: Box<dyn UnaryFn<L, K>>,
+    index: Option<IndexRef>,
 }
 
 impl<K, L, Left> WithKeyBuilder<K, L, Left>
@@ -312,6 +351,20 @@ where
     L: Tuple,
     Left: Expression<L>,
 {
+    /// Creates a new builder over `expression` with `key` as its key closure. If `index`
+    /// is given, it identifies an index of the database whose tuples are sorted by `key`.
+    pub(crate) fn new(
+        expression: Left,
+        key: impl UnaryFn<L, K> + 'static,
+        index: Option<IndexRef>,
+    ) -> Self {
+        Self {
+            expression,
+            key: Box::new(key),
+            index,
+        }
+    }
+
     pub fn join<R, Right>(
         self,
         other: WithKeyBuilder<K, R, Right>,
@@ -327,6 +380,34 @@ where
     }
 }
 
+pub struct GroupByBuilder<K, S, E>
+where
+    K: Tuple + 'static,
+    S: Tuple + 'static,
+    E: Expression<S>,
+{
+    expression: E,
+    key: Box<dyn UnaryFn<S, K>>,
+}
+
+impl<K, S, E> GroupByBuilder<K, S, E>
+where
+    K: Tuple,
+    S: Tuple,
+    E: Expression<S>,
+{
+    pub fn aggregate<A: Tuple>(
+        self,
+        init: A,
+        f: impl BinaryFn<A, S, A> + 'static,
+    ) -> Builder<(K, A), Aggregate<K, S, A, E>> {
+        Builder {
+            expression: Aggregate::new(self.expression, self.key, init, f),
+            _marker: PhantomData,
+        }
+    }
+}
+
 pub struct JoinBuilder<K, L, R, Left, Right>
 where
     K: Tuple + 'static,
@@ -349,7 +430,7 @@ where
 {
     pub fn on<T: Tuple>(
         self,
-        f: impl FnMut(&K, &L, &R) -> T + 'static,
+        f: impl TernaryFn<K, L, R, T> + 'static,
     ) -> Builder<T, Join<K, L, R, Left, Right, T>> {
         Builder {
             expression: Join::new(
@@ -358,7 +439,8 @@ where
                 self.left.key,
                 self.right.key,
                 f,
-            ),
+            )
+            .with_indexes(self.left.index, self.right.index),
             _marker: PhantomData,
         }
     }
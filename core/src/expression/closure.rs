@@ -0,0 +1,145 @@
+/*! Defines the traits of closures that are stored in expressions such as `Select`,
+`Project`, `Product` and `Join`.
+
+By default, these closures are `FnMut`. When the `sync` feature is enabled, the closures
+must be `Fn + Send + Sync` so that expressions (and databases containing views over them)
+can be shared between threads.
+*/
+#[cfg(feature = "sync")]
+use std::sync::Arc;
+#[cfg(not(feature = "sync"))]
+use std::{cell::RefCell, rc::Rc};
+
+/// Is the trait of closures with one argument, such as the predicate of [`Select`] and
+/// the mapping closure of [`Project`].
+///
+/// [`Select`]: ./struct.Select.html
+/// [`Project`]: ./struct.Project.html
+#[cfg(not(feature = "sync"))]
+pub trait UnaryFn<A, O>: FnMut(&A) -> O {}
+#[cfg(not(feature = "sync"))]
+impl<A, O, F: FnMut(&A) -> O> UnaryFn<A, O> for F {}
+
+/// Is the trait of closures with one argument, such as the predicate of [`Select`] and
+/// the mapping closure of [`Project`].
+///
+/// [`Select`]: ./struct.Select.html
+/// [`Project`]: ./struct.Project.html
+#[cfg(feature = "sync")]
+pub trait UnaryFn<A, O>: Fn(&A) -> O + Send + Sync {}
+#[cfg(feature = "sync")]
+impl<A, O, F: Fn(&A) -> O + Send + Sync> UnaryFn<A, O> for F {}
+
+/// Is the trait of closures with two arguments, such as the mapping closure of [`Product`].
+///
+/// [`Product`]: ./struct.Product.html
+#[cfg(not(feature = "sync"))]
+pub trait BinaryFn<A, B, O>: FnMut(&A, &B) -> O {}
+#[cfg(not(feature = "sync"))]
+impl<A, B, O, F: FnMut(&A, &B) -> O> BinaryFn<A, B, O> for F {}
+
+/// Is the trait of closures with two arguments, such as the mapping closure of [`Product`].
+///
+/// [`Product`]: ./struct.Product.html
+#[cfg(feature = "sync")]
+pub trait BinaryFn<A, B, O>: Fn(&A, &B) -> O + Send + Sync {}
+#[cfg(feature = "sync")]
+impl<A, B, O, F: Fn(&A, &B) -> O + Send + Sync> BinaryFn<A, B, O> for F {}
+
+/// Is the trait of closures with three arguments, such as the mapping closure of [`Join`].
+///
+/// [`Join`]: ./struct.Join.html
+#[cfg(not(feature = "sync"))]
+pub trait TernaryFn<A, B, C, O>: FnMut(&A, &B, &C) -> O {}
+#[cfg(not(feature = "sync"))]
+impl<A, B, C, O, F: FnMut(&A, &B, &C) -> O> TernaryFn<A, B, C, O> for F {}
+
+/// Is the trait of closures with three arguments, such as the mapping closure of [`Join`].
+///
+/// [`Join`]: ./struct.Join.html
+#[cfg(feature = "sync")]
+pub trait TernaryFn<A, B, C, O>: Fn(&A, &B, &C) -> O + Send + Sync {}
+#[cfg(feature = "sync")]
+impl<A, B, C, O, F: Fn(&A, &B, &C) -> O + Send + Sync> TernaryFn<A, B, C, O> for F {}
+
+/// Is a closure that is shared between the clones of an expression.
+pub(crate) struct Closure<F: ?Sized>(
+    #[cfg(not(feature = "sync"))] Rc<RefCell<F>>,
+    #[cfg(feature = "sync")] Arc<F>,
+);
+
+impl<F: ?Sized> Clone for Closure<F> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<F: ?Sized> Closure<F> {
+    /// Returns a reference to the closure, which may be called from multiple threads.
+    #[inline(always)]
+    pub fn get_ref(&self) -> &F {
+        &self.0
+    }
+}
+
+impl<A, O> Closure<dyn UnaryFn<A, O>> {
+    /// Creates a new shared closure for `f`.
+    pub fn unary(f: impl UnaryFn<A, O> + 'static) -> Self {
+        #[cfg(not(feature = "sync"))]
+        return Self(Rc::new(RefCell::new(f)));
+        #[cfg(feature = "sync")]
+        return Self(Arc::new(f));
+    }
+
+    /// Returns the closure as an `FnMut`.
+    #[inline(always)]
+    pub fn get(&self) -> impl FnMut(&A) -> O + '_ {
+        #[cfg(not(feature = "sync"))]
+        let mut f = self.0.borrow_mut();
+        #[cfg(feature = "sync")]
+        let f = &self.0;
+        move |a| f(a)
+    }
+}
+
+impl<A, B, O> Closure<dyn BinaryFn<A, B, O>> {
+    /// Creates a new shared closure for `f`.
+    pub fn binary(f: impl BinaryFn<A, B, O> + 'static) -> Self {
+        #[cfg(not(feature = "sync"))]
+        return Self(Rc::new(RefCell::new(f)));
+        #[cfg(feature = "sync")]
+        return Self(Arc::new(f));
+    }
+
+    /// Returns the closure as an `FnMut`.
+    #[inline(always)]
+    pub fn get(&self) -> impl FnMut(&A, &B) -> O + '_ {
+        #[cfg(not(feature = "sync"))]
+        let mut f = self.0.borrow_mut();
+        #[cfg(feature = "sync")]
+        let f = &self.0;
+        move |a, b| f(a, b)
+    }
+}
+
+impl<A, B, C, O> Closure<dyn TernaryFn<A, B, C, O>> {
+    /// Creates a new shared closure for `f`.
+    pub fn ternary(f: impl TernaryFn<A, B, C, O> + 'static) -> Self {
+        #[cfg(not(feature = "sync"))]
+        return Self(Rc::new(RefCell::new(f)));
+        #[cfg(feature = "sync")]
+        return Self(Arc::new(f));
+    }
+
+    /// Returns the closure as an `FnMut`.
+    #[cfg_attr(feature = "parallel", allow(dead_code))]
+    #[inline(always)]
+    pub fn get(&self) -> impl FnMut(&A, &B, &C) -> O + '_ {
+        #[cfg(not(feature = "sync"))]
+        let mut f = self.0.borrow_mut();
+        #[cfg(feature = "sync")]
+        let f = &self.0;
+        move |a, b, c| f(a, b, c)
+    }
+}
@@ -0,0 +1,194 @@
+use super::{builder::WithKeyBuilder, Closure, Expression, Relation, UnaryFn, Visitor};
+use crate::Tuple;
+
+/// Is the type of the index identifiers in a database: an index is identified by the
+/// name of its relation and its position among the indexes of the relation.
+#[derive(PartialEq, Eq, Clone, Hash, Debug)]
+pub struct IndexRef {
+    pub(crate) relation: String,
+    pub(crate) position: usize,
+}
+
+/// Is an expression corresponding to an index on a relation, which keeps the tuples of
+/// the relation sorted by a key of type `K`. An `Index` is created by
+/// [`Database::add_index`] and is maintained as tuples are inserted into and removed from
+/// its relation.
+///
+/// An `Index` evaluates to the tuples of its relation. Joins that are built by
+/// [`Index::with_key`] use the sorted tuples of the index instead of sorting the tuples
+/// of the relation by their keys on every evaluation, and [`Index::lookup`] (or a
+/// [`Select`] over the index with [`Select::with_key`]) selects the tuples of a key
+/// without scanning the relation.
+///
+/// **Example**:
+/// ```rust
+/// use codd::{Database, Expression};
+///
+/// let mut db = Database::new();
+/// let songs = db.add_relation::<(String, String)>("songs").unwrap();
+/// let bands = db.add_relation::<(String, i32)>("bands").unwrap();
+/// let by_band = db.add_index(&songs, |s| s.0.clone()).unwrap();
+///
+/// db.insert(&songs, vec![
+///    ("Metallica".to_string(), "Enter Sandman".to_string()),
+///    ("Dream Theater".to_string(), "Pull Me Under".to_string()),
+///    ("Metallica".to_string(), "One".to_string()),
+/// ].into());
+/// db.insert(&bands, vec![("Metallica".to_string(), 1981)].into());
+///
+/// // the tuples of `songs` are already sorted by their keys in the index:
+/// let formed = by_band
+///     .with_key()
+///     .join(bands.builder().with_key(|b| b.0.clone()))
+///     .on(|_, s, b| (s.1.clone(), b.1))
+///     .build();
+///
+/// assert_eq!(
+///     vec![("Enter Sandman".to_string(), 1981), ("One".to_string(), 1981)],
+///     db.evaluate(&formed).unwrap().into_tuples()
+/// );
+///
+/// let dream_theater = by_band.lookup("Dream Theater".to_string());
+/// assert_eq!(
+///     vec![("Dream Theater".to_string(), "Pull Me Under".to_string())],
+///     db.evaluate(&dream_theater).unwrap().into_tuples()
+/// );
+/// ```
+///
+/// [`Database::add_index`]: ../struct.Database.html#method.add_index
+/// [`Index::with_key`]: ./struct.Index.html#method.with_key
+/// [`Index::lookup`]: ./struct.Index.html#method.lookup
+/// [`Select`]: ./struct.Select.html
+/// [`Select::with_key`]: ./struct.Select.html#method.with_key
+#[derive(Clone)]
+pub struct Index<K, T>
+where
+    K: Tuple,
+    T: Tuple,
+{
+    relation: Relation<T>,
+    reference: IndexRef,
+    key: Closure<dyn UnaryFn<T, K>>,
+    lookup: Option<K>,
+}
+
+impl<K, T> Index<K, T>
+where
+    K: Tuple,
+    T: Tuple,
+{
+    /// Creates a new `Index` on `relation` at `position`, where `key` is the closure
+    /// that is shared with the index instance in the database.
+    pub(crate) fn new(
+        relation: Relation<T>,
+        position: usize,
+        key: Closure<dyn UnaryFn<T, K>>,
+    ) -> Self {
+        Self {
+            reference: IndexRef {
+                relation: relation.name().into(),
+                position,
+            },
+            relation,
+            key,
+            lookup: None,
+        }
+    }
+
+    /// Returns a reference to the relation of the index.
+    #[inline(always)]
+    pub fn relation(&self) -> &Relation<T> {
+        &self.relation
+    }
+
+    /// Returns a reference to the key whose tuples are selected by the receiver if the
+    /// receiver is created by [`Index::lookup`].
+    ///
+    /// [`Index::lookup`]: ./struct.Index.html#method.lookup
+    #[inline(always)]
+    pub fn lookup_key(&self) -> Option<&K> {
+        self.lookup.as_ref()
+    }
+
+    /// Returns an expression that evaluates to the tuples of the index whose key is equal
+    /// to `key`. The tuples are found by searching the sorted batches of the index.
+    pub fn lookup(&self, key: K) -> Self {
+        Self {
+            lookup: Some(key),
+            ..self.clone()
+        }
+    }
+
+    /// Combines the receiver with its key in a temporary builder, which then can be turned
+    /// into a [`Join`] expression that reuses the sorted tuples of the index.
+    ///
+    /// [`Join`]: ./struct.Join.html
+    pub fn with_key(&self) -> WithKeyBuilder<K, T, Self>
+    where
+        K: 'static,
+        T: 'static,
+    {
+        let key = self.key.clone();
+        let index = match self.lookup {
+            None => Some(self.reference.clone()),
+            Some(_) => None,
+        };
+        WithKeyBuilder::new(self.clone(), move |t: &T| (key.get())(t), index)
+    }
+
+    /// Returns a reference to the identifier of the index in its database.
+    #[inline(always)]
+    pub(crate) fn reference(&self) -> &IndexRef {
+        &self.reference
+    }
+}
+
+impl<K, T> Expression<T> for Index<K, T>
+where
+    K: Tuple + 'static,
+    T: Tuple + 'static,
+{
+    fn visit<V>(&self, visitor: &mut V)
+    where
+        V: Visitor,
+    {
+        visitor.visit_index(&self);
+    }
+}
+
+impl<K, T> std::fmt::Debug for Index<K, T>
+where
+    K: Tuple,
+    T: Tuple,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Index")
+            .field("relation", &self.relation)
+            .field("reference", &self.reference)
+            .field("lookup", &self.lookup)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Database, Tuples};
+
+    #[test]
+    fn test_clone() {
+        let mut database = Database::new();
+        let r = database.add_relation::<(i32, i32)>("r").unwrap();
+        let index = database.add_index(&r, |t| t.0).unwrap();
+        database
+            .insert(&r, vec![(1, 10), (1, 20), (2, 30)].into())
+            .unwrap();
+        assert_eq!(
+            Tuples::from(vec![(1, 10), (1, 20), (2, 30)]),
+            database.evaluate(&index.clone()).unwrap()
+        );
+        assert_eq!(
+            Tuples::from(vec![(1, 10), (1, 20)]),
+            database.evaluate(&index.lookup(1).clone()).unwrap()
+        );
+    }
+}
@@ -1,14 +1,13 @@
-use super::{view::ViewRef, Expression, IntoExpression, Visitor};
-use crate::Tuple;
-use std::{
-    cell::{RefCell, RefMut},
-    marker::PhantomData,
-    rc::Rc,
+use super::{
+    index::IndexRef, view::ViewRef, Closure, Expression, IntoExpression, TernaryFn, UnaryFn,
+    Visitor,
 };
+use crate::Tuple;
+use std::marker::PhantomData;
 
 /// Is the type of `Join` mapping closures for constructing tuples of type `T` from a key
 /// of type `K`, a left tuple of type `L`, and a right tuple of type `R`.
-type Mapper<K, L, R, T> = dyn FnMut(&K, &L, &R) -> T;
+type Mapper<K, L, R, T> = dyn TernaryFn<K, L, R, T>;
 
 /// Represents the join of its `left` and `right` sub-expressions.
 ///
@@ -50,9 +49,11 @@ where
 {
     left: Left,
     right: Right,
-    left_key: Rc<RefCell<dyn FnMut(&L) -> K>>,
-    right_key: Rc<RefCell<dyn FnMut(&R) -> K>>,
-    mapper: Rc<RefCell<Mapper<K, L, R, T>>>,
+    left_key: Closure<dyn UnaryFn<L, K>>,
+    right_key: Closure<dyn UnaryFn<R, K>>,
+    mapper: Closure<Mapper<K, L, R, T>>,
+    left_index: Option<IndexRef>,
+    right_index: Option<IndexRef>,
     relation_deps: Vec<String>,
     view_deps: Vec<ViewRef>,
 }
@@ -74,9 +75,9 @@ where
     pub fn new<IL, IR>(
         left: IL,
         right: IR,
-        left_key: impl FnMut(&L) -> K + 'static,
-        right_key: impl FnMut(&R) -> K + 'static,
-        mapper: impl FnMut(&K, &L, &R) -> T + 'static,
+        left_key: impl UnaryFn<L, K> + 'static,
+        right_key: impl UnaryFn<R, K> + 'static,
+        mapper: impl TernaryFn<K, L, R, T> + 'static,
     ) -> Self
     where
         IL: IntoExpression<L, Left>,
@@ -94,14 +95,28 @@ where
         Self {
             left,
             right,
-            left_key: Rc::new(RefCell::new(left_key)),
-            right_key: Rc::new(RefCell::new(right_key)),
-            mapper: Rc::new(RefCell::new(mapper)),
+            left_key: Closure::unary(left_key),
+            right_key: Closure::unary(right_key),
+            mapper: Closure::ternary(mapper),
+            left_index: None,
+            right_index: None,
             relation_deps: relation_deps.into_iter().collect(),
             view_deps: view_deps.into_iter().collect(),
         }
     }
 
+    /// Sets the indexes whose tuples are sorted by the keys of the left and right
+    /// sub-expressions respectively.
+    pub(crate) fn with_indexes(
+        mut self,
+        left_index: Option<IndexRef>,
+        right_index: Option<IndexRef>,
+    ) -> Self {
+        self.left_index = left_index;
+        self.right_index = right_index;
+        self
+    }
+
     /// Returns a reference to the left sub-expression.
     #[inline(always)]
     pub fn left(&self) -> &Left {
@@ -114,24 +129,61 @@ where
         &self.right
     }
 
-    /// Returns a mutable reference (of type `RefMut`) of the key closure for
-    /// the left sub-expression.
+    /// Returns a reference to the key closure for the left sub-expression.
+    #[inline(always)]
+    pub(crate) fn left_key(&self) -> &Closure<dyn UnaryFn<L, K>> {
+        &self.left_key
+    }
+
+    /// Returns a reference to the key closure for the right sub-expression.
+    #[inline(always)]
+    pub(crate) fn right_key(&self) -> &Closure<dyn UnaryFn<R, K>> {
+        &self.right_key
+    }
+
+    /// Returns a reference to the joining closure.
+    #[inline(always)]
+    pub(crate) fn mapper(&self) -> &Closure<Mapper<K, L, R, T>> {
+        &self.mapper
+    }
+
+    /// Returns the key closure for the left sub-expression as an `FnMut` closure.
+    #[inline(always)]
+    pub(crate) fn left_key_mut(&self) -> impl FnMut(&L) -> K + '_ {
+        self.left_key.get()
+    }
+
+    /// Returns the key closure for the right sub-expression as an `FnMut` closure.
+    #[inline(always)]
+    pub(crate) fn right_key_mut(&self) -> impl FnMut(&R) -> K + '_ {
+        self.right_key.get()
+    }
+
+    /// Returns a reference to the index of the left sub-expression if it exists.
+    #[inline(always)]
+    pub(crate) fn left_index(&self) -> Option<&IndexRef> {
+        self.left_index.as_ref()
+    }
+
+    /// Returns a reference to the index of the right sub-expression if it exists.
     #[inline(always)]
-    pub(crate) fn left_key_mut(&self) -> RefMut<dyn FnMut(&L) -> K> {
-        self.left_key.borrow_mut()
+    pub(crate) fn right_index(&self) -> Option<&IndexRef> {
+        self.right_index.as_ref()
     }
 
-    /// Returns a mutable reference (of type `RefMut`) of the key closure for
-    /// the right sub-expression.
+    /// Returns the joining closure as an `FnMut` closure.
+    #[cfg_attr(feature = "parallel", allow(dead_code))]
     #[inline(always)]
-    pub(crate) fn right_key_mut(&self) -> RefMut<dyn FnMut(&R) -> K> {
-        self.right_key.borrow_mut()
+    pub(crate) fn mapper_mut(&self) -> impl FnMut(&K, &L, &R) -> T + '_ {
+        self.mapper.get()
     }
 
-    /// Returns a mutable reference (of type `std::cell::RefMut`) to the joining closure.
+    /// Returns a reference to the joining closure, which may be called from multiple
+    /// threads.
+    #[cfg(feature = "parallel")]
     #[inline(always)]
-    pub(crate) fn mapper_mut(&self) -> RefMut<dyn FnMut(&K, &L, &R) -> T> {
-        self.mapper.borrow_mut()
+    pub(crate) fn mapper_ref(&self) -> &Mapper<K, L, R, T> {
+        self.mapper.get_ref()
     }
 
     /// Returns a reference to the relation dependencies of the receiver.
@@ -0,0 +1,415 @@
+/*! Implements a rewriting optimizer for [`Mono`] expressions.
+
+[`Mono`]: ./enum.Mono.html
+*/
+use super::{select::Keys, *};
+
+/// Returns a closure that calls the shared closure `f`.
+fn unary<A, O>(f: &Closure<dyn UnaryFn<A, O>>) -> impl UnaryFn<A, O> + 'static
+where
+    A: 'static,
+    O: 'static,
+{
+    let f = f.clone();
+    move |a: &A| (f.get())(a)
+}
+
+/// Returns a closure that calls the shared closure `f`.
+fn binary<A, B, O>(f: &Closure<dyn BinaryFn<A, B, O>>) -> impl BinaryFn<A, B, O> + 'static
+where
+    A: 'static,
+    B: 'static,
+    O: 'static,
+{
+    let f = f.clone();
+    move |a: &A, b: &B| (f.get())(a, b)
+}
+
+/// Returns a closure that calls the shared closure `f`.
+fn ternary<A, B, C, O>(
+    f: &Closure<dyn TernaryFn<A, B, C, O>>,
+) -> impl TernaryFn<A, B, C, O> + 'static
+where
+    A: 'static,
+    B: 'static,
+    C: 'static,
+    O: 'static,
+{
+    let f = f.clone();
+    move |a: &A, b: &B, c: &C| (f.get())(a, b, c)
+}
+
+/// Returns the `Join` of `left` and `right` on `keys` that maps the pairs of tuples by
+/// `mapper`, which replaces a `Product` of `left` and `right` under a `Select` with `keys`.
+fn join<T>(
+    left: Mono<T>,
+    right: Mono<T>,
+    mapper: &Closure<dyn BinaryFn<T, T, T>>,
+    (left_key, right_key): &Keys<T>,
+) -> Mono<T>
+where
+    T: Tuple + 'static,
+{
+    let mapper = mapper.clone();
+    Join::new(
+        left,
+        right,
+        unary(left_key),
+        unary(right_key),
+        move |_: &T, l: &T, r: &T| (mapper.get())(l, r),
+    )
+    .into()
+}
+
+/// Is the predicate of a `Select` with its declarations, applied on the expressions to
+/// which the `Select` is pushed.
+struct Selection<'s, T: Tuple> {
+    predicate: &'s Closure<dyn UnaryFn<T, bool>>,
+    side: Option<Side>,
+    keys: Option<&'s Keys<T>>,
+}
+
+impl<'s, T: Tuple + 'static> Selection<'s, T> {
+    /// Returns the selection of `select`.
+    fn new(select: &'s Select<T, Mono<T>>) -> Self {
+        Self {
+            predicate: select.predicate(),
+            side: select.side(),
+            keys: select.keys(),
+        }
+    }
+
+    /// Returns the selection without its declarations, which is pushed to one side of a
+    /// `Product` or a `Join`.
+    fn plain(&self) -> Self {
+        Self {
+            predicate: self.predicate,
+            side: None,
+            keys: None,
+        }
+    }
+
+    /// Applies the selection on the optimized `expression`.
+    fn apply(&self, expression: Mono<T>) -> Mono<T> {
+        match (expression, self.side, self.keys) {
+            (Mono::Empty(empty), _, _) => empty.into(),
+            (Mono::Union(union), _, _) => Union::new(
+                self.apply(union.left().clone()),
+                self.apply(union.right().clone()),
+            )
+            .into(),
+            (Mono::Product(product), Some(side), keys) => {
+                let (left, right) = self.split(side, product.left(), product.right());
+                match keys {
+                    // the keys of the selection pushed to `side` join the sides:
+                    Some(keys) => join(left, right, product.mapper(), keys),
+                    None => Product::new(left, right, binary(product.mapper())).into(),
+                }
+            }
+            (Mono::Join(join), Some(side), _) => {
+                let (left, right) = self.split(side, join.left(), join.right());
+                Join::new(
+                    left,
+                    right,
+                    unary(join.left_key()),
+                    unary(join.right_key()),
+                    ternary(join.mapper()),
+                )
+                .into()
+            }
+            (Mono::Product(product), None, Some(keys)) => join(
+                product.left().clone(),
+                product.right().clone(),
+                product.mapper(),
+                keys,
+            ),
+            (Mono::Select(inner), side, None) if inner.side() == side && inner.keys().is_none() => {
+                let first = inner.predicate().clone();
+                let second = self.predicate.clone();
+                let select = Select::new(inner.expression().clone(), move |t: &T| {
+                    if !(first.get())(t) {
+                        return false;
+                    }
+                    (second.get())(t)
+                });
+                match side {
+                    Some(side) => select.with_side(side).into(),
+                    None => select.into(),
+                }
+            }
+            (expression, side, keys) => {
+                let mut select = Select::new(expression, unary(self.predicate));
+                if let Some(side) = side {
+                    select = select.with_side(side);
+                }
+                if let Some((left_key, right_key)) = keys {
+                    select = select.with_keys(unary(left_key), unary(right_key));
+                }
+                select.into()
+            }
+        }
+    }
+
+    /// Pushes the selection to `side` of the pair of `left` and `right`.
+    fn split(&self, side: Side, left: &Mono<T>, right: &Mono<T>) -> (Mono<T>, Mono<T>) {
+        match side {
+            Side::Left => (self.plain().apply(left.clone()), right.clone()),
+            Side::Right => (left.clone(), self.plain().apply(right.clone())),
+        }
+    }
+}
+
+impl<T: Tuple + 'static> Mono<T> {
+    /// Returns an expression that evaluates to the same tuples as the receiver but is
+    /// cheaper to evaluate. The optimizer rewrites the receiver bottom up as follows:
+    /// * Subexpressions that evaluate to `Empty` are eliminated.
+    /// * `Select`s are pushed below `Union`s, and below `Product`s and `Join`s when the
+    ///   side of their predicate is declared by [`Select::with_side`].
+    /// * Consecutive `Select`s and consecutive `Project`s are merged.
+    /// * A `Select` over a `Product` becomes a `Join` when its keys are declared by
+    ///   [`Select::with_keys`]. A `Select` with both declarations is pushed to its side
+    ///   of the `Product` first.
+    ///
+    /// **Example**:
+    /// ```rust
+    /// use codd::{Database, expression::{Mono, Product, Select, Side}};
+    ///
+    /// let mut db = Database::new();
+    /// let r = db.add_relation::<(i32, i32)>("r").unwrap();
+    /// let s = db.add_relation::<(i32, i32)>("s").unwrap();
+    ///
+    /// db.insert(&r, vec![(1, 10), (2, 20), (3, 30)].into()).unwrap();
+    /// db.insert(&s, vec![(1, 100), (3, 300)].into()).unwrap();
+    ///
+    /// let product: Mono<_> = Product::new(
+    ///     Mono::from(r),
+    ///     Mono::from(s),
+    ///     |&l, &r| (l.0, r.1),
+    /// ).into();
+    /// // `t.0 > 1` holds exactly when it holds for the left tuple:
+    /// let filtered: Mono<_> = Select::new(product, |t| t.0 > 1)
+    ///     .with_side(Side::Left)
+    ///     .into();
+    /// // `t.1 == t.0 * 100` holds exactly when the left and the right tuples agree on `.0`:
+    /// let query: Mono<_> = Select::new(filtered, |t| t.1 == t.0 * 100)
+    ///     .with_keys(|l| (l.0, 0), |r| (r.0, 0))
+    ///     .into();
+    ///
+    /// let optimized = query.optimize();
+    /// assert!(matches!(optimized, Mono::Join(_)));
+    /// assert_eq!(vec![(3, 300)], db.evaluate(&query).unwrap().into_tuples());
+    /// assert_eq!(vec![(3, 300)], db.evaluate(&optimized).unwrap().into_tuples());
+    /// ```
+    ///
+    /// [`Select::with_side`]: ./struct.Select.html#method.with_side
+    /// [`Select::with_keys`]: ./struct.Select.html#method.with_keys
+    pub fn optimize(&self) -> Self {
+        match self {
+            Mono::Select(select) => Selection::new(select).apply(select.expression().optimize()),
+            Mono::Project(project) => match project.expression().optimize() {
+                Mono::Empty(empty) => empty.into(),
+                Mono::Project(inner) => {
+                    let first = inner.mapper().clone();
+                    let second = project.mapper().clone();
+                    Project::new(inner.expression().clone(), move |t: &T| {
+                        let t = (first.get())(t);
+                        (second.get())(&t)
+                    })
+                    .into()
+                }
+                expression => Project::new(expression, unary(project.mapper())).into(),
+            },
+            Mono::Union(union) => match (union.left().optimize(), union.right().optimize()) {
+                (Mono::Empty(_), expression) | (expression, Mono::Empty(_)) => expression,
+                (left, right) => Union::new(left, right).into(),
+            },
+            Mono::Intersect(intersect) => {
+                match (intersect.left().optimize(), intersect.right().optimize()) {
+                    (Mono::Empty(empty), _) | (_, Mono::Empty(empty)) => empty.into(),
+                    (left, right) => Intersect::new(left, right).into(),
+                }
+            }
+            Mono::Difference(difference) => {
+                match (difference.left().optimize(), difference.right().optimize()) {
+                    (Mono::Empty(empty), _) => empty.into(),
+                    (left, Mono::Empty(_)) => left,
+                    (left, right) => Difference::new(left, right).into(),
+                }
+            }
+            Mono::Product(product) => match (product.left().optimize(), product.right().optimize())
+            {
+                (Mono::Empty(empty), _) | (_, Mono::Empty(empty)) => empty.into(),
+                (left, right) => Product::new(left, right, binary(product.mapper())).into(),
+            },
+            Mono::Join(join) => match (join.left().optimize(), join.right().optimize()) {
+                (Mono::Empty(empty), _) | (_, Mono::Empty(empty)) => empty.into(),
+                (left, right) => Join::new(
+                    left,
+                    right,
+                    unary(join.left_key()),
+                    unary(join.right_key()),
+                    ternary(join.mapper()),
+                )
+                .into(),
+            },
+            _ => self.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Database;
+
+    #[test]
+    fn test_optimize_empty() {
+        let mut database = Database::new();
+        let r = database.add_relation::<i32>("r").unwrap();
+        database.insert(&r, vec![1, 2, 3].into()).unwrap();
+
+        let empty = Mono::from(Empty::new());
+        let r = Mono::from(r);
+        let union: Mono<_> = Union::new(empty.clone(), r.clone()).into();
+        assert!(matches!(union.optimize(), Mono::Relation(_)));
+
+        let difference: Mono<_> = Difference::new(r.clone(), empty.clone()).into();
+        assert!(matches!(difference.optimize(), Mono::Relation(_)));
+
+        let product: Mono<_> = Product::new(r.clone(), empty.clone(), |&l, &r| l + r).into();
+        let select: Mono<_> = Select::new(product, |&t| t > 1).into();
+        let intersect: Mono<_> = Intersect::new(r, select).into();
+        let optimized = intersect.optimize();
+        assert!(matches!(optimized, Mono::Empty(_)));
+        assert!(database.evaluate(&optimized).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_optimize_merge() {
+        let mut database = Database::new();
+        let r = database.add_relation::<i32>("r").unwrap();
+        database.insert(&r, vec![1, 2, 3, 4, 5, 6].into()).unwrap();
+
+        let project: Mono<_> = Project::new(Mono::from(r), |&t| t * 2).into();
+        let project: Mono<_> = Project::new(project, |&t| t + 1).into();
+        let select: Mono<_> = Select::new(project, |&t| t > 4).into();
+        let select: Mono<_> = Select::new(select, |&t| t < 12).into();
+
+        let optimized = select.optimize();
+        match &optimized {
+            Mono::Select(select) => match select.expression() {
+                Mono::Project(project) => {
+                    assert!(matches!(project.expression(), Mono::Relation(_)))
+                }
+                _ => panic!("expecting a project"),
+            },
+            _ => panic!("expecting a select"),
+        }
+        assert_eq!(
+            vec![5, 7, 9, 11],
+            database.evaluate(&optimized).unwrap().into_tuples()
+        );
+    }
+
+    #[test]
+    fn test_optimize_push_select() {
+        let mut database = Database::new();
+        let r = database.add_relation::<(i32, i32)>("r").unwrap();
+        let s = database.add_relation::<(i32, i32)>("s").unwrap();
+        database
+            .insert(&r, vec![(1, 10), (2, 20), (3, 30)].into())
+            .unwrap();
+        database
+            .insert(&s, vec![(1, 100), (2, 200), (4, 400)].into())
+            .unwrap();
+        let (r, s) = (Mono::from(r), Mono::from(s));
+
+        // pushed below a union:
+        let union: Mono<_> = Union::new(r.clone(), s.clone()).into();
+        let select: Mono<_> = Select::new(union, |t| t.0 > 1).into();
+        let optimized = select.optimize();
+        match &optimized {
+            Mono::Union(union) => {
+                assert!(matches!(union.left(), Mono::Select(_)));
+                assert!(matches!(union.right(), Mono::Select(_)));
+            }
+            _ => panic!("expecting a union"),
+        }
+        assert_eq!(
+            database.evaluate(&select).unwrap(),
+            database.evaluate(&optimized).unwrap()
+        );
+
+        // pushed to the right of a join:
+        let join: Mono<_> = Join::new(
+            r.clone(),
+            s.clone(),
+            |l| (l.0, 0),
+            |r| (r.0, 0),
+            |_, l, r| (l.1, r.1),
+        )
+        .into();
+        let select: Mono<_> = Select::new(join, |t| t.1 > 100)
+            .with_side(Side::Right)
+            .into();
+        let optimized = select.optimize();
+        match &optimized {
+            Mono::Join(join) => assert!(matches!(join.right(), Mono::Select(_))),
+            _ => panic!("expecting a join"),
+        }
+        assert_eq!(
+            vec![(20, 200)],
+            database.evaluate(&optimized).unwrap().into_tuples()
+        );
+
+        // a product turned into a join:
+        let product: Mono<_> = Product::new(r, s, |l, r| (l.1, r.1)).into();
+        let select: Mono<_> = Select::new(product, |t| t.1 == t.0 * 10)
+            .with_keys(|l| (l.0, 0), |r| (r.0, 0))
+            .into();
+        let optimized = select.optimize();
+        assert!(matches!(optimized, Mono::Join(_)));
+        assert_eq!(
+            vec![(10, 100), (20, 200)],
+            database.evaluate(&optimized).unwrap().into_tuples()
+        );
+    }
+
+    #[test]
+    fn test_optimize_side_and_keys() {
+        let mut database = Database::new();
+        let r = database.add_relation::<(i32, i32)>("r").unwrap();
+        let s = database.add_relation::<(i32, i32)>("s").unwrap();
+        database
+            .insert(&r, vec![(1, 100), (2, 200), (3, 300)].into())
+            .unwrap();
+        database
+            .insert(&s, vec![(1, 7), (2, 8), (3, 9), (4, 10)].into())
+            .unwrap();
+
+        // `t.0 > 1` holds for the left tuple and `t.1 == t.0 * 100` holds when the tuples
+        // agree on `.0`:
+        let product: Mono<_> =
+            Product::new(Mono::from(r), Mono::from(s), |l, r| (l.0, r.0 * 100)).into();
+        let select: Mono<_> = Select::new(product, |t| t.0 > 1 && t.1 == t.0 * 100)
+            .with_side(Side::Left)
+            .with_keys(|l| (l.0, 0), |r| (r.0, 0))
+            .into();
+        let optimized = select.optimize();
+        match &optimized {
+            Mono::Join(join) => {
+                assert!(matches!(join.left(), Mono::Select(_)));
+                assert!(matches!(join.right(), Mono::Relation(_)));
+            }
+            _ => panic!("expecting a join"),
+        }
+        assert_eq!(
+            vec![(2, 200), (3, 300)],
+            database.evaluate(&select).unwrap().into_tuples()
+        );
+        assert_eq!(
+            vec![(2, 200), (3, 300)],
+            database.evaluate(&optimized).unwrap().into_tuples()
+        );
+    }
+}
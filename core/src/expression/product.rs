@@ -1,10 +1,6 @@
-use super::{view::ViewRef, Expression, IntoExpression, Visitor};
+use super::{view::ViewRef, BinaryFn, Closure, Expression, IntoExpression, Visitor};
 use crate::Tuple;
-use std::{
-    cell::{RefCell, RefMut},
-    marker::PhantomData,
-    rc::Rc,
-};
+use std::marker::PhantomData;
 
 /// Corresponds to the cartesian product of two expression.
 ///
@@ -34,7 +30,7 @@ where
 {
     left: Left,
     right: Right,
-    mapper: Rc<RefCell<dyn FnMut(&L, &R) -> T>>,
+    mapper: Closure<dyn BinaryFn<L, R, T>>,
     relation_deps: Vec<String>,
     view_deps: Vec<ViewRef>,
 }
@@ -49,7 +45,7 @@ where
 {
     /// Creates a `Product` expression over `left` and `right` with `mapper` as the closure
     /// that produces the tuples of the resulting expression from tuples of `left` and `right`.
-    pub fn new<IL, IR>(left: IL, right: IR, project: impl FnMut(&L, &R) -> T + 'static) -> Self
+    pub fn new<IL, IR>(left: IL, right: IR, project: impl BinaryFn<L, R, T> + 'static) -> Self
     where
         IL: IntoExpression<L, Left>,
         IR: IntoExpression<R, Right>,
@@ -66,7 +62,7 @@ where
         Self {
             left,
             right,
-            mapper: Rc::new(RefCell::new(project)),
+            mapper: Closure::binary(project),
             relation_deps: relation_deps.into_iter().collect(),
             view_deps: view_deps.into_iter().collect(),
         }
@@ -84,10 +80,24 @@ where
         &self.right
     }
 
-    /// Returns a mutable reference (of type `std::cell::RefMut`) to the mapping closure.
+    /// Returns a reference to the mapping closure.
     #[inline(always)]
-    pub fn mapper_mut(&self) -> RefMut<dyn FnMut(&L, &R) -> T> {
-        self.mapper.borrow_mut()
+    pub(crate) fn mapper(&self) -> &Closure<dyn BinaryFn<L, R, T>> {
+        &self.mapper
+    }
+
+    /// Returns the mapping closure as an `FnMut` closure.
+    #[inline(always)]
+    pub fn mapper_mut(&self) -> impl FnMut(&L, &R) -> T + '_ {
+        self.mapper.get()
+    }
+
+    /// Returns a reference to the mapping closure, which may be called from multiple
+    /// threads.
+    #[cfg(feature = "parallel")]
+    #[inline(always)]
+    pub(crate) fn mapper_ref(&self) -> &(dyn BinaryFn<L, R, T> + 'static) {
+        self.mapper.get_ref()
     }
 
     /// Returns a reference to the relation dependencies of the receiver.
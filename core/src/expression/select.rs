@@ -1,10 +1,22 @@
-use super::{view::ViewRef, Expression, IntoExpression, Visitor};
+use super::{view::ViewRef, Closure, Expression, Index, IntoExpression, UnaryFn, Visitor};
 use crate::Tuple;
-use std::{
-    cell::{RefCell, RefMut},
-    marker::PhantomData,
-    rc::Rc,
-};
+use std::marker::PhantomData;
+
+/// Is the side of a [`Product`] or a [`Join`] whose tuples determine the predicate of a
+/// [`Select`] over the `Product` or the `Join`.
+///
+/// [`Product`]: ./struct.Product.html
+/// [`Join`]: ./struct.Join.html
+/// [`Select`]: ./struct.Select.html
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// Is the pair of closures that return the join keys of the left and right tuples of a
+/// `Product` under a `Select`.
+pub(crate) type Keys<T> = (Closure<dyn UnaryFn<T, T>>, Closure<dyn UnaryFn<T, T>>);
 
 /// Selects tuples of the underlying sub-expression according to a given predicate.
 ///
@@ -31,7 +43,9 @@ where
     E: Expression<T>,
 {
     expression: E,
-    predicate: Rc<RefCell<dyn FnMut(&T) -> bool>>,
+    predicate: Closure<dyn UnaryFn<T, bool>>,
+    side: Option<Side>,
+    keys: Option<Keys<T>>,
     relation_deps: Vec<String>,
     view_deps: Vec<ViewRef>,
 }
@@ -45,7 +59,7 @@ where
     pub fn new<I, P>(expression: I, predicate: P) -> Self
     where
         I: IntoExpression<T, E>,
-        P: FnMut(&T) -> bool + 'static,
+        P: UnaryFn<T, bool> + 'static,
     {
         use super::dependency;
         let expression = expression.into_expression();
@@ -56,22 +70,82 @@ where
 
         Self {
             expression,
-            predicate: Rc::new(RefCell::new(predicate)),
+            predicate: Closure::unary(predicate),
+            side: None,
+            keys: None,
             relation_deps: relation_deps.into_iter().collect(),
             view_deps: view_deps.into_iter().collect(),
         }
     }
 
+    /// Declares that the predicate holds for a tuple of a [`Product`] or a [`Join`] under
+    /// the receiver exactly when it holds for the tuple on `side` from which the tuple is
+    /// made. [`Mono::optimize`] pushes such a `Select` below the `Product` or the `Join`.
+    ///
+    /// [`Product`]: ./struct.Product.html
+    /// [`Join`]: ./struct.Join.html
+    /// [`Mono::optimize`]: ./enum.Mono.html#method.optimize
+    pub fn with_side(self, side: Side) -> Self {
+        Self {
+            side: Some(side),
+            ..self
+        }
+    }
+
+    /// Declares that the predicate holds for a tuple of a [`Product`] under the receiver
+    /// exactly when `left_key` of its left tuple is equal to `right_key` of its right tuple.
+    /// [`Mono::optimize`] turns such a `Select` over a `Product` into a [`Join`]. When the
+    /// side of the predicate is also declared by [`Select::with_side`], the predicate must
+    /// hold exactly when it holds for the tuple on that side and the keys are equal.
+    ///
+    /// **Note**: both keys have the type `T` of the tuples of the receiver, so a key that
+    /// is narrower than `T` must be padded to `T`, e.g., `|l| (l.0, 0)` for `(i32, i32)`.
+    ///
+    /// [`Product`]: ./struct.Product.html
+    /// [`Join`]: ./struct.Join.html
+    /// [`Mono::optimize`]: ./enum.Mono.html#method.optimize
+    /// [`Select::with_side`]: ./struct.Select.html#method.with_side
+    pub fn with_keys(
+        self,
+        left_key: impl UnaryFn<T, T> + 'static,
+        right_key: impl UnaryFn<T, T> + 'static,
+    ) -> Self {
+        Self {
+            keys: Some((Closure::unary(left_key), Closure::unary(right_key))),
+            ..self
+        }
+    }
+
     /// Returns a reference to the underlying sub-expression.
     #[inline(always)]
     pub fn expression(&self) -> &E {
         &self.expression
     }
 
-    /// Returns a mutable reference (of type `std::cell::RefMut`) to the select predicate.
+    /// Returns the side that is declared by [`Select::with_side`] if it exists.
+    ///
+    /// [`Select::with_side`]: ./struct.Select.html#method.with_side
+    #[inline(always)]
+    pub fn side(&self) -> Option<Side> {
+        self.side
+    }
+
+    /// Returns a reference to the select predicate.
+    #[inline(always)]
+    pub(crate) fn predicate(&self) -> &Closure<dyn UnaryFn<T, bool>> {
+        &self.predicate
+    }
+
+    /// Returns a reference to the keys that are declared by `with_keys` if they exist.
     #[inline(always)]
-    pub(crate) fn predicate_mut(&self) -> RefMut<dyn FnMut(&T) -> bool> {
-        self.predicate.borrow_mut()
+    pub(crate) fn keys(&self) -> Option<&Keys<T>> {
+        self.keys.as_ref()
+    }
+
+    /// Returns the select predicate as an `FnMut` closure.
+    #[inline(always)]
+    pub(crate) fn predicate_mut(&self) -> impl FnMut(&T) -> bool + '_ {
+        self.predicate.get()
     }
 
     /// Returns a reference to the relation dependencies of the receiver.
@@ -87,6 +161,47 @@ where
     }
 }
 
+impl<K, T> Select<T, Index<K, T>>
+where
+    K: Tuple + 'static,
+    T: Tuple + 'static,
+{
+    /// Declares that the predicate holds for a tuple of the [`Index`] under the receiver
+    /// exactly when the key of the tuple in the index is equal to `key`. The receiver then
+    /// looks up the tuples of `key` in the sorted batches of the index instead of scanning
+    /// all tuples of the relation of the index.
+    ///
+    /// **Example**:
+    /// ```rust
+    /// use codd::{Database, expression::Select};
+    ///
+    /// let mut db = Database::new();
+    /// let songs = db.add_relation::<(String, String)>("songs").unwrap();
+    /// let by_band = db.add_index(&songs, |s| s.0.clone()).unwrap();
+    ///
+    /// db.insert(&songs, vec![
+    ///    ("Metallica".to_string(), "Enter Sandman".to_string()),
+    ///    ("Dream Theater".to_string(), "Pull Me Under".to_string()),
+    /// ].into());
+    ///
+    /// let metallica = Select::new(by_band.clone(), |s| s.0 == "Metallica")
+    ///     .with_key("Metallica".to_string());
+    ///
+    /// assert_eq!(
+    ///     vec![("Metallica".to_string(), "Enter Sandman".to_string())],
+    ///     db.evaluate(&metallica).unwrap().into_tuples()
+    /// );
+    /// ```
+    ///
+    /// [`Index`]: ./struct.Index.html
+    pub fn with_key(self, key: K) -> Self {
+        Self {
+            expression: self.expression.lookup(key),
+            ..self
+        }
+    }
+}
+
 impl<T, E> Expression<T> for Select<T, E>
 where
     T: Tuple,
@@ -140,4 +255,48 @@ mod tests {
             database.evaluate(&p).unwrap()
         );
     }
+
+    #[test]
+    fn test_select_with_key() {
+        use std::sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        };
+
+        let mut database = Database::new();
+        let r = database.add_relation::<(i32, i32)>("r").unwrap();
+        let index = database.add_index(&r, |t| t.0).unwrap();
+        database
+            .insert(&r, (0..100).map(|i| (i % 10, i)).collect::<Vec<_>>().into())
+            .unwrap();
+        database.evaluate(&r).unwrap();
+        database
+            .insert(&r, vec![(3, 100), (4, 101)].into())
+            .unwrap();
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let select = {
+            let calls = calls.clone();
+            Select::new(index.clone(), move |t: &(i32, i32)| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                t.0 == 3
+            })
+            .with_key(3)
+        };
+        let result = database.evaluate(&select).unwrap();
+        assert_eq!(11, result.len());
+        assert!(result.iter().all(|t| t.0 == 3));
+        // only the tuples of the key in the index are tested by the predicate:
+        assert_eq!(11, calls.load(Ordering::SeqCst));
+
+        let view = database.store_view(select).unwrap();
+        database.evaluate(&view).unwrap();
+        calls.store(0, Ordering::SeqCst);
+        database
+            .insert(&r, vec![(3, 102), (5, 103)].into())
+            .unwrap();
+        database.remove(&r, vec![(3, 13), (5, 15)].into()).unwrap();
+        assert_eq!(11, database.evaluate(&view).unwrap().len());
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+    }
 }
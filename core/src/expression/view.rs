@@ -41,8 +41,12 @@ pub struct ViewRef(pub(crate) i32);
 /// );
 ///
 /// use codd::expression::Difference;
-/// // incremental view update for `Difference` is currently not supported:
-/// assert!(db.store_view(Difference::new(dividends, divisors)).is_err());
+/// let difference = db.store_view(Difference::new(dividends, divisors.clone())).unwrap();
+/// assert_eq!(vec![6, 12, 18, 24, 30], db.evaluate(&difference).unwrap().into_tuples());
+///
+/// // tuples inserted into the right side of `Difference` are removed from the view:
+/// db.insert(&divisors, vec![6, 12].into());
+/// assert_eq!(vec![18, 24, 30], db.evaluate(&difference).unwrap().into_tuples());
 /// ```
 #[derive(PartialEq, Eq, Clone, Debug)]
 pub struct View<T, E>
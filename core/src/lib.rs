@@ -1,23 +1,47 @@
 /*! Implements a minimal [database] and relational algebraic [expressions] for evaluating queries in the database.
+Queries over dynamically typed [rows] can also be [parsed] from text at runtime.
 
 [database]: ./struct.Database.html
 [expressions]: ./expression/index.html
+[rows]: ./type.Row.html
+[parsed]: ./query/index.html
  */
 mod database;
 pub mod expression;
+pub mod query;
+mod value;
 
 #[cfg(feature = "unstable")]
 mod macros;
+#[cfg(feature = "sync")]
+mod sync;
 
-pub use database::{Database, Tuples};
+#[cfg(feature = "serde")]
+pub use database::Schema;
+pub use database::{Analysis, Cursor, Database, Plan, Statistics, Transaction, Tuples};
 pub use expression::Expression;
 use thiserror::Error;
+pub use value::{Row, RowSchema, Value, ValueType};
 
 /// Is the trait of tuples. Tuples are the smallest unit of data stored in databases.
 ///
 /// **Note**: Tuples are analogous to the rows of a table in a conventional database.
-pub trait Tuple: Ord + Clone + std::fmt::Debug {}
-impl<T: Ord + Clone + std::fmt::Debug> Tuple for T {}
+pub trait Tuple: Ord + Clone + std::fmt::Debug + MaybeSync {}
+impl<T: Ord + Clone + std::fmt::Debug + MaybeSync> Tuple for T {}
+
+/// Is implemented by types that can be shared between threads (`Send + Sync`) when the
+/// `sync` feature is enabled; otherwise, it is implemented by all types.
+#[cfg(feature = "sync")]
+pub trait MaybeSync: Send + Sync {}
+#[cfg(feature = "sync")]
+impl<T: Send + Sync + ?Sized> MaybeSync for T {}
+
+/// Is implemented by types that can be shared between threads (`Send + Sync`) when the
+/// `sync` feature is enabled; otherwise, it is implemented by all types.
+#[cfg(not(feature = "sync"))]
+pub trait MaybeSync {}
+#[cfg(not(feature = "sync"))]
+impl<T: ?Sized> MaybeSync for T {}
 
 /// Is the type of errors returned by `codd`.
 #[derive(Error, Debug)]
@@ -33,4 +57,62 @@ pub enum Error {
     /// Is returned when attempting to re-define an existing instance in a database.
     #[error("database instance `{name:?}` already exists")]
     InstanceExists { name: String },
+
+    /// Is returned when a given index doesn't exist.
+    #[error("index {position} of relation `{relation:?}` not found")]
+    IndexNotFound { relation: String, position: usize },
+
+    /// Is returned when reading or writing the files of a database fails.
+    #[error("failed to access database file `{path:?}`: {source}")]
+    Storage {
+        path: std::path::PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// Is returned when exporting or importing the instances of a database fails.
+    #[error("failed to serialize or deserialize database: {message}")]
+    Serialization { message: String },
+
+    /// Is returned when a row with a wrong number of columns is inserted into a relation
+    /// with a schema.
+    #[error("expecting rows with {expected} columns in relation `{relation:?}`, found {found}")]
+    ArityMismatch {
+        relation: String,
+        expected: usize,
+        found: usize,
+    },
+
+    /// Is returned when a value of a wrong type is inserted into a column of a relation
+    /// with a schema.
+    #[error(
+        "expecting `{expected}` in column `{column:?}` of relation `{relation:?}`, found `{found}`"
+    )]
+    TypeMismatch {
+        relation: String,
+        column: String,
+        expected: ValueType,
+        found: ValueType,
+    },
+
+    /// Is returned when `Null` is inserted into a key column of a relation with a schema.
+    #[error("null in key column `{column:?}` of relation `{relation:?}`")]
+    NullKey { relation: String, column: String },
+
+    /// Is returned when inserting tuples into a relation violates a constraint of the
+    /// relation.
+    #[error("constraint of relation `{relation:?}` is violated by tuples {tuples:?}")]
+    ConstraintViolation {
+        relation: String,
+        tuples: Vec<String>,
+    },
+
+    /// Is returned when evaluating an expression at an epoch for which the history of a
+    /// relation is not kept.
+    #[error("history of relation `{relation:?}` at epoch {epoch} not found")]
+    HistoryNotFound { relation: String, epoch: u64 },
+
+    /// Is returned when the text of a query cannot be parsed.
+    #[error("failed to parse query at position {position}: {message}")]
+    Parse { position: usize, message: String },
 }
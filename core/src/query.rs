@@ -0,0 +1,686 @@
+/*! Implements a parser for a textual relational algebra over relations of [`Row`]s,
+which makes it possible to build queries at runtime. A query is parsed into a [`Mono`]
+expression that is evaluated in a [`Database`] like any other expression.
+
+The syntax of queries is as follows, where keywords are case insensitive:
+```text
+query     ::= primary (("union" | "intersect" | "minus") primary)*
+primary   ::= relation
+            | "select" "[" condition "]" "(" query ")"
+            | "project" "[" column ("," column)* "]" "(" query ")"
+            | "join" "[" column "=" column ("," column "=" column)* "]" "(" query "," query ")"
+            | "(" query ")"
+condition ::= conjunct ("or" conjunct)*
+conjunct  ::= negation ("and" negation)*
+negation  ::= "not" negation | "(" condition ")" | operand comparison operand
+operand   ::= column | integer | float | string | "true" | "false" | "null"
+column    ::= "#" position | name | relation "." name
+```
+Columns are referred to by their position (starting from `#0`) or by their name, which
+may be qualified by the name of the relation from which the column originates. The
+columns of a `join` are the columns of its left query followed by the columns of its right
+query, and the columns on the left and the right of `=` in a `join` refer to its left and
+right queries respectively. A comparison (`=`, `!=`, `<>`, `<`, `<=`, `>`, `>=`) compares
+values by the order of [`Value`]s; in particular, `null` is equal to `null` and values
+of different types are ordered by their types.
+
+**Example**:
+```rust
+use codd::{Database, query::Parser, Value};
+
+let mut db = Database::new();
+let emp = db.add_relation("emp").unwrap();
+let dept = db.add_relation("dept").unwrap();
+
+db.insert(&emp, vec![
+    vec![Value::from("Alice"), Value::from(1), Value::from(120)],
+    vec![Value::from("Bob"), Value::from(2), Value::from(90)],
+    vec![Value::from("Carol"), Value::from(1), Value::from(80)],
+].into()).unwrap();
+db.insert(&dept, vec![
+    vec![Value::from(1), Value::from("Research")],
+    vec![Value::from(2), Value::from("Sales")],
+].into()).unwrap();
+
+let mut parser = Parser::new();
+parser.add_relation(&emp, &["name", "dept", "salary"]);
+parser.add_relation(&dept, &["id", "title"]);
+
+let query = parser
+    .parse("project[emp.name, title](join[dept = id](select[salary > 85](emp), dept))")
+    .unwrap();
+assert_eq!(vec!["name", "title"], query.columns());
+assert_eq!(
+    vec![
+        vec![Value::from("Alice"), Value::from("Research")],
+        vec![Value::from("Bob"), Value::from("Sales")],
+    ],
+    db.evaluate(query.expression()).unwrap().into_tuples()
+);
+```
+
+[`Row`]: ../type.Row.html
+[`Value`]: ../enum.Value.html
+[`Mono`]: ../expression/enum.Mono.html
+[`Database`]: ../struct.Database.html
+*/
+mod lexer;
+
+use crate::{
+    expression::{Difference, Intersect, Join, Mono, Project, Relation, Select, Union},
+    Database, Error, Row, Value,
+};
+use lexer::{error, tokenize, Token};
+use std::collections::HashMap;
+
+/// Is a column of a query, identified by its name and the name of the relation from which
+/// it originates.
+#[derive(Clone, Debug)]
+struct Column {
+    relation: String,
+    name: String,
+}
+
+/// Is an operand of a comparison in a condition.
+#[derive(Clone, Debug)]
+enum Operand {
+    Column(usize),
+    Literal(Value),
+}
+
+impl Operand {
+    #[inline(always)]
+    fn value<'a>(&'a self, row: &'a Row) -> &'a Value {
+        match self {
+            Self::Column(position) => &row[*position],
+            Self::Literal(value) => value,
+        }
+    }
+}
+
+/// Is the condition of a `select` query.
+#[derive(Clone, Debug)]
+enum Condition {
+    Compare(Operand, &'static str, Operand),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+    Not(Box<Condition>),
+}
+
+impl Condition {
+    /// Returns true if the receiver holds for `row`.
+    fn holds(&self, row: &Row) -> bool {
+        match self {
+            Self::Compare(left, comparison, right) => {
+                let (left, right) = (left.value(row), right.value(row));
+                match *comparison {
+                    "=" => left == right,
+                    "!=" | "<>" => left != right,
+                    "<" => left < right,
+                    "<=" => left <= right,
+                    ">" => left > right,
+                    _ => left >= right,
+                }
+            }
+            Self::And(left, right) => left.holds(row) && right.holds(row),
+            Self::Or(left, right) => left.holds(row) || right.holds(row),
+            Self::Not(condition) => !condition.holds(row),
+        }
+    }
+}
+
+/// Is a query that is parsed by a [`Parser`].
+///
+/// [`Parser`]: ./struct.Parser.html
+#[derive(Clone, Debug)]
+pub struct Query {
+    expression: Mono<Row>,
+    columns: Vec<Column>,
+}
+
+impl Query {
+    /// Returns a reference to the expression of the query.
+    #[inline(always)]
+    pub fn expression(&self) -> &Mono<Row> {
+        &self.expression
+    }
+
+    /// Consumes the receiver and returns its expression.
+    #[inline(always)]
+    pub fn into_expression(self) -> Mono<Row> {
+        self.expression
+    }
+
+    /// Returns the names of the columns of the rows to which the query evaluates.
+    pub fn columns(&self) -> Vec<&str> {
+        self.columns.iter().map(|c| c.name.as_str()).collect()
+    }
+}
+
+/// Parses queries over the relations of [`Row`]s that are added to the parser with the
+/// names of their columns.
+///
+/// [`Row`]: ../type.Row.html
+#[derive(Clone, Default, Debug)]
+pub struct Parser {
+    relations: HashMap<String, Vec<String>>,
+}
+
+impl Parser {
+    /// Creates a new `Parser` with no relations.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new `Parser` for the relations of `database` that are added with schemas.
+    pub fn from_database(database: &Database) -> Self {
+        let mut parser = Self::new();
+        for (name, schema) in database.relations() {
+            if let Some(schema) = schema {
+                let columns = schema.names().into_iter().map(String::from).collect();
+                parser.relations.insert(name.into(), columns);
+            }
+        }
+        parser
+    }
+
+    /// Adds `relation` with the names of its `columns` to the relations that can be
+    /// queried by the receiver.
+    ///
+    /// **Note**: the rows of `relation` are expected to have a value for every column.
+    pub fn add_relation(&mut self, relation: &Relation<Row>, columns: &[&str]) {
+        self.relations.insert(
+            relation.name().into(),
+            columns.iter().map(|&c| c.into()).collect(),
+        );
+    }
+
+    /// Parses `text` into a [`Query`].
+    ///
+    /// [`Query`]: ./struct.Query.html
+    pub fn parse(&self, text: &str) -> Result<Query, Error> {
+        let mut state = State {
+            parser: self,
+            tokens: tokenize(text)?,
+            next: 0,
+            end: text.len(),
+        };
+        let query = state.query()?;
+        match state.peek() {
+            None => Ok(query),
+            Some(token) => Err(state.unexpected(token)),
+        }
+    }
+}
+
+/// Is the state of parsing the tokens of a query.
+struct State<'p> {
+    parser: &'p Parser,
+    tokens: Vec<(usize, Token)>,
+    /// Is the index of the next token.
+    next: usize,
+    /// Is the position of the end of the query text.
+    end: usize,
+}
+
+impl<'p> State<'p> {
+    fn peek(&self) -> Option<Token> {
+        self.tokens.get(self.next).map(|(_, t)| t.clone())
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.next)
+            .map(|(p, _)| *p)
+            .unwrap_or(self.end)
+    }
+
+    fn unexpected(&self, token: Token) -> Error {
+        error(self.position(), format!("unexpected token `{:?}`", token))
+    }
+
+    fn advance(&mut self) -> Result<Token, Error> {
+        let token = self
+            .peek()
+            .ok_or_else(|| error(self.end, "unexpected end of query"))?;
+        self.next += 1;
+        Ok(token)
+    }
+
+    /// Consumes the next token if it is `expected`.
+    fn accept(&mut self, expected: &Token) -> bool {
+        if self.peek().as_ref() == Some(expected) {
+            self.next += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, symbol: &'static str) -> Result<(), Error> {
+        if self.accept(&Token::Symbol(symbol)) {
+            Ok(())
+        } else {
+            Err(error(self.position(), format!("expecting `{}`", symbol)))
+        }
+    }
+
+    fn query(&mut self) -> Result<Query, Error> {
+        let mut left = self.primary()?;
+        loop {
+            let position = self.position();
+            let operator = match self.peek() {
+                Some(Token::Keyword(k)) if ["union", "intersect", "minus"].contains(&k) => k,
+                _ => return Ok(left),
+            };
+            self.next += 1;
+            let right = self.primary()?;
+            if left.columns.len() != right.columns.len() {
+                return Err(error(
+                    position,
+                    format!(
+                        "`{}` of queries with {} and {} columns",
+                        operator,
+                        left.columns.len(),
+                        right.columns.len()
+                    ),
+                ));
+            }
+            let (l, r) = (left.expression, right.expression);
+            left.expression = match operator {
+                "union" => Union::new(l, r).into(),
+                "intersect" => Intersect::new(l, r).into(),
+                _ => Difference::new(l, r).into(),
+            };
+        }
+    }
+
+    fn primary(&mut self) -> Result<Query, Error> {
+        let position = self.position();
+        match self.advance()? {
+            Token::Ident(name) => {
+                let columns = self
+                    .parser
+                    .relations
+                    .get(&name)
+                    .ok_or_else(|| error(position, format!("unknown relation `{}`", name)))?
+                    .iter()
+                    .map(|c| Column {
+                        relation: name.clone(),
+                        name: c.clone(),
+                    })
+                    .collect();
+                Ok(Query {
+                    expression: Relation::new(name).into(),
+                    columns,
+                })
+            }
+            Token::Keyword("select") => {
+                let (condition, query) =
+                    self.bracketed(1, |state, queries| state.condition(&queries[0].columns))?;
+                let query = query.into_iter().next().unwrap();
+                Ok(Query {
+                    expression: Select::new(query.expression, move |row: &Row| {
+                        condition.holds(row)
+                    })
+                    .into(),
+                    columns: query.columns,
+                })
+            }
+            Token::Keyword("project") => {
+                let (columns, query) = self.bracketed(1, |state, queries| {
+                    let mut columns = vec![state.column(&queries[0].columns)?];
+                    while state.accept(&Token::Symbol(",")) {
+                        columns.push(state.column(&queries[0].columns)?);
+                    }
+                    Ok(columns)
+                })?;
+                let query = query.into_iter().next().unwrap();
+                let projected = columns.iter().map(|&c| query.columns[c].clone()).collect();
+                Ok(Query {
+                    expression: Project::new(query.expression, move |row: &Row| {
+                        columns.iter().map(|&c| row[c].clone()).collect()
+                    })
+                    .into(),
+                    columns: projected,
+                })
+            }
+            Token::Keyword("join") => {
+                let (keys, mut queries) = self.bracketed(2, |state, queries| {
+                    let mut keys = Vec::new();
+                    loop {
+                        let left = state.column(&queries[0].columns)?;
+                        state.expect("=")?;
+                        let right = state.column(&queries[1].columns)?;
+                        keys.push((left, right));
+                        if !state.accept(&Token::Symbol(",")) {
+                            return Ok(keys);
+                        }
+                    }
+                })?;
+                let right = queries.pop().unwrap();
+                let left = queries.pop().unwrap();
+                let (left_keys, right_keys): (Vec<_>, Vec<_>) = keys.into_iter().unzip();
+                let mut columns = left.columns;
+                columns.extend(right.columns);
+                Ok(Query {
+                    expression: Join::new(
+                        left.expression,
+                        right.expression,
+                        move |row: &Row| left_keys.iter().map(|&c| row[c].clone()).collect::<Row>(),
+                        move |row: &Row| {
+                            right_keys.iter().map(|&c| row[c].clone()).collect::<Row>()
+                        },
+                        |_, left: &Row, right: &Row| {
+                            let mut row = left.clone();
+                            row.extend(right.iter().cloned());
+                            row
+                        },
+                    )
+                    .into(),
+                    columns,
+                })
+            }
+            Token::Symbol("(") => {
+                let query = self.query()?;
+                self.expect(")")?;
+                Ok(query)
+            }
+            token => {
+                self.next -= 1;
+                Err(self.unexpected(token))
+            }
+        }
+    }
+
+    /// Parses `"[" arguments "]" "(" query ("," query)* ")"` with `count` queries, where
+    /// `arguments` are parsed by `parse` after the queries, once the columns of the queries
+    /// are known.
+    fn bracketed<A>(
+        &mut self,
+        count: usize,
+        parse: impl FnOnce(&mut Self, &[Query]) -> Result<A, Error>,
+    ) -> Result<(A, Vec<Query>), Error> {
+        self.expect("[")?;
+        let start = self.next;
+        while self.peek() != Some(Token::Symbol("]")) {
+            self.advance()?;
+        }
+        let end = self.next;
+        self.next += 1;
+
+        self.expect("(")?;
+        let mut queries = vec![self.query()?];
+        for _ in 1..count {
+            self.expect(",")?;
+            queries.push(self.query()?);
+        }
+        self.expect(")")?;
+
+        let resume = self.next;
+        self.next = start;
+        let arguments = parse(self, &queries)?;
+        if self.next != end {
+            return Err(self.unexpected(self.peek().unwrap()));
+        }
+        self.next = resume;
+        Ok((arguments, queries))
+    }
+
+    fn condition(&mut self, columns: &[Column]) -> Result<Condition, Error> {
+        let mut left = self.conjunct(columns)?;
+        while self.accept(&Token::Keyword("or")) {
+            left = Condition::Or(Box::new(left), Box::new(self.conjunct(columns)?));
+        }
+        Ok(left)
+    }
+
+    fn conjunct(&mut self, columns: &[Column]) -> Result<Condition, Error> {
+        let mut left = self.negation(columns)?;
+        while self.accept(&Token::Keyword("and")) {
+            left = Condition::And(Box::new(left), Box::new(self.negation(columns)?));
+        }
+        Ok(left)
+    }
+
+    fn negation(&mut self, columns: &[Column]) -> Result<Condition, Error> {
+        if self.accept(&Token::Keyword("not")) {
+            Ok(Condition::Not(Box::new(self.negation(columns)?)))
+        } else if self.accept(&Token::Symbol("(")) {
+            let condition = self.condition(columns)?;
+            self.expect(")")?;
+            Ok(condition)
+        } else {
+            let left = self.operand(columns)?;
+            let position = self.position();
+            let comparison = match self.advance()? {
+                Token::Symbol(s) if ["=", "!=", "<>", "<", "<=", ">", ">="].contains(&s) => s,
+                _ => return Err(error(position, "expecting a comparison")),
+            };
+            let right = self.operand(columns)?;
+            Ok(Condition::Compare(left, comparison, right))
+        }
+    }
+
+    fn operand(&mut self, columns: &[Column]) -> Result<Operand, Error> {
+        let literal = match self.peek() {
+            Some(Token::Int(value)) => Value::Int(value),
+            Some(Token::Float(value)) => Value::Float(value),
+            Some(Token::Keyword("null")) => Value::Null,
+            Some(Token::Str(value)) => Value::Str(value),
+            Some(Token::Keyword("true")) => Value::Bool(true),
+            Some(Token::Keyword("false")) => Value::Bool(false),
+            _ => return self.column(columns).map(Operand::Column),
+        };
+        self.next += 1;
+        Ok(Operand::Literal(literal))
+    }
+
+    /// Parses a column and returns its position in `columns`.
+    fn column(&mut self, columns: &[Column]) -> Result<usize, Error> {
+        let position = self.position();
+        let (relation, name) = match self.advance()? {
+            Token::Position(p) if p < columns.len() => return Ok(p),
+            Token::Position(p) => {
+                return Err(error(position, format!("no column at position #{}", p)))
+            }
+            Token::Ident(name) => {
+                if self.accept(&Token::Symbol(".")) {
+                    match self.advance()? {
+                        Token::Ident(column) => (Some(name), column),
+                        _ => return Err(error(position, "expecting a column name")),
+                    }
+                } else {
+                    (None, name)
+                }
+            }
+            token => {
+                self.next -= 1;
+                return Err(self.unexpected(token));
+            }
+        };
+        let mut found = columns
+            .iter()
+            .enumerate()
+            .filter(|(_, c)| c.name == name && relation.as_ref().is_none_or(|r| *r == c.relation));
+        let qualified = match &relation {
+            Some(relation) => format!("{}.{}", relation, name),
+            None => name.clone(),
+        };
+        match (found.next(), found.next()) {
+            (Some((p, _)), None) => Ok(p),
+            (None, _) => Err(error(position, format!("unknown column `{}`", qualified))),
+            _ => Err(error(position, format!("ambiguous column `{}`", qualified))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RowSchema, ValueType};
+
+    fn row(values: &[i64]) -> Row {
+        values.iter().map(|&v| Value::Int(v)).collect()
+    }
+
+    fn setup() -> (Database, Parser) {
+        let mut database = Database::new();
+        let r = database.add_relation("r").unwrap();
+        let s = database.add_relation("s").unwrap();
+        database
+            .insert(&r, vec![row(&[1, 10]), row(&[2, 20]), row(&[3, 30])].into())
+            .unwrap();
+        database
+            .insert(
+                &s,
+                vec![row(&[1, 100]), row(&[3, 300]), row(&[3, 30])].into(),
+            )
+            .unwrap();
+
+        let mut parser = Parser::new();
+        parser.add_relation(&r, &["a", "b"]);
+        parser.add_relation(&s, &["a", "c"]);
+        (database, parser)
+    }
+
+    fn evaluate(database: &Database, parser: &Parser, text: &str) -> Vec<Row> {
+        let query = parser.parse(text).unwrap();
+        database.evaluate(query.expression()).unwrap().into_tuples()
+    }
+
+    #[test]
+    fn test_parse_select() {
+        let (database, parser) = setup();
+        assert_eq!(
+            vec![row(&[2, 20]), row(&[3, 30])],
+            evaluate(&database, &parser, "select[#0 > 1](r)")
+        );
+        assert_eq!(
+            vec![row(&[1, 10]), row(&[3, 30])],
+            evaluate(
+                &database,
+                &parser,
+                "SELECT[not (a = 2) and (b < 15 or b >= 30)](r)"
+            )
+        );
+        assert_eq!(
+            vec![row(&[2, 20])],
+            evaluate(&database, &parser, "select[a != 1](select[b <> 30](r))")
+        );
+    }
+
+    #[test]
+    fn test_parse_literals() {
+        let mut database = Database::new();
+        let schema = RowSchema::new()
+            .with_column("name", ValueType::Str)
+            .with_column("price", ValueType::Float);
+        let items = database.add_relation_with_schema("items", schema).unwrap();
+        database
+            .insert(
+                &items,
+                vec![
+                    vec![Value::from("apple"), Value::from(0.5)],
+                    vec![Value::from("lemon"), Value::Null],
+                    vec![Value::from("melon"), Value::from(3.25)],
+                ]
+                .into(),
+            )
+            .unwrap();
+
+        let parser = Parser::from_database(&database);
+        assert_eq!(
+            vec![vec![Value::from("lemon")]],
+            evaluate(
+                &database,
+                &parser,
+                "project[name](select[price = null](items))"
+            )
+        );
+        assert_eq!(
+            vec![vec![Value::from("melon")]],
+            evaluate(
+                &database,
+                &parser,
+                "project[name](select[price > 1.0 and name != 'apple'](items))"
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_project_join() {
+        let (database, parser) = setup();
+        let query = parser.parse("join[a = a](r, s)").unwrap();
+        assert_eq!(vec!["a", "b", "a", "c"], query.columns());
+        assert_eq!(
+            vec![
+                row(&[1, 10, 1, 100]),
+                row(&[3, 30, 3, 30]),
+                row(&[3, 30, 3, 300])
+            ],
+            database.evaluate(query.expression()).unwrap().into_tuples()
+        );
+        assert_eq!(
+            vec![row(&[30, 3])],
+            evaluate(
+                &database,
+                &parser,
+                "project[b, s.a](join[a = a, b = c](r, s))"
+            )
+        );
+        assert_eq!(
+            vec![row(&[100]), row(&[300])],
+            evaluate(
+                &database,
+                &parser,
+                "project[#3](select[c > b](join[a = a](r, s)))"
+            )
+        );
+    }
+
+    #[test]
+    fn test_parse_set_operations() {
+        let (database, parser) = setup();
+        assert_eq!(
+            vec![
+                row(&[1, 10]),
+                row(&[1, 100]),
+                row(&[2, 20]),
+                row(&[3, 30]),
+                row(&[3, 300])
+            ],
+            evaluate(&database, &parser, "r union s")
+        );
+        assert_eq!(
+            vec![row(&[3, 30])],
+            evaluate(&database, &parser, "r intersect s")
+        );
+        assert_eq!(
+            vec![row(&[1, 10])],
+            evaluate(&database, &parser, "r minus s minus select[a = 2](r)")
+        );
+        assert_eq!(
+            vec![row(&[1]), row(&[2]), row(&[3])],
+            evaluate(&database, &parser, "(project[a](r)) union project[a](s)")
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let (_, parser) = setup();
+        let position = |text| match parser.parse(text) {
+            Err(Error::Parse { position, .. }) => position,
+            _ => panic!("expecting a parse error"),
+        };
+        assert_eq!(0, position("t"));
+        assert_eq!(7, position("select[d = 1](r)"));
+        assert_eq!(7, position("select[#2 = 1](r)"));
+        assert_eq!(8, position("project[a](join[a = a](r, s))"));
+        assert_eq!(2, position("r union project[a](s)"));
+        assert_eq!(11, position("select[a = ](r)"));
+        assert_eq!(9, position("select[a 1](r)"));
+        assert_eq!(2, position("r s"));
+        assert_eq!(8, position("project["));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::Error;
+
+/// Is a token of the query language.
+#[derive(PartialEq, Clone, Debug)]
+pub(super) enum Token {
+    /// Is an identifier, naming a relation or a column.
+    Ident(String),
+
+    /// Is a keyword, e.g., `select`, in lowercase.
+    Keyword(&'static str),
+
+    /// Is a column referred to by its position, e.g., `#0`.
+    Position(usize),
+
+    /// Is an integer literal.
+    Int(i64),
+
+    /// Is a floating point literal.
+    Float(f64),
+
+    /// Is a string literal.
+    Str(String),
+
+    /// Is a punctuation or comparison symbol.
+    Symbol(&'static str),
+}
+
+const KEYWORDS: &[&str] = &[
+    "select",
+    "project",
+    "join",
+    "union",
+    "intersect",
+    "minus",
+    "and",
+    "or",
+    "not",
+    "true",
+    "false",
+    "null",
+];
+
+const SYMBOLS: &[&str] = &[
+    "<=", ">=", "!=", "<>", "=", "<", ">", "(", ")", "[", "]", ",", ".",
+];
+
+/// Returns an error for `message` at `position` of the query text.
+pub(super) fn error(position: usize, message: impl Into<String>) -> Error {
+    Error::Parse {
+        position,
+        message: message.into(),
+    }
+}
+
+/// Splits `text` into tokens, each paired with its position in `text`.
+pub(super) fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut word = String::new();
+            while let Some(&(_, c)) = chars
+                .peek()
+                .filter(|(_, c)| c.is_ascii_alphanumeric() || *c == '_')
+            {
+                word.push(c);
+                chars.next();
+            }
+            let lower = word.to_lowercase();
+            match KEYWORDS.iter().find(|k| **k == lower) {
+                Some(keyword) => tokens.push((start, Token::Keyword(keyword))),
+                None => tokens.push((start, Token::Ident(word))),
+            }
+        } else if c.is_ascii_digit() || c == '-' || c == '#' {
+            chars.next();
+            let mut digits = String::new();
+            if c != '#' {
+                digits.push(c);
+            }
+            let mut float = false;
+            while let Some(&(_, c)) = chars
+                .peek()
+                .filter(|(_, d)| d.is_ascii_digit() || (*d == '.' && c != '#' && !float))
+            {
+                float = float || c == '.';
+                digits.push(c);
+                chars.next();
+            }
+            let invalid = || error(start, format!("invalid number `{}`", digits));
+            let token = if c == '#' {
+                digits.parse().map(Token::Position).map_err(|_| invalid())?
+            } else if float {
+                digits.parse().map(Token::Float).map_err(|_| invalid())?
+            } else {
+                digits.parse().map(Token::Int).map_err(|_| invalid())?
+            };
+            tokens.push((start, token));
+        } else if c == '\'' || c == '"' {
+            chars.next();
+            let mut string = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, q)) if q == c => break,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, escaped)) => string.push(escaped),
+                        None => return Err(error(start, "unterminated string")),
+                    },
+                    Some((_, x)) => string.push(x),
+                    None => return Err(error(start, "unterminated string")),
+                }
+            }
+            tokens.push((start, Token::Str(string)));
+        } else {
+            let rest = &text[start..];
+            let symbol = SYMBOLS
+                .iter()
+                .find(|s| rest.starts_with(**s))
+                .ok_or_else(|| error(start, format!("unexpected character `{}`", c)))?;
+            for _ in 0..symbol.len() {
+                chars.next();
+            }
+            tokens.push((start, Token::Symbol(symbol)));
+        }
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            vec![
+                (0, Token::Keyword("select")),
+                (6, Token::Symbol("[")),
+                (7, Token::Position(0)),
+                (10, Token::Symbol(">=")),
+                (13, Token::Int(-2)),
+                (16, Token::Keyword("and")),
+                (20, Token::Ident("name".into())),
+                (25, Token::Symbol("<>")),
+                (28, Token::Str("it's".into())),
+                (35, Token::Symbol("]")),
+                (36, Token::Symbol("(")),
+                (37, Token::Ident("r".into())),
+                (38, Token::Symbol(")")),
+            ],
+            tokenize("SELECT[#0 >= -2 and name <> 'it\\'s'](r)").unwrap()
+        );
+        assert!(matches!(
+            tokenize("r ; s"),
+            Err(Error::Parse { position: 2, .. })
+        ));
+        assert_eq!(
+            vec![(0, Token::Float(-1.5)), (5, Token::Keyword("null"))],
+            tokenize("-1.5 NULL").unwrap()
+        );
+        assert!(tokenize("'open").is_err());
+        assert!(tokenize("- 1").is_err());
+    }
+}
@@ -0,0 +1,45 @@
+/*! Implements the primitives for sharing databases between threads when the `sync`
+feature is enabled.
+*/
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+/// Is a wrapper around `RwLock` with an interface similar to `RefCell`, replacing
+/// `RefCell` in the instances of a database that is shared between threads.
+///
+/// **Note**: unlike `RefCell`, `Lock` blocks (instead of panicking) on borrows that
+/// conflict with the borrows of other threads; therefore, operations on a database are
+/// serialized by the database. A conflicting borrow on the same thread deadlocks where
+/// `RefCell` panics, for example, when a `Select` predicate calls `Database::evaluate`
+/// on the database in which it is evaluated.
+#[derive(Debug, Default)]
+pub(crate) struct Lock<T>(RwLock<T>);
+
+impl<T> Lock<T> {
+    /// Creates a new `Lock` containing `value`.
+    pub fn new(value: T) -> Self {
+        Self(RwLock::new(value))
+    }
+
+    /// Immutably borrows the wrapped value.
+    #[inline(always)]
+    pub fn borrow(&self) -> RwLockReadGuard<'_, T> {
+        self.0.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Mutably borrows the wrapped value.
+    #[inline(always)]
+    pub fn borrow_mut(&self) -> RwLockWriteGuard<'_, T> {
+        self.0.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Replaces the wrapped value with `value` and returns the old value.
+    pub fn replace(&self, value: T) -> T {
+        std::mem::replace(&mut *self.borrow_mut(), value)
+    }
+}
+
+impl<T: PartialEq> PartialEq for Lock<T> {
+    fn eq(&self, other: &Self) -> bool {
+        *self.borrow() == *other.borrow()
+    }
+}
@@ -0,0 +1,340 @@
+/*! Implements dynamically typed tuples for relations whose columns are only known at
+runtime.
+*/
+use crate::Error;
+use std::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+};
+
+/// Is a dynamically typed value in a column of a [`Row`].
+///
+/// **Note**: values are totally ordered, so they can be stored in relations. Values of
+/// different types are ordered by their types (`Null < Bool < Int < Float < Str`), and
+/// floats are ordered by `f64::total_cmp` (e.g., `-0.0 < 0.0` and `NaN` is equal to
+/// itself).
+///
+/// [`Row`]: ./type.Row.html
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Value {
+    /// Is the missing value.
+    Null,
+
+    /// Is a boolean value.
+    Bool(bool),
+
+    /// Is an integer value.
+    Int(i64),
+
+    /// Is a floating point value.
+    Float(f64),
+
+    /// Is a string value.
+    Str(String),
+}
+
+impl Value {
+    /// Returns the type of the receiver or `None` if the receiver is `Null`.
+    pub fn value_type(&self) -> Option<ValueType> {
+        match self {
+            Self::Null => None,
+            Self::Bool(_) => Some(ValueType::Bool),
+            Self::Int(_) => Some(ValueType::Int),
+            Self::Float(_) => Some(ValueType::Float),
+            Self::Str(_) => Some(ValueType::Str),
+        }
+    }
+
+    /// Returns the rank of the type of the receiver in the order of values.
+    fn rank(&self) -> u8 {
+        match self {
+            Self::Null => 0,
+            Self::Bool(_) => 1,
+            Self::Int(_) => 2,
+            Self::Float(_) => 3,
+            Self::Str(_) => 4,
+        }
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value {}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Bool(left), Self::Bool(right)) => left.cmp(right),
+            (Self::Int(left), Self::Int(right)) => left.cmp(right),
+            (Self::Float(left), Self::Float(right)) => left.total_cmp(right),
+            (Self::Str(left), Self::Str(right)) => left.cmp(right),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.rank().hash(state);
+        match self {
+            Self::Null => {}
+            Self::Bool(value) => value.hash(state),
+            Self::Int(value) => value.hash(state),
+            Self::Float(value) => value.to_bits().hash(state),
+            Self::Str(value) => value.hash(state),
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::Str(value.into())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::Str(value)
+    }
+}
+
+impl<V: Into<Value>> From<Option<V>> for Value {
+    fn from(value: Option<V>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Null => write!(f, "null"),
+            Self::Bool(value) => write!(f, "{}", value),
+            Self::Int(value) => write!(f, "{}", value),
+            Self::Float(value) => write!(f, "{:?}", value),
+            Self::Str(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+/// Is a dynamically typed tuple, containing the values of its columns by their positions.
+pub type Row = Vec<Value>;
+
+/// Is the type of the values in a column of a [`RowSchema`].
+///
+/// [`RowSchema`]: ./struct.RowSchema.html
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum ValueType {
+    Bool,
+    Int,
+    Float,
+    Str,
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool => write!(f, "bool"),
+            Self::Int => write!(f, "int"),
+            Self::Float => write!(f, "float"),
+            Self::Str => write!(f, "str"),
+        }
+    }
+}
+
+/// Describes the [`Row`]s of a relation that is added by [`Database::add_relation_with_schema`]
+/// by the names and the types of their columns. The columns that are not key columns may
+/// contain `Null` values. Rows that don't match the schema of their relation are rejected
+/// on insertion.
+///
+/// **Example**:
+/// ```rust
+/// use codd::{RowSchema, ValueType};
+///
+/// let schema = RowSchema::new()
+///     .with_key_column("id", ValueType::Int)
+///     .with_column("name", ValueType::Str)
+///     .with_column("price", ValueType::Float);
+///
+/// assert_eq!(vec!["id", "name", "price"], schema.names());
+/// assert_eq!(Some(2), schema.position("price"));
+/// assert_eq!(&[0], schema.keys());
+/// ```
+///
+/// [`Row`]: ./type.Row.html
+/// [`Database::add_relation_with_schema`]: ./struct.Database.html#method.add_relation_with_schema
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+pub struct RowSchema {
+    columns: Vec<(String, ValueType)>,
+    keys: Vec<usize>,
+}
+
+impl RowSchema {
+    /// Creates a new schema with no columns.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the receiver with a new column `name` with values of type `value_type`.
+    pub fn with_column(mut self, name: &str, value_type: ValueType) -> Self {
+        self.columns.push((name.into(), value_type));
+        self
+    }
+
+    /// Returns the receiver with a new key column `name` with values of type `value_type`.
+    pub fn with_key_column(mut self, name: &str, value_type: ValueType) -> Self {
+        self.keys.push(self.columns.len());
+        self.with_column(name, value_type)
+    }
+
+    /// Returns the names and the types of the columns of the schema.
+    #[inline(always)]
+    pub fn columns(&self) -> &[(String, ValueType)] {
+        &self.columns
+    }
+
+    /// Returns the names of the columns of the schema.
+    pub fn names(&self) -> Vec<&str> {
+        self.columns.iter().map(|(name, _)| name.as_str()).collect()
+    }
+
+    /// Returns the position of the column `name` if it exists.
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|(n, _)| n == name)
+    }
+
+    /// Returns the positions of the key columns of the schema.
+    #[inline(always)]
+    pub fn keys(&self) -> &[usize] {
+        &self.keys
+    }
+
+    /// Returns an error if `row` of the relation `relation` doesn't match the receiver.
+    pub(crate) fn validate(&self, relation: &str, row: &Row) -> Result<(), Error> {
+        if row.len() != self.columns.len() {
+            return Err(Error::ArityMismatch {
+                relation: relation.into(),
+                expected: self.columns.len(),
+                found: row.len(),
+            });
+        }
+        for (position, ((name, expected), value)) in self.columns.iter().zip(row).enumerate() {
+            match value.value_type() {
+                None if self.keys.contains(&position) => {
+                    return Err(Error::NullKey {
+                        relation: relation.into(),
+                        column: name.clone(),
+                    })
+                }
+                Some(found) if found != *expected => {
+                    return Err(Error::TypeMismatch {
+                        relation: relation.into(),
+                        column: name.clone(),
+                        expected: *expected,
+                        found,
+                    })
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_order() {
+        let mut values = vec![
+            Value::from("a"),
+            Value::from(2.5),
+            Value::from(f64::NAN),
+            Value::from(-0.0),
+            Value::from(0.0),
+            Value::from(3),
+            Value::from(true),
+            Value::Null,
+        ];
+        values.sort();
+        assert_eq!(
+            vec![
+                Value::Null,
+                Value::from(true),
+                Value::from(3),
+                Value::from(-0.0),
+                Value::from(0.0),
+                Value::from(2.5),
+                Value::from(f64::NAN),
+                Value::from("a"),
+            ],
+            values
+        );
+        assert_eq!(Value::from(f64::NAN), Value::from(f64::NAN));
+        assert_ne!(Value::from(1), Value::from(1.0));
+        assert_eq!(Value::Null, Value::from(None::<i64>));
+    }
+
+    #[test]
+    fn test_validate() {
+        let schema = RowSchema::new()
+            .with_key_column("id", ValueType::Int)
+            .with_column("name", ValueType::Str);
+        assert!(schema
+            .validate("r", &vec![Value::from(1), Value::from("a")])
+            .is_ok());
+        assert!(schema
+            .validate("r", &vec![Value::from(1), Value::Null])
+            .is_ok());
+        assert!(matches!(
+            schema.validate("r", &vec![Value::from(1)]),
+            Err(Error::ArityMismatch {
+                expected: 2,
+                found: 1,
+                ..
+            })
+        ));
+        assert!(matches!(
+            schema.validate("r", &vec![Value::from(1), Value::from(2)]),
+            Err(Error::TypeMismatch {
+                expected: ValueType::Str,
+                found: ValueType::Int,
+                ..
+            })
+        ));
+        assert!(matches!(
+            schema.validate("r", &vec![Value::Null, Value::from("a")]),
+            Err(Error::NullKey { .. })
+        ));
+    }
+}